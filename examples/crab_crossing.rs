@@ -12,7 +12,7 @@ fn main() {
             primary_window: Some(switch_window("Crab Crossing")),
             ..default()
         }))
-        .add_plugins(SwitchPlugin)
+        .add_plugins(SwitchPlugin::default())
         .insert_resource(ClearColor(Color::srgb(0.1, 0.1, 0.15)))
         .add_systems(Startup, setup)
         .add_systems(Update, (move_player, rotate_cube))
@@ -40,6 +40,7 @@ struct Enemy;
 #[derive(Resource, Default)]
 struct GameState {
     frame_count: u64,
+    #[allow(dead_code)] // Not scored yet in this demo
     player_score: u32,
 }
 
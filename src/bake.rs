@@ -0,0 +1,680 @@
+//! Pre-baking assets for Switch targets.
+//!
+//! Desktop-sized textures and audio blow the console's memory budget. A
+//! manifest in the game repo, `switch-assets.toml`, sets limits for each
+//! [`Platform`] and byte budgets for asset directories:
+//!
+//! ```toml
+//! [defaults]
+//! max_texture_size = 2048
+//!
+//! [platforms.switch_handheld]
+//! max_texture_size = 1024
+//! audio_sample_rate = 32000
+//! audio_mono = true
+//!
+//! [budgets]
+//! "." = "64 MiB"
+//! sprites = "16 MiB"
+//! "audio/music" = "8 MiB"
+//! ```
+//!
+//! PNG, JPEG and BMP textures larger than `max_texture_size` are downsized,
+//! keeping their aspect ratio and format. WAV audio above `audio_sample_rate`
+//! is resampled, mixed down to mono with `audio_mono`, and stored as 16-bit
+//! PCM. Other files are copied as they are; textures and audio in formats
+//! that cannot be baked, such as KTX2 or Ogg Vorbis, are listed in the
+//! [`BudgetReport`].
+//!
+//! Bake with Bevy's asset processor through [`SwitchBakePlugin`], or ahead of
+//! time with `cargo switchbrew bake`, which also prints a [`BudgetReport`].
+
+use core::fmt;
+use core::marker::PhantomData;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use bevy::asset::io::{AssetWriterError, Writer};
+use bevy::asset::meta::AssetMeta;
+use bevy::asset::processor::{Process, ProcessContext, ProcessError};
+use bevy::asset::{AssetLoader, AssetPath, AsyncWriteExt};
+use bevy::image::{ImageLoader, ImageLoaderSettings};
+use bevy::prelude::*;
+use image::ImageFormat;
+use serde::de::{self, Deserializer, Visitor};
+use serde::Deserialize;
+
+use crate::platform::{Platform, PlatformInfo};
+
+mod wav;
+
+/// Manifest file read by `cargo switchbrew bake`.
+pub const MANIFEST_FILE: &str = "switch-assets.toml";
+
+/// Limits applied when baking assets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BakeSettings {
+    /// Largest texture width or height in pixels.
+    pub max_texture_size: Option<u32>,
+    /// Highest audio sample rate in Hz.
+    pub audio_sample_rate: Option<u32>,
+    /// Mix audio down to mono.
+    pub audio_mono: Option<bool>,
+}
+
+impl BakeSettings {
+    /// Replace these settings with the ones set in `other`.
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            max_texture_size: other.max_texture_size.or(self.max_texture_size),
+            audio_sample_rate: other.audio_sample_rate.or(self.audio_sample_rate),
+            audio_mono: other.audio_mono.or(self.audio_mono),
+        }
+    }
+}
+
+/// Asset baking manifest, usually [`MANIFEST_FILE`] in the game repo.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AssetManifest {
+    /// Settings for every platform.
+    pub defaults: BakeSettings,
+    /// Settings for a platform, on top of the defaults.
+    pub platforms: BTreeMap<Platform, BakeSettings>,
+    /// Byte budget of each directory, `.` being the whole asset directory.
+    pub budgets: BTreeMap<String, ByteSize>,
+}
+
+impl AssetManifest {
+    /// Parse a manifest.
+    pub fn from_toml(text: &str) -> Result<Self, BakeError> {
+        toml::from_str(text).map_err(|error| BakeError::Manifest(error.to_string()))
+    }
+
+    /// Read a manifest file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BakeError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|source| BakeError::Io {
+            path: path.into(),
+            source,
+        })?;
+        toml::from_str(&text)
+            .map_err(|error| BakeError::Manifest(format!("{}: {error}", path.display())))
+    }
+
+    /// Get the settings for a platform.
+    pub fn settings_for(&self, platform: Platform) -> BakeSettings {
+        let platform = self.platforms.get(&platform).copied().unwrap_or_default();
+        self.defaults.merge(platform)
+    }
+}
+
+/// Size in bytes, written in a manifest as a number of bytes or as a
+/// string like `"16 MiB"`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ByteSize(pub u64);
+
+impl ByteSize {
+    /// Parse a size like `"512"`, `"1.5 KiB"`, `"16 MiB"` or `"1 GiB"`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let split = text
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(text.len());
+        let (number, unit) = text.split_at(split);
+        let scale = match unit.trim() {
+            "" | "B" => 1,
+            "KiB" => 1 << 10,
+            "MiB" => 1 << 20,
+            "GiB" => 1 << 30,
+            _ => return None,
+        };
+        let number: f64 = number.parse().ok()?;
+        Some(Self((number * scale as f64).round() as u64))
+    }
+}
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            bytes if bytes >= 1 << 30 => write!(f, "{:.1} GiB", bytes as f64 / (1u64 << 30) as f64),
+            bytes if bytes >= 1 << 20 => write!(f, "{:.1} MiB", bytes as f64 / (1u64 << 20) as f64),
+            bytes if bytes >= 1 << 10 => write!(f, "{:.1} KiB", bytes as f64 / (1u64 << 10) as f64),
+            bytes => write!(f, "{bytes} B"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteSize {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SizeVisitor;
+
+        impl Visitor<'_> for SizeVisitor {
+            type Value = ByteSize;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a number of bytes or a size like \"16 MiB\"")
+            }
+
+            fn visit_u64<E: de::Error>(self, bytes: u64) -> Result<ByteSize, E> {
+                Ok(ByteSize(bytes))
+            }
+
+            fn visit_i64<E: de::Error>(self, bytes: i64) -> Result<ByteSize, E> {
+                u64::try_from(bytes)
+                    .map(ByteSize)
+                    .map_err(|_| E::invalid_value(de::Unexpected::Signed(bytes), &self))
+            }
+
+            fn visit_str<E: de::Error>(self, text: &str) -> Result<ByteSize, E> {
+                ByteSize::parse(text)
+                    .ok_or_else(|| E::invalid_value(de::Unexpected::Str(text), &self))
+            }
+        }
+
+        deserializer.deserialize_any(SizeVisitor)
+    }
+}
+
+/// Kind of asset, from its file extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetKind {
+    /// PNG, JPEG or BMP image
+    Texture,
+    /// WAV sound
+    Audio,
+    /// Texture or sound in a format that cannot be baked, copied as it is
+    Unsupported,
+    /// Anything else, copied as it is
+    Other,
+}
+
+impl AssetKind {
+    /// Get the kind of an asset file.
+    pub fn of(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match extension.as_str() {
+            "png" | "jpg" | "jpeg" | "bmp" => AssetKind::Texture,
+            "wav" => AssetKind::Audio,
+            "tga" | "dds" | "ktx2" | "basis" | "ogg" | "oga" | "mp3" | "flac" => {
+                AssetKind::Unsupported
+            }
+            _ => AssetKind::Other,
+        }
+    }
+}
+
+/// Bake an asset by its kind, returning the bytes unchanged if there is
+/// nothing to do.
+pub fn bake_asset<'a>(
+    path: &Path,
+    bytes: &'a [u8],
+    settings: &BakeSettings,
+) -> Result<Cow<'a, [u8]>, BakeError> {
+    match AssetKind::of(path) {
+        AssetKind::Texture => bake_texture(bytes, settings),
+        AssetKind::Audio => bake_audio(bytes, settings),
+        AssetKind::Unsupported | AssetKind::Other => Ok(Cow::Borrowed(bytes)),
+    }
+}
+
+/// Downsize a PNG, JPEG or BMP texture to fit
+/// [`BakeSettings::max_texture_size`], re-encoding it in the same format.
+///
+/// Other image formats are returned unchanged.
+pub fn bake_texture<'a>(
+    bytes: &'a [u8],
+    settings: &BakeSettings,
+) -> Result<Cow<'a, [u8]>, BakeError> {
+    let Some(max) = settings.max_texture_size else {
+        return Ok(Cow::Borrowed(bytes));
+    };
+    let Some(format) = texture_format(bytes) else {
+        return Ok(Cow::Borrowed(bytes));
+    };
+    let texture = image::load_from_memory_with_format(bytes, format)
+        .map_err(|error| BakeError::Texture(error.to_string()))?;
+    if texture.width() <= max && texture.height() <= max {
+        return Ok(Cow::Borrowed(bytes));
+    }
+
+    let texture = texture.resize(max, max, image::imageops::FilterType::Triangle);
+    let mut baked = Vec::new();
+    texture
+        .write_to(&mut Cursor::new(&mut baked), format)
+        .map_err(|error| BakeError::Texture(error.to_string()))?;
+    Ok(Cow::Owned(baked))
+}
+
+/// Get the format of a texture that can be baked from its magic bytes.
+fn texture_format(bytes: &[u8]) -> Option<ImageFormat> {
+    match bytes {
+        [0x89, b'P', b'N', b'G', ..] => Some(ImageFormat::Png),
+        [0xff, 0xd8, 0xff, ..] => Some(ImageFormat::Jpeg),
+        [b'B', b'M', ..] => Some(ImageFormat::Bmp),
+        _ => None,
+    }
+}
+
+/// Re-encode WAV audio as 16-bit PCM at [`BakeSettings::audio_sample_rate`],
+/// mixed down to mono with [`BakeSettings::audio_mono`].
+///
+/// Other audio formats are returned unchanged, as is audio that already
+/// fits the settings.
+pub fn bake_audio<'a>(
+    bytes: &'a [u8],
+    settings: &BakeSettings,
+) -> Result<Cow<'a, [u8]>, BakeError> {
+    if !wav::is_wav(bytes) {
+        return Ok(Cow::Borrowed(bytes));
+    }
+    let mut audio = wav::decode(bytes).map_err(BakeError::Audio)?;
+    let mono = settings.audio_mono.unwrap_or(false) && audio.channels > 1;
+    let rate = settings
+        .audio_sample_rate
+        .filter(|&rate| rate > 0 && rate < audio.sample_rate);
+    if !mono && rate.is_none() && audio.bits <= 16 {
+        return Ok(Cow::Borrowed(bytes));
+    }
+
+    if mono {
+        audio.mix_to_mono();
+    }
+    if let Some(rate) = rate {
+        audio.downsample(rate);
+    }
+    Ok(Cow::Owned(audio.encode()))
+}
+
+/// Bake every file under `source` into `out` for a platform, keeping their
+/// relative paths, and report the size of each directory and the files that
+/// could not be baked.
+pub fn bake_dir(
+    source: &Path,
+    out: &Path,
+    manifest: &AssetManifest,
+    platform: Platform,
+) -> Result<BudgetReport, BakeError> {
+    let settings = manifest.settings_for(platform);
+    let mut report = BudgetReport::new(platform, manifest);
+    let io_error = |path: &Path| {
+        let path = path.to_path_buf();
+        move |source| BakeError::Io { path, source }
+    };
+
+    let mut pending = vec![PathBuf::new()];
+    while let Some(dir) = pending.pop() {
+        let mut entries: Vec<_> = std::fs::read_dir(source.join(&dir))
+            .and_then(|entries| entries.collect::<Result<_, _>>())
+            .map_err(io_error(&source.join(&dir)))?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let relative = dir.join(entry.file_name());
+            if entry.file_type().map_err(io_error(&entry.path()))?.is_dir() {
+                pending.push(relative);
+                continue;
+            }
+            let bytes = std::fs::read(entry.path()).map_err(io_error(&entry.path()))?;
+            let baked = bake_asset(&relative, &bytes, &settings)?;
+            let target = out.join(&relative);
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent).map_err(io_error(parent))?;
+            }
+            std::fs::write(&target, &baked).map_err(io_error(&target))?;
+            report.add(&relative, bytes.len() as u64, baked.len() as u64);
+            if AssetKind::of(&relative) == AssetKind::Unsupported {
+                let names: Vec<_> = relative
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect();
+                report.unbaked.push(names.join("/"));
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Asset sizes per directory after baking for a platform.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetReport {
+    /// Platform the assets were baked for.
+    pub platform: Platform,
+    /// Usage of each directory, sorted by path, `.` being the whole asset
+    /// directory. Sizes include subdirectories.
+    pub dirs: Vec<DirUsage>,
+    /// Textures and audio copied as they are because their format cannot be
+    /// baked, `/`-separated.
+    pub unbaked: Vec<String>,
+}
+
+/// Asset size of a directory, including its subdirectories.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirUsage {
+    /// Path relative to the asset directory, `/`-separated.
+    pub dir: String,
+    /// Number of files.
+    pub files: usize,
+    /// Size before baking.
+    pub source_bytes: u64,
+    /// Size after baking.
+    pub baked_bytes: u64,
+    /// Budget from the manifest.
+    pub budget: Option<u64>,
+}
+
+impl DirUsage {
+    /// Whether the baked size exceeds the budget.
+    pub fn over_budget(&self) -> bool {
+        self.budget.is_some_and(|budget| self.baked_bytes > budget)
+    }
+}
+
+impl BudgetReport {
+    /// Create an empty report with the manifest's budgets.
+    pub fn new(platform: Platform, manifest: &AssetManifest) -> Self {
+        let mut report = Self {
+            platform,
+            dirs: Vec::new(),
+            unbaked: Vec::new(),
+        };
+        for (dir, budget) in &manifest.budgets {
+            report.dir_mut(dir.trim_matches('/')).budget = Some(budget.0);
+        }
+        report.dir_mut(".");
+        report
+    }
+
+    /// Count a file toward its directory and each parent.
+    pub fn add(&mut self, path: &Path, source_bytes: u64, baked_bytes: u64) {
+        let mut dir = String::from(".");
+        let parents: Vec<_> = path
+            .parent()
+            .into_iter()
+            .flat_map(Path::components)
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        for depth in 0..=parents.len() {
+            if depth > 0 {
+                dir = parents[..depth].join("/");
+            }
+            let usage = self.dir_mut(&dir);
+            usage.files += 1;
+            usage.source_bytes += source_bytes;
+            usage.baked_bytes += baked_bytes;
+        }
+    }
+
+    /// Get a directory's usage, if it has files or a budget.
+    pub fn dir(&self, dir: &str) -> Option<&DirUsage> {
+        self.dirs.iter().find(|usage| usage.dir == dir)
+    }
+
+    /// Directories over their budget.
+    pub fn over_budget(&self) -> impl Iterator<Item = &DirUsage> {
+        self.dirs.iter().filter(|usage| usage.over_budget())
+    }
+
+    fn dir_mut(&mut self, dir: &str) -> &mut DirUsage {
+        let dir = if dir.is_empty() { "." } else { dir };
+        let index = match self
+            .dirs
+            .binary_search_by(|usage| usage.dir.as_str().cmp(dir))
+        {
+            Ok(index) => index,
+            Err(index) => {
+                self.dirs.insert(
+                    index,
+                    DirUsage {
+                        dir: dir.into(),
+                        files: 0,
+                        source_bytes: 0,
+                        baked_bytes: 0,
+                        budget: None,
+                    },
+                );
+                index
+            }
+        };
+        &mut self.dirs[index]
+    }
+}
+
+impl fmt::Display for BudgetReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Asset budget for {}", self.platform.name())?;
+        writeln!(
+            f,
+            "{:<24} {:>6} {:>12} {:>12} {:>12}",
+            "directory", "files", "source", "baked", "budget"
+        )?;
+        for usage in &self.dirs {
+            let budget = usage
+                .budget
+                .map_or_else(|| "-".into(), |budget| ByteSize(budget).to_string());
+            write!(
+                f,
+                "{:<24} {:>6} {:>12} {:>12} {:>12}",
+                usage.dir,
+                usage.files,
+                ByteSize(usage.source_bytes).to_string(),
+                ByteSize(usage.baked_bytes).to_string(),
+                budget,
+            )?;
+            if usage.over_budget() {
+                write!(f, "  OVER BUDGET")?;
+            }
+            writeln!(f)?;
+        }
+        for path in &self.unbaked {
+            writeln!(f, "warning: {path} not baked, unsupported format")?;
+        }
+        Ok(())
+    }
+}
+
+/// Error baking assets.
+#[derive(Debug)]
+pub enum BakeError {
+    /// A file could not be read or written
+    Io {
+        /// File path.
+        path: PathBuf,
+        /// Underlying error.
+        source: std::io::Error,
+    },
+    /// The manifest is not valid TOML or does not match the manifest layout
+    Manifest(String),
+    /// A texture could not be decoded or encoded
+    Texture(String),
+    /// An audio file could not be decoded
+    Audio(String),
+}
+
+impl fmt::Display for BakeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BakeError::Io { path, source } => write!(f, "{}: {source}", path.display()),
+            BakeError::Manifest(message) => write!(f, "invalid asset manifest: {message}"),
+            BakeError::Texture(message) => write!(f, "failed to bake texture: {message}"),
+            BakeError::Audio(message) => write!(f, "failed to bake audio: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for BakeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BakeError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Plugin that bakes assets for a platform with Bevy's asset processor.
+///
+/// Registers [`BakeTexture`] as the default processor for PNG, JPEG and BMP
+/// files. Audio needs the audio loader, e.g.
+/// `with_audio_loader::<AudioLoader>()`. Processing only runs with Bevy's
+/// `asset_processor` feature and `AssetMode::Processed`. Add the plugin after
+/// `AssetPlugin`:
+///
+/// ```rust,no_run
+/// use bevy::asset::AssetMode;
+/// use bevy::prelude::*;
+/// use switchbrew_bevy::bake::{AssetManifest, SwitchBakePlugin};
+///
+/// let manifest = AssetManifest::load("switch-assets.toml").unwrap();
+/// App::new()
+///     .add_plugins(DefaultPlugins.set(AssetPlugin {
+///         mode: AssetMode::Processed,
+///         ..default()
+///     }))
+///     .add_plugins(SwitchBakePlugin::new(manifest))
+///     .run();
+/// ```
+pub struct SwitchBakePlugin {
+    manifest: AssetManifest,
+    platform: Option<Platform>,
+    audio: Option<fn(&mut App, BakeSettings)>,
+}
+
+impl SwitchBakePlugin {
+    /// Bake assets with a manifest's settings for the detected platform.
+    pub fn new(manifest: AssetManifest) -> Self {
+        Self {
+            manifest,
+            platform: None,
+            audio: None,
+        }
+    }
+
+    /// Bake for a platform instead of the detected one.
+    pub fn with_platform(mut self, platform: Platform) -> Self {
+        self.platform = Some(platform);
+        self
+    }
+
+    /// Also bake WAV audio, loaded afterwards with `L`.
+    pub fn with_audio_loader<L>(mut self) -> Self
+    where
+        L: AssetLoader,
+        L::Settings: Default,
+    {
+        self.audio = Some(|app, settings| {
+            app.register_asset_processor(BakeAudio::<L>::new(settings))
+                .set_default_asset_processor::<BakeAudio<L>>("wav");
+        });
+        self
+    }
+}
+
+impl Plugin for SwitchBakePlugin {
+    fn build(&self, app: &mut App) {
+        let platform = self.platform.unwrap_or_else(|| {
+            app.world()
+                .get_resource::<PlatformInfo>()
+                .map_or_else(Platform::detect, |info| info.platform)
+        });
+        let settings = self.manifest.settings_for(platform);
+        app.register_asset_processor(BakeTexture::new(settings));
+        for extension in ["png", "jpg", "jpeg", "bmp"] {
+            app.set_default_asset_processor::<BakeTexture>(extension);
+        }
+        if let Some(register) = self.audio {
+            register(app, settings);
+        }
+    }
+}
+
+/// Asset processor that downsizes textures, see [`bake_texture`].
+pub struct BakeTexture {
+    settings: BakeSettings,
+}
+
+impl BakeTexture {
+    /// Bake textures with some settings.
+    pub fn new(settings: BakeSettings) -> Self {
+        Self { settings }
+    }
+}
+
+impl Process for BakeTexture {
+    type Settings = ();
+    type OutputLoader = ImageLoader;
+
+    async fn process(
+        &self,
+        context: &mut ProcessContext<'_>,
+        _meta: AssetMeta<(), Self>,
+        writer: &mut Writer,
+    ) -> Result<ImageLoaderSettings, ProcessError> {
+        let baked = bake_texture(context.asset_bytes(), &self.settings).map_err(save_error)?;
+        write_baked(context.path(), writer, &baked).await?;
+        Ok(ImageLoaderSettings::default())
+    }
+}
+
+/// Asset processor that re-encodes WAV audio, see [`bake_audio`].
+///
+/// `L` loads the baked audio, e.g. Bevy's `AudioLoader`.
+pub struct BakeAudio<L> {
+    settings: BakeSettings,
+    loader: PhantomData<fn() -> L>,
+}
+
+impl<L> BakeAudio<L> {
+    /// Bake audio with some settings.
+    pub fn new(settings: BakeSettings) -> Self {
+        Self {
+            settings,
+            loader: PhantomData,
+        }
+    }
+}
+
+impl<L> Process for BakeAudio<L>
+where
+    L: AssetLoader,
+    L::Settings: Default,
+{
+    type Settings = ();
+    type OutputLoader = L;
+
+    async fn process(
+        &self,
+        context: &mut ProcessContext<'_>,
+        _meta: AssetMeta<(), Self>,
+        writer: &mut Writer,
+    ) -> Result<L::Settings, ProcessError> {
+        let baked = bake_audio(context.asset_bytes(), &self.settings).map_err(save_error)?;
+        write_baked(context.path(), writer, &baked).await?;
+        Ok(L::Settings::default())
+    }
+}
+
+// `ProcessError` is Bevy's, returned as is from `Process::process`
+#[allow(clippy::result_large_err)]
+async fn write_baked(
+    path: &AssetPath<'_>,
+    writer: &mut Writer,
+    bytes: &[u8],
+) -> Result<(), ProcessError> {
+    writer
+        .write_all(bytes)
+        .await
+        .map_err(|error| ProcessError::AssetWriterError {
+            path: path.clone_owned(),
+            err: AssetWriterError::Io(error),
+        })
+}
+
+fn save_error(error: BakeError) -> ProcessError {
+    ProcessError::AssetSaveError(Box::new(error))
+}
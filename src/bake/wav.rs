@@ -0,0 +1,209 @@
+//! Minimal WAV decoding and 16-bit PCM encoding for audio baking.
+
+use std::f64::consts::PI;
+
+/// `WAVE_FORMAT_PCM`.
+const FORMAT_PCM: u16 = 1;
+
+/// `WAVE_FORMAT_IEEE_FLOAT`.
+const FORMAT_FLOAT: u16 = 3;
+
+/// `WAVE_FORMAT_EXTENSIBLE`, with the real format in the sub-format GUID.
+const FORMAT_EXTENSIBLE: u16 = 0xfffe;
+
+/// Zero crossings of the resampling filter on each side of a sample.
+const SINC_ZERO_CROSSINGS: usize = 16;
+
+/// Resampling cutoff as a fraction of the output's Nyquist frequency.
+const LOWPASS_CUTOFF: f64 = 0.9;
+
+/// Decoded audio, samples interleaved by channel.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Wav {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub bits: u16,
+    pub samples: Vec<f32>,
+}
+
+/// Whether the bytes start like a WAV file.
+pub(super) fn is_wav(bytes: &[u8]) -> bool {
+    bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WAVE"
+}
+
+/// Decode a PCM or float WAV file.
+pub(super) fn decode(bytes: &[u8]) -> Result<Wav, String> {
+    if !is_wav(bytes) {
+        return Err("not a WAV file".into());
+    }
+    let mut format = None;
+    let mut data = None;
+    let mut rest = &bytes[12..];
+    while rest.len() >= 8 {
+        let id = &rest[..4];
+        let len = u32::from_le_bytes(rest[4..8].try_into().unwrap()) as usize;
+        let body = rest
+            .get(8..8 + len)
+            .ok_or_else(|| format!("truncated {} chunk", String::from_utf8_lossy(id)))?;
+        match id {
+            b"fmt " => format = Some(body),
+            b"data" => data = Some(body),
+            _ => {}
+        }
+        // Chunks are padded to an even size
+        rest = rest.get(8 + len + len % 2..).unwrap_or_default();
+    }
+    let format = format
+        .filter(|f| f.len() >= 16)
+        .ok_or("missing fmt chunk")?;
+    let data = data.ok_or("missing data chunk")?;
+
+    let u16_at = |offset: usize| u16::from_le_bytes([format[offset], format[offset + 1]]);
+    let mut tag = u16_at(0);
+    let channels = u16_at(2);
+    let sample_rate = u32::from_le_bytes(format[4..8].try_into().unwrap());
+    let bits = u16_at(14);
+    if tag == FORMAT_EXTENSIBLE {
+        tag = format
+            .get(24..26)
+            .map(|sub| u16::from_le_bytes([sub[0], sub[1]]))
+            .ok_or("truncated extensible fmt chunk")?;
+    }
+    if channels == 0 || sample_rate == 0 {
+        return Err("invalid fmt chunk".into());
+    }
+
+    let samples = match (tag, bits) {
+        (FORMAT_PCM, 8) => data.iter().map(|&s| (s as f32 - 128.0) / 128.0).collect(),
+        (FORMAT_PCM, 16) => data
+            .as_chunks::<2>()
+            .0
+            .iter()
+            .map(|&s| i16::from_le_bytes(s) as f32 / 32768.0)
+            .collect(),
+        (FORMAT_PCM, 24) => data
+            .as_chunks::<3>()
+            .0
+            .iter()
+            .map(|&[a, b, c]| i32::from_le_bytes([0, a, b, c]) as f32 / 2_147_483_648.0)
+            .collect(),
+        (FORMAT_PCM, 32) => data
+            .as_chunks::<4>()
+            .0
+            .iter()
+            .map(|&s| i32::from_le_bytes(s) as f32 / 2_147_483_648.0)
+            .collect(),
+        (FORMAT_FLOAT, 32) => data
+            .as_chunks::<4>()
+            .0
+            .iter()
+            .map(|&s| f32::from_le_bytes(s))
+            .collect(),
+        _ => return Err(format!("unsupported WAV format {tag} with {bits} bits")),
+    };
+    Ok(Wav {
+        channels,
+        sample_rate,
+        bits,
+        samples,
+    })
+}
+
+impl Wav {
+    /// Average all channels into one.
+    pub fn mix_to_mono(&mut self) {
+        let channels = self.channels as usize;
+        self.samples = self
+            .samples
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect();
+        self.channels = 1;
+    }
+
+    /// Resample to a lower rate.
+    ///
+    /// Each output sample is a Blackman-windowed sinc of the input, which
+    /// low-passes below the new Nyquist frequency so higher frequencies do
+    /// not alias into the audible range.
+    pub fn downsample(&mut self, sample_rate: u32) {
+        let channels = self.channels as usize;
+        let frames = self.samples.len() / channels;
+        let step = self.sample_rate as f64 / sample_rate as f64;
+        let out_frames = (frames as f64 / step).floor() as usize;
+
+        // Cutoff relative to the input's Nyquist frequency, a little below
+        // the output's so the transition band ends before it
+        let cutoff = LOWPASS_CUTOFF / step;
+        let half_width = SINC_ZERO_CROSSINGS as f64 / cutoff;
+
+        let mut samples = Vec::with_capacity(out_frames * channels);
+        let mut weights = Vec::new();
+        for frame in 0..out_frames {
+            let position = frame as f64 * step;
+            let first = (position - half_width).ceil().max(0.0) as usize;
+            let last = ((position + half_width).floor() as usize).min(frames - 1);
+
+            weights.clear();
+            weights.extend((first..=last).map(|index| {
+                let x = index as f64 - position;
+                cutoff * sinc(cutoff * x) * blackman(x / half_width)
+            }));
+            // Normalized, so the gain stays 1 near the ends of the clip
+            let total: f64 = weights.iter().sum();
+            for channel in 0..channels {
+                let sum: f64 = weights
+                    .iter()
+                    .zip(first..=last)
+                    .map(|(weight, index)| weight * self.samples[index * channels + channel] as f64)
+                    .sum();
+                samples.push((sum / total) as f32);
+            }
+        }
+        self.samples = samples;
+        self.sample_rate = sample_rate;
+    }
+
+    /// Encode as a 16-bit PCM WAV file.
+    pub fn encode(&self) -> Vec<u8> {
+        let data_len = self.samples.len() as u32 * 2;
+        let block_align = self.channels * 2;
+        let mut bytes = Vec::with_capacity(44 + data_len as usize);
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&FORMAT_PCM.to_le_bytes());
+        bytes.extend_from_slice(&self.channels.to_le_bytes());
+        bytes.extend_from_slice(&self.sample_rate.to_le_bytes());
+        bytes.extend_from_slice(&(self.sample_rate * block_align as u32).to_le_bytes());
+        bytes.extend_from_slice(&block_align.to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data_len.to_le_bytes());
+        for sample in &self.samples {
+            let sample = (sample.clamp(-1.0, 1.0) * 32767.0).round() as i16;
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+        bytes
+    }
+}
+
+/// Normalized sinc, `sin(πx) / πx`.
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        let x = PI * x;
+        x.sin() / x
+    }
+}
+
+/// Blackman window over `[-1, 1]`, zero outside it.
+fn blackman(x: f64) -> f64 {
+    if x.abs() > 1.0 {
+        return 0.0;
+    }
+    let x = PI * x;
+    0.42 + 0.5 * x.cos() + 0.08 * (2.0 * x).cos()
+}
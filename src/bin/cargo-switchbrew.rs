@@ -0,0 +1,341 @@
+//! `cargo switchbrew`: tooling for building Switch homebrew.
+//!
+//! ```text
+//! cargo switchbrew romfs <dir> <image>   Pack a directory into a RomFS image
+//! cargo switchbrew bake <platform> <dir> <out> [manifest]
+//!                                        Bake assets for a platform
+//! cargo switchbrew package <elf> <nro> [Cargo.toml]
+//!                                        Package an ELF into an NRO
+//! cargo switchbrew run <elf> [Cargo.toml] [--dry-run]
+//!                                        Package an ELF and run it in an emulator
+//! cargo switchbrew inspect <nro> [icon.jpg]
+//!                                        Show an NRO's metadata and RomFS
+//! ```
+
+use std::env;
+use std::path::Path;
+use std::process::ExitCode;
+
+#[cfg(feature = "bake")]
+use switchbrew_bevy::bake::{bake_dir, AssetManifest, MANIFEST_FILE};
+#[cfg(feature = "package")]
+use switchbrew_bevy::emulator::{EmulatorConfig, EMULATOR_ENV_VAR};
+use switchbrew_bevy::nro::{self, Nro};
+#[cfg(feature = "package")]
+use switchbrew_bevy::nro::{NroBuilder, NroSegments, SwitchMetadata};
+#[cfg(feature = "bake")]
+use switchbrew_bevy::platform::Platform;
+use switchbrew_bevy::romfs::{RomFs, RomFsBuilder};
+
+const USAGE: &str = "\
+Usage: cargo switchbrew <command> [args]
+
+Commands:
+    romfs <dir> <image>    Pack a directory into a RomFS image
+    bake <platform> <dir> <out> [manifest]
+                           Bake assets for desktop, switch_docked or
+                           switch_handheld and print the budget report.
+                           The manifest defaults to switch-assets.toml.
+                           Fails if a directory is over budget.
+    package <elf> <nro> [Cargo.toml]
+                           Package a built ELF into an NRO with the icon,
+                           NACP and RomFS from [package.metadata.switch].
+                           The manifest defaults to ./Cargo.toml.
+    run <elf> [Cargo.toml] [--dry-run]
+                           Package the ELF next to it as an NRO and launch the
+                           emulator from [package.metadata.switch.emulator]
+                           or SWITCHBREW_EMULATOR, showing its output.
+                           --dry-run prints the emulator command instead.
+    inspect <nro> [icon.jpg]
+                           Show an NRO's title metadata, segments and RomFS
+                           files, and save its icon if a path is given.";
+
+fn main() -> ExitCode {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    // Cargo passes the subcommand name first
+    if args.first().is_some_and(|arg| arg == "switchbrew") {
+        args.remove(0);
+    }
+
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}\n\n{USAGE}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    match args {
+        [command, dir, image] if command == "romfs" => romfs(Path::new(dir), Path::new(image)),
+        #[cfg(feature = "bake")]
+        [command, platform, dir, out, manifest @ ..]
+            if command == "bake" && manifest.len() <= 1 =>
+        {
+            let manifest = manifest.first().map_or(MANIFEST_FILE, String::as_str);
+            bake(
+                platform,
+                Path::new(dir),
+                Path::new(out),
+                Path::new(manifest),
+            )
+        }
+        #[cfg(feature = "package")]
+        [command, elf, nro, manifest @ ..] if command == "package" && manifest.len() <= 1 => {
+            let manifest = manifest.first().map_or("Cargo.toml", String::as_str);
+            package(Path::new(elf), Path::new(nro), Path::new(manifest)).map(drop)
+        }
+        #[cfg(feature = "package")]
+        [command, rest @ ..] if command == "run" => {
+            let dry_run = rest.iter().any(|arg| arg == "--dry-run");
+            let paths: Vec<_> = rest.iter().filter(|arg| *arg != "--dry-run").collect();
+            match paths[..] {
+                [elf] => run_emulator(Path::new(elf), Path::new("Cargo.toml"), dry_run),
+                [elf, manifest] => run_emulator(Path::new(elf), Path::new(manifest), dry_run),
+                _ => Err("expected an ELF, an optional Cargo.toml and --dry-run".into()),
+            }
+        }
+        [command, nro, icon @ ..] if command == "inspect" && icon.len() <= 1 => {
+            inspect(Path::new(nro), icon.first().map(Path::new))
+        }
+        [command, ..] if command == "-h" || command == "--help" => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        [command, ..] if command == "romfs" => Err("expected a directory and an image path".into()),
+        #[cfg(feature = "bake")]
+        [command, ..] if command == "bake" => Err(
+            "expected a platform, a directory, an output directory and an optional manifest".into(),
+        ),
+        #[cfg(feature = "package")]
+        [command, ..] if command == "package" => {
+            Err("expected an ELF, an NRO path and an optional Cargo.toml".into())
+        }
+        [command, ..] if command == "inspect" => {
+            Err("expected an NRO and an optional icon path".into())
+        }
+        [command, ..] => Err(format!("unknown command {command:?}")),
+        [] => Err("missing command".into()),
+    }
+}
+
+/// Pack a directory into a RomFS image.
+fn romfs(dir: &Path, image: &Path) -> Result<(), String> {
+    let mut builder = RomFsBuilder::new();
+    builder
+        .add_host_dir(dir)
+        .map_err(|error| format!("failed to read {}: {error}", dir.display()))?;
+    let bytes = builder.build();
+    write_output(image, &bytes)?;
+    println!(
+        "Packed {} files from {} into {} ({} bytes)",
+        builder.file_count(),
+        dir.display(),
+        image.display(),
+        bytes.len()
+    );
+    Ok(())
+}
+
+/// Bake a directory of assets for a platform and print the budget report.
+#[cfg(feature = "bake")]
+fn bake(platform: &str, dir: &Path, out: &Path, manifest: &Path) -> Result<(), String> {
+    let platform =
+        Platform::parse(platform).ok_or_else(|| format!("unknown platform {platform:?}"))?;
+    let manifest = if manifest.exists() {
+        AssetManifest::load(manifest).map_err(|error| error.to_string())?
+    } else {
+        eprintln!(
+            "warning: {} not found, baking without limits",
+            manifest.display()
+        );
+        AssetManifest::default()
+    };
+    let report = bake_dir(dir, out, &manifest, platform).map_err(|error| error.to_string())?;
+    print!("{report}");
+    let over: Vec<_> = report
+        .over_budget()
+        .map(|usage| usage.dir.as_str())
+        .collect();
+    if !over.is_empty() {
+        return Err(format!("over budget: {}", over.join(", ")));
+    }
+    Ok(())
+}
+
+/// Package a built ELF into an NRO, with the assets from `Cargo.toml`.
+#[cfg(feature = "package")]
+fn package(elf: &Path, nro: &Path, manifest: &Path) -> Result<SwitchMetadata, String> {
+    let text = std::fs::read_to_string(manifest)
+        .map_err(|error| format!("failed to read {}: {error}", manifest.display()))?;
+    let manifest_dir = manifest.parent().unwrap_or(Path::new(""));
+    let metadata =
+        SwitchMetadata::from_cargo_toml(&text, manifest_dir).map_err(|error| error.to_string())?;
+
+    let elf_bytes =
+        std::fs::read(elf).map_err(|error| format!("failed to read {}: {error}", elf.display()))?;
+    let segments =
+        NroSegments::from_elf(&elf_bytes).map_err(|error| format!("{}: {error}", elf.display()))?;
+    let mut builder = NroBuilder::new(segments)
+        .with_nacp(&metadata.nacp)
+        .map_err(|error| error.to_string())?;
+    if let Some(icon) = &metadata.icon {
+        let bytes = std::fs::read(icon)
+            .map_err(|error| format!("failed to read {}: {error}", icon.display()))?;
+        builder = builder
+            .with_icon(bytes)
+            .map_err(|error| format!("{}: {error}", icon.display()))?;
+    }
+    if let Some(dir) = &metadata.romfs {
+        let mut romfs = RomFsBuilder::new();
+        romfs
+            .add_host_dir(dir)
+            .map_err(|error| format!("failed to read {}: {error}", dir.display()))?;
+        builder = builder.with_romfs(romfs.build());
+    }
+
+    let bytes = builder.build();
+    write_output(nro, &bytes)?;
+    println!(
+        "Packaged {} {} into {} ({} bytes)",
+        metadata.nacp.title,
+        metadata.nacp.version,
+        nro.display(),
+        bytes.len()
+    );
+    Ok(metadata)
+}
+
+/// Package an ELF next to it as an NRO and launch it in the configured
+/// emulator, or print the command with `dry_run`.
+#[cfg(feature = "package")]
+fn run_emulator(elf: &Path, manifest: &Path, dry_run: bool) -> Result<(), String> {
+    let nro = elf.with_extension("nro");
+    let metadata = package(elf, &nro, manifest)?;
+    let emulator = metadata
+        .emulator
+        .unwrap_or_else(|| EmulatorConfig::new(""))
+        .with_env_override(env::var(EMULATOR_ENV_VAR).ok().as_deref());
+    if emulator.path.as_os_str().is_empty() {
+        return Err(format!(
+            "no emulator set; add [package.metadata.switch.emulator] to {} or set {EMULATOR_ENV_VAR}",
+            manifest.display()
+        ));
+    }
+
+    let command_line = emulator.command_line(&nro);
+    if dry_run {
+        println!("{command_line}");
+        return Ok(());
+    }
+    println!("Running {command_line}");
+    let status = emulator
+        .run(&nro)
+        .map_err(|error| format!("failed to launch {}: {error}", emulator.path.display()))?;
+    if !status.success() {
+        return Err(format!("emulator exited with {status}"));
+    }
+    Ok(())
+}
+
+/// Print an NRO's metadata, segments and RomFS tree, saving its icon.
+fn inspect(path: &Path, icon_out: Option<&Path>) -> Result<(), String> {
+    let bytes = std::fs::read(path)
+        .map_err(|error| format!("failed to read {}: {error}", path.display()))?;
+    let nro = Nro::parse(&bytes).map_err(|error| format!("{}: {error}", path.display()))?;
+
+    println!("{} ({} bytes)", path.display(), bytes.len());
+    match &nro.nacp {
+        Some(nacp) => {
+            println!("  Title:    {}", nacp.title);
+            println!("  Author:   {}", nacp.author);
+            println!("  Version:  {}", nacp.version);
+            if let Some(title_id) = nacp.title_id {
+                println!("  Title ID: {title_id:016x}");
+            }
+        }
+        None => println!("  No NACP"),
+    }
+
+    let header = &nro.header;
+    // The build ID is zero padded to 32 bytes
+    let len = header
+        .build_id
+        .iter()
+        .rposition(|&byte| byte != 0)
+        .map_or(0, |last| last + 1);
+    let build_id: String = header.build_id[..len]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    println!("  Build ID: {build_id}");
+    for (name, (offset, size)) in [
+        ("text", header.text),
+        ("rodata", header.ro),
+        ("data", header.data),
+    ] {
+        println!("  {name:<8}  {offset:#08x}  {size:#x} bytes");
+    }
+    println!("  bss                 {:#x} bytes", header.bss_size);
+
+    match &nro.icon {
+        Some(icon) => {
+            let size = nro::jpeg_size(icon).map_or_else(
+                || "not a JPEG".into(),
+                |(width, height)| format!("{width}×{height} JPEG"),
+            );
+            println!("  Icon:     {size}, {} bytes", icon.len());
+            if let Some(out) = icon_out {
+                write_output(out, icon)?;
+                println!("  Saved icon to {}", out.display());
+            }
+        }
+        None => println!("  No icon"),
+    }
+
+    match &nro.romfs {
+        Some(romfs) => {
+            println!("  RomFS:    {} files", romfs.paths().len());
+            print_tree(romfs, "", 2);
+        }
+        None => println!("  No RomFS"),
+    }
+    Ok(())
+}
+
+/// Print a RomFS directory, indented by depth.
+fn print_tree(romfs: &RomFs, dir: &str, depth: usize) {
+    for entry in romfs.read_dir(dir).unwrap_or_default() {
+        let path = if dir.is_empty() {
+            entry.name.clone()
+        } else {
+            format!("{dir}/{}", entry.name)
+        };
+        match entry.size {
+            None => {
+                println!("{:indent$}{}/", "", entry.name, indent = depth * 2);
+                print_tree(romfs, &path, depth + 1);
+            }
+            Some(size) => println!(
+                "{:indent$}{} ({size} bytes)",
+                "",
+                entry.name,
+                indent = depth * 2
+            ),
+        }
+    }
+}
+
+/// Write an output file, creating its parent directories.
+fn write_output(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(parent)
+            .map_err(|error| format!("failed to create {}: {error}", parent.display()))?;
+    }
+    std::fs::write(path, bytes)
+        .map_err(|error| format!("failed to write {}: {error}", path.display()))
+}
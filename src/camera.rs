@@ -0,0 +1,205 @@
+//! Fixed virtual resolution and letterboxing for cameras.
+
+use bevy::camera::{ScalingMode, Viewport};
+use bevy::prelude::*;
+
+use crate::window::SwitchDisplay;
+
+/// Plugin that keeps [`VirtualResolution`] cameras scaled to their render target.
+pub struct SwitchCameraPlugin;
+
+impl Plugin for SwitchCameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, update_virtual_resolution_cameras);
+    }
+}
+
+/// How a virtual resolution is scaled to fill the render target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScaleMode {
+    /// Largest whole-number scale that fits - crisp pixel art, may leave bars
+    Integer,
+    /// Largest scale that fits while keeping the aspect ratio - letterbox/pillarbox bars
+    #[default]
+    Fit,
+    /// Smallest scale that covers the whole target - no bars, edges are cropped
+    Fill,
+}
+
+impl ScaleMode {
+    /// Compute the viewport layout for a virtual resolution inside a target.
+    ///
+    /// Both sizes are in physical pixels. Returns `None` if either is empty
+    /// (e.g. a minimized window).
+    pub fn layout(&self, virtual_size: UVec2, target_size: UVec2) -> Option<ViewportLayout> {
+        if virtual_size.min_element() == 0 || target_size.min_element() == 0 {
+            return None;
+        }
+
+        let ratio = target_size.as_vec2() / virtual_size.as_vec2();
+        let fit = ratio.min_element();
+        let scale = match self {
+            // Fall back to fractional scaling when the target is smaller than
+            // the virtual resolution, rather than showing nothing.
+            ScaleMode::Integer if fit >= 1.0 => fit.floor(),
+            ScaleMode::Integer | ScaleMode::Fit => fit,
+            ScaleMode::Fill => ratio.max_element(),
+        };
+
+        let size = (virtual_size.as_vec2() * scale)
+            .round()
+            .as_uvec2()
+            .min(target_size)
+            .max(UVec2::ONE);
+        let position = (target_size - size) / 2;
+
+        Some(ViewportLayout {
+            position,
+            size,
+            scale,
+        })
+    }
+}
+
+/// Where a virtual resolution ends up on the render target.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewportLayout {
+    /// Top-left corner of the viewport in physical pixels.
+    pub position: UVec2,
+    /// Size of the viewport in physical pixels.
+    pub size: UVec2,
+    /// Physical pixels per virtual pixel.
+    pub scale: f32,
+}
+
+impl ViewportLayout {
+    /// Thickness of the bars on each side: pillarbox (x) and letterbox (y).
+    pub fn bars(&self) -> UVec2 {
+        self.position
+    }
+
+    /// Check if the viewport leaves any bars around it.
+    pub fn has_bars(&self) -> bool {
+        self.position != UVec2::ZERO
+    }
+
+    /// Area of the virtual resolution that is visible, in virtual pixels.
+    ///
+    /// Equal to the virtual resolution except in [`ScaleMode::Fill`], where
+    /// the edges are cropped.
+    pub fn visible_size(&self) -> Vec2 {
+        self.size.as_vec2() / self.scale
+    }
+
+    /// Convert to a camera [`Viewport`].
+    pub fn viewport(&self) -> Viewport {
+        Viewport {
+            physical_position: self.position,
+            physical_size: self.size,
+            ..default()
+        }
+    }
+}
+
+/// Render a camera at a fixed virtual resolution.
+///
+/// The camera's viewport is resized to keep the virtual resolution's aspect
+/// ratio inside its render target, and orthographic projections are set to
+/// show exactly the virtual resolution in world units. Anything outside the
+/// viewport shows the clear color as letterbox/pillarbox bars.
+///
+/// ```rust,no_run
+/// use bevy::prelude::*;
+/// use switchbrew_bevy::prelude::*;
+///
+/// fn setup(mut commands: Commands) {
+///     // 640x360 pixel art, scaled by whole numbers only
+///     commands.spawn((
+///         Camera2d,
+///         VirtualResolution::new(640, 360).with_mode(ScaleMode::Integer),
+///     ));
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Default, Component)]
+#[require(Camera)]
+pub struct VirtualResolution {
+    /// Fixed virtual resolution, or `None` to follow [`SwitchDisplay::resolution`].
+    pub size: Option<UVec2>,
+    /// How the virtual resolution is scaled to the render target.
+    pub mode: ScaleMode,
+}
+
+impl VirtualResolution {
+    /// Create a fixed virtual resolution.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            size: Some(UVec2::new(width, height)),
+            ..Default::default()
+        }
+    }
+
+    /// Follow the current Switch display resolution (1080p docked, 720p handheld).
+    pub fn display() -> Self {
+        Self::default()
+    }
+
+    /// Set the scale mode.
+    pub fn with_mode(mut self, mode: ScaleMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Get the virtual resolution, resolving against the display if not fixed.
+    pub fn size(&self, display: &SwitchDisplay) -> UVec2 {
+        self.size
+            .unwrap_or_else(|| UVec2::new(display.resolution.0, display.resolution.1))
+    }
+
+    /// Compute the viewport layout for a render target size in physical pixels.
+    pub fn layout(&self, display: &SwitchDisplay, target_size: UVec2) -> Option<ViewportLayout> {
+        self.mode.layout(self.size(display), target_size)
+    }
+}
+
+/// System to fit [`VirtualResolution`] cameras to their render targets.
+fn update_virtual_resolution_cameras(
+    display: Res<SwitchDisplay>,
+    mut cameras: Query<(&VirtualResolution, &mut Camera, Option<&mut Projection>)>,
+) {
+    for (virtual_resolution, mut camera, projection) in &mut cameras {
+        let Some(target_size) = camera.physical_target_size() else {
+            continue;
+        };
+        // Stay inside the screen when a handheld bezel is shown on desktop
+        let screen = display.screen_rect(target_size);
+        let Some(layout) = virtual_resolution.layout(&display, screen.size()) else {
+            continue;
+        };
+
+        let mut viewport = layout.viewport();
+        viewport.physical_position += screen.min;
+        let unchanged = camera.viewport.as_ref().is_some_and(|current| {
+            current.physical_position == viewport.physical_position
+                && current.physical_size == viewport.physical_size
+        });
+        if !unchanged {
+            camera.viewport = Some(viewport);
+        }
+
+        if let Some(mut projection) = projection {
+            let visible = layout.visible_size();
+            if let Projection::Orthographic(ortho) = projection.bypass_change_detection()
+                && !matches!(
+                    ortho.scaling_mode,
+                    ScalingMode::Fixed { width, height } if width == visible.x && height == visible.y
+                )
+            {
+                ortho.scaling_mode = ScalingMode::Fixed {
+                    width: visible.x,
+                    height: visible.y,
+                };
+                projection.set_changed();
+            }
+        }
+    }
+}
@@ -0,0 +1,594 @@
+//! Loading [`SwitchConfig`] and related settings from a config file.
+//!
+//! Settings are read from `switch.toml` (or a `.ron` file) at startup, then
+//! environment variables are applied on top so QA can tweak a build without
+//! editing files:
+//!
+//! ```toml
+//! display_mode = "handheld"
+//! target_fps = 30
+//! preview_scale = 0.5
+//! log_level = "debug"
+//!
+//! [display]
+//! safe_area = "action"
+//!
+//! [input]
+//! stick_deadzone = 0.15
+//! ```
+//!
+//! On desktop the file is watched and re-applied when it changes.
+//!
+//! The [`SwitchConfigFilePlugin`] is opt-in; add it after the
+//! [`SwitchPlugins`](crate::SwitchPlugins) so the file is applied on top of
+//! the config passed to them.
+
+use core::fmt;
+use core::time::Duration;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::input::SwitchInputSettings;
+use crate::logging::LogLevel;
+use crate::platform::{
+    ConfigError, ConfigField, DisplayMode, FsError, Hal, Platform, PlatformInfo, SwitchConfig,
+    SwitchHal, SwitchModel, PLATFORM_ENV_VAR,
+};
+use crate::window::{SafeArea, SwitchDisplay, SwitchUiScale};
+
+/// Config file loaded when no path is given.
+pub const DEFAULT_CONFIG_PATH: &str = "switch.toml";
+
+/// Environment variable that overrides the config file path.
+pub const CONFIG_PATH_ENV_VAR: &str = "SWITCHBREW_CONFIG";
+
+/// How often a watched config file is checked for changes.
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Plugin that loads settings from a config file at startup.
+///
+/// Applies the file on top of the [`SwitchConfig`], [`SwitchDisplay`],
+/// [`SwitchUiScale`] and [`SwitchInputSettings`] resources, then applies
+/// environment overrides (see [`apply_env_overrides`]). Invalid settings are
+/// logged and the previous values kept; a missing file is not an error.
+///
+/// Not part of [`SwitchPlugins`](crate::SwitchPlugins); add it after them:
+///
+/// ```rust,no_run
+/// use bevy::prelude::*;
+/// use switchbrew_bevy::prelude::*;
+///
+/// App::new()
+///     .add_plugins(DefaultPlugins)
+///     .add_plugins(SwitchPlugin::new(SwitchConfig::handheld()))
+///     .add_plugins(SwitchConfigFilePlugin::default())
+///     .run();
+/// ```
+///
+/// When the file is watched, changes are applied on top of the current
+/// resources, so settings changed by the game since startup are kept unless
+/// the file sets them.
+#[derive(Debug, Clone)]
+pub struct SwitchConfigFilePlugin {
+    /// Path of the config file, unless overridden by [`CONFIG_PATH_ENV_VAR`].
+    pub path: PathBuf,
+    /// Reload the file when it changes (default on desktop).
+    pub watch: bool,
+}
+
+impl Default for SwitchConfigFilePlugin {
+    fn default() -> Self {
+        Self::new(DEFAULT_CONFIG_PATH)
+    }
+}
+
+impl SwitchConfigFilePlugin {
+    /// Load settings from a file.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            watch: cfg!(feature = "desktop"),
+        }
+    }
+
+    /// Enable or disable hot reload.
+    pub fn with_watch(mut self, watch: bool) -> Self {
+        self.watch = watch;
+        self
+    }
+}
+
+impl Plugin for SwitchConfigFilePlugin {
+    fn build(&self, app: &mut App) {
+        let hal = app
+            .world()
+            .get_resource::<Hal>()
+            .cloned()
+            .unwrap_or_default();
+        let path = hal
+            .env_var(CONFIG_PATH_ENV_VAR)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| self.path.clone());
+        let base = SwitchSettings::from_world(app.world());
+
+        match load_settings(&*hal, &path, &base, |name| hal.env_var(name)) {
+            Ok(settings) => settings.insert_into(app.world_mut()),
+            Err(error) => error!("{error}; keeping the current settings"),
+        }
+
+        if self.watch {
+            app.insert_resource(ConfigFileWatcher {
+                contents: read_file(&*hal, &path),
+                path,
+                last_check: hal.now(),
+            })
+            .add_systems(Update, reload_config_file);
+        }
+    }
+}
+
+/// All settings that can be loaded from a config file.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SwitchSettings {
+    /// Platform and display configuration.
+    pub config: SwitchConfig,
+    /// Display settings (only `vsync` and `safe_area` are loaded).
+    pub display: SwitchDisplay,
+    /// UI scale policy.
+    pub ui_scale: SwitchUiScale,
+    /// Input tuning.
+    pub input: SwitchInputSettings,
+}
+
+impl SwitchSettings {
+    /// Read the current settings from the world, using defaults for missing resources.
+    pub fn from_world(world: &World) -> Self {
+        Self {
+            config: world.get_resource().cloned().unwrap_or_default(),
+            display: world.get_resource().cloned().unwrap_or_default(),
+            ui_scale: world.get_resource().copied().unwrap_or_default(),
+            input: world.get_resource().copied().unwrap_or_default(),
+        }
+    }
+
+    /// Write the settings into the world, touching only resources that changed.
+    pub fn insert_into(self, world: &mut World) {
+        if world.get_resource::<SwitchConfig>() != Some(&self.config) {
+            world.insert_resource(self.config);
+        }
+
+        // Mode, resolution and bezel follow the config
+        match world.get_resource_mut::<SwitchDisplay>() {
+            Some(mut display) => {
+                if display.vsync != self.display.vsync
+                    || display.safe_area != self.display.safe_area
+                {
+                    display.vsync = self.display.vsync;
+                    display.safe_area = self.display.safe_area;
+                }
+            }
+            None => world.insert_resource(self.display),
+        }
+
+        if world.get_resource::<SwitchUiScale>() != Some(&self.ui_scale) {
+            world.insert_resource(self.ui_scale);
+        }
+        if world.get_resource::<SwitchInputSettings>() != Some(&self.input) {
+            world.insert_resource(self.input);
+        }
+    }
+
+    /// Check that all settings are usable.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.config.validate()?;
+
+        if !(0.0..1.0).contains(&self.input.stick_deadzone) {
+            return Err(ConfigError::new(
+                ConfigField::StickDeadzone,
+                format!("{} is outside [0, 1)", self.input.stick_deadzone),
+            ));
+        }
+        if !(self.ui_scale.docked > 0.0 && self.ui_scale.handheld > 0.0) {
+            return Err(ConfigError::new(
+                ConfigField::UiScale,
+                format!(
+                    "multipliers must be positive, got docked {} and handheld {}",
+                    self.ui_scale.docked, self.ui_scale.handheld
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Contents of a config file.
+///
+/// Every field is optional; only the values present in the file are applied.
+/// Unknown keys are rejected so typos are reported instead of ignored.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SettingsFile {
+    /// Target platform.
+    pub platform: Option<Platform>,
+    /// Console model to simulate.
+    pub model: Option<SwitchModel>,
+    /// Display mode; also sets the resolution unless `resolution` is given.
+    pub display_mode: Option<DisplayMode>,
+    /// Target resolution.
+    pub resolution: Option<(u32, u32)>,
+    /// Target frame rate.
+    pub target_fps: Option<u32>,
+    /// Show the performance overlay.
+    pub show_perf_overlay: Option<bool>,
+    /// Desktop window scale.
+    pub preview_scale: Option<f32>,
+    /// Draw the handheld bezel on desktop.
+    pub bezel: Option<bool>,
+    /// Minimum log level.
+    pub log_level: Option<LogLevel>,
+    /// `[display]` table.
+    pub display: DisplayFileSettings,
+    /// `[ui_scale]` table.
+    pub ui_scale: Option<SwitchUiScale>,
+    /// `[input]` table.
+    pub input: Option<SwitchInputSettings>,
+}
+
+/// `[display]` table of a config file.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayFileSettings {
+    /// Whether vsync is enabled.
+    pub vsync: Option<bool>,
+    /// Safe area used for UI when docked.
+    pub safe_area: Option<SafeArea>,
+}
+
+impl SettingsFile {
+    /// Read a config file through the HAL, or `Ok(None)` if it does not exist.
+    pub fn load(hal: &dyn SwitchHal, path: &Path) -> Result<Option<Self>, ConfigFileError> {
+        let bytes = match hal.read_file(&path.to_string_lossy()) {
+            Ok(bytes) => bytes,
+            Err(FsError::NotFound) => return Ok(None),
+            Err(source) => {
+                return Err(ConfigFileError::Io {
+                    path: path.to_path_buf(),
+                    source,
+                });
+            }
+        };
+        let text = String::from_utf8(bytes).map_err(|_| ConfigFileError::Parse {
+            path: path.to_path_buf(),
+            message: "file is not valid UTF-8".into(),
+        })?;
+        Self::parse(path, &text).map(Some)
+    }
+
+    /// Parse config file contents, picking the format from the path's extension.
+    pub fn parse(path: &Path, text: &str) -> Result<Self, ConfigFileError> {
+        let parse_error = |message: String| ConfigFileError::Parse {
+            path: path.to_path_buf(),
+            message,
+        };
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(text).map_err(|error| parse_error(error.to_string())),
+            Some("ron") => ron::Options::default()
+                .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+                .from_str(text)
+                .map_err(|error| parse_error(error.to_string())),
+            _ => Err(ConfigFileError::UnsupportedFormat {
+                path: path.to_path_buf(),
+            }),
+        }
+    }
+
+    /// Apply the values present in the file to `settings`.
+    pub fn apply(&self, settings: &mut SwitchSettings) {
+        let config = &mut settings.config;
+        if let Some(platform) = self.platform {
+            config.platform = platform;
+        }
+        if let Some(model) = self.model {
+            config.model = model;
+        }
+        if let Some(mode) = self.display_mode {
+            *config = config.clone().with_display_mode(mode);
+        } else if self.model.is_some() {
+            *config = config.clone().with_supported_display_mode();
+        }
+        if let Some(resolution) = self.resolution {
+            config.resolution = resolution;
+        }
+        if let Some(fps) = self.target_fps {
+            config.target_fps = fps;
+        }
+        if let Some(show) = self.show_perf_overlay {
+            config.show_perf_overlay = show;
+        }
+        if let Some(scale) = self.preview_scale {
+            config.preview_scale = scale;
+        }
+        if let Some(bezel) = self.bezel {
+            config.bezel = bezel;
+        }
+        if let Some(level) = self.log_level {
+            config.log_level = level;
+        }
+
+        if let Some(vsync) = self.display.vsync {
+            settings.display.vsync = vsync;
+        }
+        if let Some(safe_area) = self.display.safe_area {
+            settings.display.safe_area = safe_area;
+        }
+        if let Some(ui_scale) = self.ui_scale {
+            settings.ui_scale = ui_scale;
+        }
+        if let Some(input) = self.input {
+            settings.input = input;
+        }
+    }
+}
+
+/// Apply `SWITCHBREW_*` environment overrides to `settings`.
+///
+/// `lookup` returns the value of an environment variable, so overrides can be
+/// tested without touching the process environment. Supported variables:
+///
+/// | Variable | Example |
+/// |----------|---------|
+/// | `SWITCHBREW_MODEL` | `lite` |
+/// | `SWITCHBREW_PLATFORM` | `handheld` (see [`PLATFORM_ENV_VAR`]) |
+/// | `SWITCHBREW_RESOLUTION` | `1280x720` |
+/// | `SWITCHBREW_TARGET_FPS` | `30` |
+/// | `SWITCHBREW_PREVIEW_SCALE` | `0.5` |
+/// | `SWITCHBREW_BEZEL` | `true` |
+/// | `SWITCHBREW_SHOW_PERF_OVERLAY` | `1` |
+/// | `SWITCHBREW_LOG_LEVEL` | `debug` |
+pub fn apply_env_overrides(
+    settings: &mut SwitchSettings,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<(), ConfigFileError> {
+    let config = &mut settings.config;
+
+    let model = env_value(
+        &lookup,
+        "SWITCHBREW_MODEL",
+        "original, lite or oled",
+        SwitchModel::parse,
+    )?;
+    if let Some(model) = model {
+        config.model = model;
+    }
+    // Already applied by platform detection, again here so it wins over the file
+    if let Some((platform, mode)) = env_value(
+        &lookup,
+        PLATFORM_ENV_VAR,
+        "desktop, docked, handheld or tabletop",
+        PlatformInfo::parse_override,
+    )? {
+        config.platform = platform;
+        *config = config.clone().with_display_mode(mode);
+    } else if model.is_some() {
+        *config = config.clone().with_supported_display_mode();
+    }
+    if let Some(resolution) = env_value(
+        &lookup,
+        "SWITCHBREW_RESOLUTION",
+        "WIDTHxHEIGHT",
+        parse_resolution,
+    )? {
+        config.resolution = resolution;
+    }
+    if let Some(fps) = env_value(&lookup, "SWITCHBREW_TARGET_FPS", "a frame rate", |value| {
+        value.parse().ok()
+    })? {
+        config.target_fps = fps;
+    }
+    if let Some(scale) = env_value(&lookup, "SWITCHBREW_PREVIEW_SCALE", "a number", |value| {
+        value.parse().ok()
+    })? {
+        config.preview_scale = scale;
+    }
+    if let Some(bezel) = env_value(&lookup, "SWITCHBREW_BEZEL", "true or false", parse_bool)? {
+        config.bezel = bezel;
+    }
+    if let Some(show) = env_value(
+        &lookup,
+        "SWITCHBREW_SHOW_PERF_OVERLAY",
+        "true or false",
+        parse_bool,
+    )? {
+        config.show_perf_overlay = show;
+    }
+    if let Some(level) = env_value(
+        &lookup,
+        "SWITCHBREW_LOG_LEVEL",
+        "off, error, warn, info, debug or trace",
+        LogLevel::parse,
+    )? {
+        config.log_level = level;
+    }
+    Ok(())
+}
+
+/// Load settings: apply the config file and environment overrides to `base`, then validate.
+///
+/// A missing file leaves `base` unchanged apart from the environment overrides.
+pub fn load_settings(
+    hal: &dyn SwitchHal,
+    path: &Path,
+    base: &SwitchSettings,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<SwitchSettings, ConfigFileError> {
+    let mut settings = base.clone();
+
+    match SettingsFile::load(hal, path)? {
+        Some(file) => {
+            file.apply(&mut settings);
+            info!("Loaded settings from {}", path.display());
+        }
+        None => info!("No config file at {}, using defaults", path.display()),
+    }
+    apply_env_overrides(&mut settings, lookup)?;
+
+    settings
+        .validate()
+        .map_err(|error| ConfigFileError::Invalid {
+            path: path.to_path_buf(),
+            error,
+        })?;
+    Ok(settings)
+}
+
+/// Error returned when loading settings fails.
+#[derive(Debug)]
+pub enum ConfigFileError {
+    /// The file exists but could not be read
+    Io {
+        /// Config file path.
+        path: PathBuf,
+        /// Underlying filesystem error.
+        source: FsError,
+    },
+    /// The file is not valid TOML/RON or does not match the settings layout
+    Parse {
+        /// Config file path.
+        path: PathBuf,
+        /// Parser message, including the location of the error.
+        message: String,
+    },
+    /// The file extension is not `.toml` or `.ron`
+    UnsupportedFormat {
+        /// Config file path.
+        path: PathBuf,
+    },
+    /// An environment override has an invalid value
+    Env {
+        /// Variable name.
+        var: &'static str,
+        /// Value that failed to parse.
+        value: String,
+        /// Description of the accepted values.
+        expected: &'static str,
+    },
+    /// The resulting settings failed validation
+    Invalid {
+        /// Config file path.
+        path: PathBuf,
+        /// Validation error.
+        error: ConfigError,
+    },
+}
+
+impl fmt::Display for ConfigFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigFileError::Io { path, source } => {
+                write!(f, "failed to read {}: {source}", path.display())
+            }
+            ConfigFileError::Parse { path, message } => {
+                write!(f, "failed to parse {}: {message}", path.display())
+            }
+            ConfigFileError::UnsupportedFormat { path } => write!(
+                f,
+                "unsupported config file {}, expected .toml or .ron",
+                path.display()
+            ),
+            ConfigFileError::Env {
+                var,
+                value,
+                expected,
+            } => write!(f, "invalid {var}={value:?}, expected {expected}"),
+            ConfigFileError::Invalid { path, error } => {
+                write!(f, "invalid settings from {}: {error}", path.display())
+            }
+        }
+    }
+}
+
+impl core::error::Error for ConfigFileError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            ConfigFileError::Io { source, .. } => Some(source),
+            ConfigFileError::Invalid { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// State for reloading a watched config file.
+#[derive(Resource)]
+struct ConfigFileWatcher {
+    path: PathBuf,
+    /// File contents when last applied, or `None` if it did not exist.
+    contents: Option<Vec<u8>>,
+    last_check: Duration,
+}
+
+/// System to re-apply the config file when it changes on disk.
+fn reload_config_file(world: &mut World) {
+    let hal = world.resource::<Hal>().clone();
+    let mut watcher = world.resource_mut::<ConfigFileWatcher>();
+    let now = hal.now();
+    if now.saturating_sub(watcher.last_check) < WATCH_INTERVAL {
+        return;
+    }
+    watcher.last_check = now;
+
+    let contents = read_file(&*hal, &watcher.path);
+    if contents == watcher.contents {
+        return;
+    }
+    watcher.contents = contents;
+    let path = watcher.path.clone();
+    let base = SwitchSettings::from_world(world);
+
+    match load_settings(&*hal, &path, &base, |name| hal.env_var(name)) {
+        Ok(settings) => settings.insert_into(world),
+        Err(error) => error!("{error}; keeping previous settings"),
+    }
+}
+
+/// Read a file's contents, or `None` if it cannot be read.
+fn read_file(hal: &dyn SwitchHal, path: &Path) -> Option<Vec<u8>> {
+    hal.read_file(&path.to_string_lossy()).ok()
+}
+
+/// Read and parse an environment override, ignoring unset or empty variables.
+fn env_value<T>(
+    lookup: &impl Fn(&str) -> Option<String>,
+    var: &'static str,
+    expected: &'static str,
+    parse: impl Fn(&str) -> Option<T>,
+) -> Result<Option<T>, ConfigFileError> {
+    let Some(value) = lookup(var).filter(|value| !value.trim().is_empty()) else {
+        return Ok(None);
+    };
+    match parse(value.trim()) {
+        Some(parsed) => Ok(Some(parsed)),
+        None => Err(ConfigFileError::Env {
+            var,
+            value,
+            expected,
+        }),
+    }
+}
+
+/// Parse a `WIDTHxHEIGHT` resolution.
+fn parse_resolution(value: &str) -> Option<(u32, u32)> {
+    let (width, height) = value.split_once(['x', 'X'])?;
+    Some((width.trim().parse().ok()?, height.trim().parse().ok()?))
+}
+
+/// Parse a boolean flag (`true`/`false`, `1`/`0`, `yes`/`no`, `on`/`off`).
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Some(true),
+        "false" | "0" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
@@ -0,0 +1,242 @@
+//! Crash reports for panics.
+//!
+//! With `panic = "abort"` a panic ends the game without a trace, so the
+//! [`SwitchCrashPlugin`] writes a [`CrashReport`] first: the panic message
+//! and location, the frame it happened on, the [`SwitchConfig`] and the most
+//! recent lines of the [`SwitchLogger`]. The HAL then
+//! [shows the error](crate::platform::SwitchHal::show_error) on screen.
+//!
+//! On desktop the plugin installs a panic hook, keeping the default one's
+//! output. `no_std` builds have no hooks, so call [`report_panic`] from the
+//! game's panic handler:
+//!
+//! ```rust,ignore
+//! #[panic_handler]
+//! fn panic(info: &core::panic::PanicInfo) -> ! {
+//!     switchbrew_bevy::crash::report_panic(&info.message(), info.location());
+//!     loop {}
+//! }
+//! ```
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::panic::Location;
+
+use bevy::platform::sync::Mutex;
+use bevy::prelude::*;
+
+use crate::logging::SwitchLogger;
+use crate::platform::{Hal, SwitchConfig};
+
+/// Crash report written when no path is given, next to the game's NRO on the
+/// console.
+pub const DEFAULT_CRASH_REPORT: &str = "crash.txt";
+
+/// Number of recent log lines in a report by default.
+pub const DEFAULT_LOG_LINES: usize = 50;
+
+/// Plugin that writes a crash report and shows an error when the game panics.
+///
+/// The report goes to the most recently built app's [`Hal`].
+#[derive(Debug, Clone)]
+pub struct SwitchCrashPlugin {
+    /// Crash report path, written through the HAL.
+    ///
+    /// Relative paths are [resolved](crate::platform::SwitchHal::game_path)
+    /// against the running NRO's directory, like the log file's.
+    pub path: String,
+    /// Number of recent log lines in the report.
+    pub log_lines: usize,
+    /// Show the error on screen (default on).
+    pub show_error: bool,
+}
+
+impl Default for SwitchCrashPlugin {
+    fn default() -> Self {
+        Self::new(DEFAULT_CRASH_REPORT)
+    }
+}
+
+impl SwitchCrashPlugin {
+    /// Write crash reports to a path.
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            log_lines: DEFAULT_LOG_LINES,
+            show_error: true,
+        }
+    }
+
+    /// Show the error on screen, or only write the report.
+    pub fn with_show_error(mut self, show_error: bool) -> Self {
+        self.show_error = show_error;
+        self
+    }
+}
+
+impl Plugin for SwitchCrashPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Hal>();
+        let world = app.world();
+        let hal = world.resource::<Hal>().clone();
+        *context() = Some(CrashContext {
+            path: hal.game_path(&self.path),
+            hal,
+            logger: world.get_resource::<SwitchLogger>().cloned(),
+            config: world.get_resource::<SwitchConfig>().cloned(),
+            frame: 0,
+            log_lines: self.log_lines,
+            show_error: self.show_error,
+        });
+        #[cfg(feature = "std")]
+        install_hook();
+
+        app.add_systems(Last, track_crash_context);
+    }
+}
+
+/// What a panic left behind.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrashReport {
+    /// Panic message.
+    pub message: String,
+    /// Where the panic happened, as `file:line:column`.
+    pub location: Option<String>,
+    /// Frames completed before the panic, like Bevy's `FrameCount`.
+    pub frame: u64,
+    /// Config at the time of the panic.
+    pub config: Option<SwitchConfig>,
+    /// Most recent log lines, oldest first.
+    pub recent_log: Vec<String>,
+}
+
+impl fmt::Display for CrashReport {
+    /// Format the report as written to the crash report file.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "switchbrew_bevy crash report")?;
+        writeln!(f)?;
+        writeln!(f, "Message: {}", self.message)?;
+        writeln!(
+            f,
+            "Location: {}",
+            self.location.as_deref().unwrap_or("unknown")
+        )?;
+        writeln!(f, "Frame: {}", self.frame)?;
+        if let Some(config) = &self.config {
+            writeln!(f)?;
+            writeln!(f, "Config:")?;
+            writeln!(f, "{config:#?}")?;
+        }
+        if !self.recent_log.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "Recent log:")?;
+            for line in &self.recent_log {
+                writeln!(f, "{line}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// App state captured for crash reports.
+#[derive(Clone)]
+struct CrashContext {
+    hal: Hal,
+    logger: Option<SwitchLogger>,
+    config: Option<SwitchConfig>,
+    frame: u64,
+    path: String,
+    log_lines: usize,
+    show_error: bool,
+}
+
+static CONTEXT: Mutex<Option<CrashContext>> = Mutex::new(None);
+
+fn context() -> bevy::platform::sync::MutexGuard<'static, Option<CrashContext>> {
+    CONTEXT
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// System to keep the frame count, config and logger for crash reports.
+fn track_crash_context(config: Option<Res<SwitchConfig>>, logger: Option<Res<SwitchLogger>>) {
+    let mut context = context();
+    let Some(context) = context.as_mut() else {
+        return;
+    };
+    context.frame += 1;
+    if let Some(config) = config.filter(|config| config.is_changed()) {
+        context.config = Some(config.clone());
+    }
+    if let Some(logger) = logger.filter(|logger| logger.is_added()) {
+        context.logger = Some(logger.clone());
+    }
+}
+
+/// Write a crash report for a panic and show the error.
+///
+/// Called by the panic hook on desktop; call it from the panic handler in
+/// `no_std` builds. Returns `None` if no app added the [`SwitchCrashPlugin`].
+pub fn report_panic(
+    message: &dyn fmt::Display,
+    location: Option<&Location<'_>>,
+) -> Option<CrashReport> {
+    // Don't hold the lock while writing, in case that panics too
+    let context = context().clone()?;
+    let report = CrashReport {
+        message: message.to_string(),
+        location: location.map(ToString::to_string),
+        frame: context.frame,
+        config: context.config,
+        recent_log: context
+            .logger
+            .as_ref()
+            .map(|logger| logger.recent_lines(context.log_lines))
+            .unwrap_or_default(),
+    };
+
+    let text = report.to_string();
+    let saved = match context.hal.write_file(&context.path, text.as_bytes()) {
+        Ok(()) => format!("Crash report saved to {}", context.path),
+        Err(error) => {
+            // Keep the report in the debug output instead
+            for line in text.lines() {
+                context.hal.debug_output(line);
+            }
+            format!("Could not save crash report to {}: {error}", context.path)
+        }
+    };
+    context.hal.debug_output(&saved);
+    if let Some(logger) = &context.logger {
+        let _ = logger.flush();
+    }
+    if context.show_error {
+        context.hal.show_error(
+            "The game crashed",
+            &format!("{}\n\n{saved}", report.message),
+        );
+    }
+    Some(report)
+}
+
+/// Install the panic hook once, after the default one.
+#[cfg(feature = "std")]
+fn install_hook() {
+    static INSTALL: std::sync::Once = std::sync::Once::new();
+
+    INSTALL.call_once(|| {
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            default_hook(info);
+            let payload = info.payload();
+            let message = payload
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("Box<dyn Any>");
+            report_panic(&message, info.location());
+        }));
+    });
+}
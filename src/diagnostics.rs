@@ -0,0 +1,34 @@
+//! Startup diagnostics for Switch compatibility.
+
+use alloc::format;
+
+use bevy::prelude::*;
+use log::Level;
+
+use crate::platform::{Hal, PlatformInfo, SwitchConfig};
+
+/// Plugin that logs platform information at startup.
+pub struct SwitchDiagnosticsPlugin;
+
+impl Plugin for SwitchDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, log_platform_info);
+    }
+}
+
+fn log_platform_info(hal: Res<Hal>, config: Res<SwitchConfig>, info: Res<PlatformInfo>) {
+    let log = |message: &str| hal.log(Level::Info, message);
+    log("switchbrew_bevy initialized");
+    log(&format!("Platform: {}", config.platform.name()));
+    log(&format!("Model: {}", config.model.name()));
+    log(&format!(
+        "OS: {}{}{}",
+        info.os.name(),
+        if info.emulator { " (emulator)" } else { "" },
+        if info.simulated { " (simulated)" } else { "" }
+    ));
+    log(&format!(
+        "Resolution: {}x{}",
+        config.resolution.0, config.resolution.1
+    ));
+}
@@ -0,0 +1,262 @@
+//! Launching packaged NROs in an emulator.
+//!
+//! `cargo switchbrew run` packages an ELF and starts the emulator set in
+//! `Cargo.toml`, forwarding its output to the terminal:
+//!
+//! ```toml
+//! [package.metadata.switch.emulator]
+//! path = "/opt/ryubing/Ryujinx"    # or just "Ryujinx" to search the PATH
+//! kind = "ryujinx"                 # ryujinx, citron or custom; guessed from the path
+//! args = ["--fullscreen"]          # extra arguments, before the NRO
+//! log = "logs/emulator.log"        # log file to tail, if the emulator writes one
+//! ```
+//!
+//! [`EMULATOR_ENV_VAR`] overrides the path, e.g. to try another emulator.
+
+use std::ffi::OsString;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+/// Environment variable that overrides the emulator path.
+pub const EMULATOR_ENV_VAR: &str = "SWITCHBREW_EMULATOR";
+
+/// Placeholder for the NRO path in [`EmulatorKind::Custom`] arguments.
+pub const NRO_PLACEHOLDER: &str = "{nro}";
+
+/// How often the log file is checked for new lines.
+const TAIL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Emulator family, deciding how the NRO is passed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmulatorKind {
+    /// Ryujinx or its Ryubing fork: the NRO is the last argument
+    Ryujinx,
+    /// Citron, or another yuzu-based emulator: `-g <nro>`
+    Citron,
+    /// Arguments as configured, with [`NRO_PLACEHOLDER`] replaced by the
+    /// NRO path, or the NRO appended if there is no placeholder
+    Custom,
+}
+
+impl EmulatorKind {
+    /// Guess the emulator from its executable name.
+    pub fn from_path(path: &Path) -> Self {
+        let name = path
+            .file_stem()
+            .map(|name| name.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        if ["ryujinx", "ryubing"]
+            .iter()
+            .any(|known| name.contains(known))
+        {
+            EmulatorKind::Ryujinx
+        } else if ["citron", "yuzu", "suyu", "sudachi", "eden"]
+            .iter()
+            .any(|known| name.contains(known))
+        {
+            EmulatorKind::Citron
+        } else {
+            EmulatorKind::Custom
+        }
+    }
+}
+
+/// Emulator to launch, from `[package.metadata.switch.emulator]`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EmulatorConfig {
+    /// Emulator executable.
+    pub path: PathBuf,
+    /// Emulator family, guessed from the path if not set.
+    #[serde(default)]
+    pub kind: Option<EmulatorKind>,
+    /// Extra arguments.
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Log file the emulator writes, tailed while it runs.
+    #[serde(default)]
+    pub log: Option<PathBuf>,
+}
+
+impl EmulatorConfig {
+    /// Launch an emulator executable with no extra arguments.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            kind: None,
+            args: Vec::new(),
+            log: None,
+        }
+    }
+
+    /// Set the emulator family.
+    pub fn with_kind(mut self, kind: EmulatorKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    /// Add extra arguments.
+    pub fn with_args(mut self, args: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Tail a log file while the emulator runs.
+    pub fn with_log(mut self, log: impl Into<PathBuf>) -> Self {
+        self.log = Some(log.into());
+        self
+    }
+
+    /// Use the path in [`EMULATOR_ENV_VAR`] if set, guessing the family
+    /// again from it.
+    pub fn with_env_override(mut self, env_override: Option<&str>) -> Self {
+        if let Some(path) = env_override.filter(|path| !path.is_empty()) {
+            self.path = path.into();
+            self.kind = None;
+        }
+        self
+    }
+
+    /// Get the emulator family.
+    pub fn kind(&self) -> EmulatorKind {
+        self.kind
+            .unwrap_or_else(|| EmulatorKind::from_path(&self.path))
+    }
+
+    /// Get the arguments that launch an NRO.
+    pub fn args(&self, nro: &Path) -> Vec<OsString> {
+        let mut args: Vec<OsString> = Vec::new();
+        match self.kind() {
+            EmulatorKind::Ryujinx => {
+                args.extend(self.args.iter().map(Into::into));
+                args.push(nro.into());
+            }
+            EmulatorKind::Citron => {
+                args.extend(self.args.iter().map(Into::into));
+                args.extend(["-g".into(), nro.into()]);
+            }
+            EmulatorKind::Custom => {
+                let path = nro.to_string_lossy();
+                args.extend(
+                    self.args
+                        .iter()
+                        .map(|arg| arg.replace(NRO_PLACEHOLDER, &path).into()),
+                );
+                if !self.args.iter().any(|arg| arg.contains(NRO_PLACEHOLDER)) {
+                    args.push(nro.into());
+                }
+            }
+        }
+        args
+    }
+
+    /// Get the command that launches an NRO.
+    pub fn command(&self, nro: &Path) -> Command {
+        let mut command = Command::new(&self.path);
+        command.args(self.args(nro));
+        command
+    }
+
+    /// Get the command line that launches an NRO, quoted for a shell, as
+    /// printed by a dry run.
+    pub fn command_line(&self, nro: &Path) -> String {
+        std::iter::once(self.path.as_os_str().to_owned())
+            .chain(self.args(nro))
+            .map(|arg| shell_quote(&arg.to_string_lossy()))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Launch an NRO and wait for the emulator to exit, copying its output
+    /// and any new lines of its log file to the terminal.
+    pub fn run(&self, nro: &Path) -> std::io::Result<ExitStatus> {
+        let log_start = self
+            .log
+            .as_ref()
+            .and_then(|log| std::fs::metadata(log).ok())
+            .map_or(0, |metadata| metadata.len());
+        let mut child = self
+            .command(nro)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let done = Arc::new(AtomicBool::new(false));
+        let mut threads = forward_output(&mut child);
+        if let Some(log) = self.log.clone() {
+            let done = done.clone();
+            threads.push(thread::spawn(move || tail(&log, log_start, &done)));
+        }
+        let status = child.wait();
+        done.store(true, Ordering::Relaxed);
+        for thread in threads {
+            let _ = thread.join();
+        }
+        status
+    }
+}
+
+/// Copy a child's stdout and stderr to ours, line by line.
+fn forward_output(child: &mut Child) -> Vec<thread::JoinHandle<()>> {
+    let mut threads = Vec::new();
+    if let Some(stdout) = child.stdout.take() {
+        threads.push(thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                println!("{line}");
+            }
+        }));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        threads.push(thread::spawn(move || {
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                eprintln!("{line}");
+            }
+        }));
+    }
+    threads
+}
+
+/// Print what is appended to a log file after `start` until `done` is set.
+fn tail(log: &Path, mut start: u64, done: &AtomicBool) {
+    loop {
+        // Read once more after the emulator exits, for its last lines
+        let finished = done.load(Ordering::Relaxed);
+        if let Ok(mut file) = std::fs::File::open(log) {
+            let len = file.metadata().map_or(0, |metadata| metadata.len());
+            // The emulator started a new file
+            if len < start {
+                start = 0;
+            }
+            let mut new = Vec::new();
+            if file.seek(SeekFrom::Start(start)).is_ok() && file.read_to_end(&mut new).is_ok() {
+                start += new.len() as u64;
+                let mut stdout = std::io::stdout().lock();
+                let _ = stdout.write_all(&new);
+                let _ = stdout.flush();
+            }
+        }
+        if finished {
+            break;
+        }
+        thread::sleep(TAIL_INTERVAL);
+    }
+}
+
+/// Quote an argument for a POSIX shell, if it needs it.
+fn shell_quote(arg: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./=:+,@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        arg.into()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
@@ -1,7 +1,11 @@
 //! Nintendo Switch input handling and Joy-Con abstractions.
 
+use bevy::math::ops;
+use bevy::platform::collections::HashSet;
 use bevy::prelude::*;
-use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+
+use crate::platform::Hal;
 
 /// Plugin for Switch-style input handling.
 pub struct SwitchInputPlugin;
@@ -9,6 +13,7 @@ pub struct SwitchInputPlugin;
 impl Plugin for SwitchInputPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<SwitchInput>()
+            .init_resource::<SwitchInputSettings>()
             .add_systems(Update, update_switch_input);
     }
 }
@@ -113,6 +118,22 @@ pub enum SwitchController {
     Sideways,
 }
 
+/// Input tuning resource.
+#[derive(Debug, Clone, Copy, PartialEq, Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SwitchInputSettings {
+    /// Stick values below this magnitude are treated as centered (0.0 to 1.0).
+    pub stick_deadzone: f32,
+}
+
+impl Default for SwitchInputSettings {
+    fn default() -> Self {
+        Self {
+            stick_deadzone: 0.1,
+        }
+    }
+}
+
 /// Resource tracking Switch input state.
 #[derive(Debug, Default, Resource)]
 pub struct SwitchInput {
@@ -166,67 +187,97 @@ impl SwitchInput {
     }
 }
 
-/// System to update Switch input from keyboard and gamepads.
+/// System to update Switch input from keyboard, gamepads and the [`Hal`].
 fn update_switch_input(
     mut switch_input: ResMut<SwitchInput>,
-    keyboard: Res<ButtonInput<KeyCode>>,
+    settings: Res<SwitchInputSettings>,
+    hal: Option<Res<Hal>>,
+    keyboard: Option<Res<ButtonInput<KeyCode>>>,
     gamepads: Query<&Gamepad>,
+    mut native_pressed: Local<HashSet<SwitchButton>>,
 ) {
     // Clear frame-specific state
     switch_input.just_pressed.clear();
     switch_input.just_released.clear();
 
-    // Update from keyboard (development mode)
-    for key in keyboard.get_just_pressed() {
-        if let Some(button) = SwitchButton::from_keycode(*key) {
-            switch_input.pressed.insert(button);
-            switch_input.just_pressed.insert(button);
+    // Update from keyboard (development mode, absent when headless)
+    let mut kb_movement = Vec2::ZERO;
+    if let Some(keyboard) = keyboard {
+        for key in keyboard.get_just_pressed() {
+            if let Some(button) = SwitchButton::from_keycode(*key) {
+                switch_input.pressed.insert(button);
+                switch_input.just_pressed.insert(button);
+            }
         }
-    }
 
-    for key in keyboard.get_just_released() {
-        if let Some(button) = SwitchButton::from_keycode(*key) {
-            switch_input.pressed.remove(&button);
-            switch_input.just_released.insert(button);
+        for key in keyboard.get_just_released() {
+            if let Some(button) = SwitchButton::from_keycode(*key) {
+                switch_input.pressed.remove(&button);
+                switch_input.just_released.insert(button);
+            }
         }
-    }
 
-    // Keyboard movement (WASD or IJKL)
-    let mut kb_movement = Vec2::ZERO;
-    if keyboard.pressed(KeyCode::KeyW) || keyboard.pressed(KeyCode::KeyI) {
-        kb_movement.y += 1.0;
-    }
-    if keyboard.pressed(KeyCode::KeyS) || keyboard.pressed(KeyCode::KeyK) {
-        kb_movement.y -= 1.0;
-    }
-    if keyboard.pressed(KeyCode::KeyA) || keyboard.pressed(KeyCode::KeyJ) {
-        kb_movement.x -= 1.0;
-    }
-    if keyboard.pressed(KeyCode::KeyD) || keyboard.pressed(KeyCode::KeyL) {
-        kb_movement.x += 1.0;
+        // Keyboard movement (WASD or IJKL)
+        if keyboard.pressed(KeyCode::KeyW) || keyboard.pressed(KeyCode::KeyI) {
+            kb_movement.y += 1.0;
+        }
+        if keyboard.pressed(KeyCode::KeyS) || keyboard.pressed(KeyCode::KeyK) {
+            kb_movement.y -= 1.0;
+        }
+        if keyboard.pressed(KeyCode::KeyA) || keyboard.pressed(KeyCode::KeyJ) {
+            kb_movement.x -= 1.0;
+        }
+        if keyboard.pressed(KeyCode::KeyD) || keyboard.pressed(KeyCode::KeyL) {
+            kb_movement.x += 1.0;
+        }
     }
 
     // Update from gamepads
+    let deadzone = settings.stick_deadzone;
     for gamepad in gamepads.iter() {
         // Left stick
         let left_x = gamepad.get(GamepadAxis::LeftStickX).unwrap_or(0.0);
         let left_y = gamepad.get(GamepadAxis::LeftStickY).unwrap_or(0.0);
-        if left_x.abs() > 0.1 || left_y.abs() > 0.1 {
+        if ops::abs(left_x) > deadzone || ops::abs(left_y) > deadzone {
             switch_input.left_stick = Vec2::new(left_x, left_y);
         }
 
         // Right stick
         let right_x = gamepad.get(GamepadAxis::RightStickX).unwrap_or(0.0);
         let right_y = gamepad.get(GamepadAxis::RightStickY).unwrap_or(0.0);
-        if right_x.abs() > 0.1 || right_y.abs() > 0.1 {
+        if ops::abs(right_x) > deadzone || ops::abs(right_y) > deadzone {
             switch_input.right_stick = Vec2::new(right_x, right_y);
         }
     }
 
+    // Update from the platform's native controllers (console only)
+    if let Some(native) = hal.and_then(|hal| hal.input()) {
+        let pressed: HashSet<SwitchButton> = native.buttons.iter().copied().collect();
+        for &button in pressed.difference(&native_pressed) {
+            switch_input.pressed.insert(button);
+            switch_input.just_pressed.insert(button);
+        }
+        for &button in native_pressed.difference(&pressed) {
+            switch_input.pressed.remove(&button);
+            switch_input.just_released.insert(button);
+        }
+        *native_pressed = pressed;
+
+        let apply_deadzone = |stick: Vec2| {
+            if stick.length() > deadzone {
+                stick
+            } else {
+                Vec2::ZERO
+            }
+        };
+        switch_input.left_stick = apply_deadzone(native.left_stick);
+        switch_input.right_stick = apply_deadzone(native.right_stick);
+    }
+
     // Combine keyboard and gamepad for left stick
     if kb_movement.length() > 0.1 {
         switch_input.left_stick = kb_movement.normalize();
-    } else if switch_input.left_stick.length() < 0.1 {
+    } else if switch_input.left_stick.length() < deadzone {
         switch_input.left_stick = Vec2::ZERO;
     }
 }
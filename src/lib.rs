@@ -14,7 +14,7 @@
 //! fn main() {
 //!     App::new()
 //!         .add_plugins(DefaultPlugins)
-//!         .add_plugins(SwitchPlugin)
+//!         .add_plugins(SwitchPlugin::default())
 //!         .run();
 //! }
 //! ```
@@ -22,20 +22,95 @@
 //! ## Features
 //!
 //! - `desktop` (default): Build for desktop development/testing
+//! - `config` (default via `desktop`): Load settings from `switch.toml`/`.ron`
+//! - `bake` (default via `desktop`): Downsize textures and audio per platform
+//!   with the asset processor
+//! - `package` (default via `desktop`): Read NRO metadata from `Cargo.toml`
+//!   for `cargo switchbrew package`, and launch emulators for
+//!   `cargo switchbrew run`
+//! - `save` (default): Typed per-user save data
 //! - `switch`: Build for Nintendo Switch target
+//! - `no_std`: Build without the standard library (disable default features).
+//!   Windows, cameras and UI helpers need `std` and are left out.
+//! - `libm`: Use `libm` for math in Bevy and glam (enabled by `no_std`)
+//! - `critical-section`: Use `critical-section` for synchronization in Bevy
+//! - `critical-section-single-core`: Provide a critical-section implementation
+//!   for single-core contexts
+//! - `critical-section-spin`: Provide a spinlock critical-section
+//!   implementation that is safe across threads (enabled by `switch`)
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "bake")]
+pub mod bake;
+#[cfg(feature = "std")]
+pub mod camera;
+#[cfg(feature = "config")]
+pub mod config;
+pub mod crash;
+pub mod diagnostics;
+#[cfg(feature = "package")]
+pub mod emulator;
 pub mod input;
+pub mod lifecycle;
+pub mod logging;
+pub mod memory;
+pub mod nro;
+#[cfg(feature = "desktop")]
+pub mod performance;
 pub mod platform;
+pub mod romfs;
+pub mod runner;
+#[cfg(feature = "save")]
+pub mod save;
+#[cfg(all(
+    feature = "critical-section-single-core",
+    not(feature = "critical-section-spin")
+))]
+mod single_core;
+#[cfg(feature = "critical-section-spin")]
+mod spin_section;
 pub mod window;
 
 /// Prelude module - import commonly used items
 pub mod prelude {
-    pub use crate::input::{SwitchButton, SwitchController, SwitchInput};
-    pub use crate::platform::{Platform, SwitchConfig};
-    pub use crate::window::{handheld_window, switch_window, SwitchDisplay, SwitchWindowPlugin};
-    pub use crate::SwitchPlugin;
+    #[cfg(feature = "bake")]
+    pub use crate::bake::SwitchBakePlugin;
+    #[cfg(feature = "std")]
+    pub use crate::camera::{ScaleMode, SwitchCameraPlugin, VirtualResolution};
+    #[cfg(feature = "config")]
+    pub use crate::config::SwitchConfigFilePlugin;
+    pub use crate::crash::SwitchCrashPlugin;
+    pub use crate::diagnostics::SwitchDiagnosticsPlugin;
+    pub use crate::input::{
+        SwitchButton, SwitchController, SwitchInput, SwitchInputPlugin, SwitchInputSettings,
+    };
+    pub use crate::lifecycle::{LifecycleEvent, SwitchLifecycle, SwitchLifecyclePlugin};
+    pub use crate::logging::{LogLevel, SwitchLogPlugin, SwitchLogger};
+    pub use crate::memory::{
+        with_tag, MemoryTag, MemoryUsage, SwitchMemoryPlugin, TrackingAllocator,
+    };
+    #[cfg(feature = "desktop")]
+    pub use crate::performance::{FrameLimiter, SwitchFrameLimiterPlugin};
+    pub use crate::platform::{
+        AppletMessage, ConfigError, ConfigField, DisplayMode, Hal, HalInput, HostOs, LaunchMode,
+        PerformanceMode, PerformanceModeChanged, Platform, PlatformInfo, SwitchConfig,
+        SwitchConfigPlugin, SwitchHal, SwitchModel,
+    };
+    #[cfg(feature = "std")]
+    pub use crate::romfs::RomFsAssetPlugin;
+    pub use crate::runner::{RunnerPlatform, SwitchRunner};
+    #[cfg(feature = "save")]
+    pub use crate::save::{SaveData, SwitchSave, SwitchSavePlugin, UserId};
+    #[cfg(feature = "std")]
+    pub use crate::window::{handheld_window, switch_window, switch_window_for, SafeAreaNode};
+    pub use crate::window::{SafeArea, SwitchDisplay, SwitchUiScale, SwitchWindowPlugin};
+    pub use crate::{SwitchPlugin, SwitchPlugins};
 }
 
+use bevy::app::PluginGroupBuilder;
 use bevy::prelude::*;
 
 /// Main plugin that sets up all Switch compatibility features.
@@ -48,25 +123,98 @@ use bevy::prelude::*;
 ///
 /// App::new()
 ///     .add_plugins(DefaultPlugins)
-///     .add_plugins(SwitchPlugin)
+///     .add_plugins(SwitchPlugin::default())
 ///     .run();
 /// ```
-pub struct SwitchPlugin;
+///
+/// Pass a config with [`SwitchPlugin::new`]. A [`SwitchConfig`](platform::SwitchConfig)
+/// resource inserted before the plugin is added always takes precedence. To
+/// disable or replace individual parts, use [`SwitchPlugins`] instead.
+///
+/// The plugin also works headless, e.g. for running game logic in CI under
+/// [`MinimalPlugins`] without a window, GPU or keyboard. Display state is
+/// still tracked in [`SwitchDisplay`](window::SwitchDisplay) from the
+/// [`SwitchConfig`](platform::SwitchConfig).
+#[derive(Debug, Clone, Default)]
+pub struct SwitchPlugin {
+    /// Config to insert, or `None` for [`SwitchConfig::default`](platform::SwitchConfig::default).
+    pub config: Option<platform::SwitchConfig>,
+}
+
+impl SwitchPlugin {
+    /// Create the plugin with a config.
+    pub fn new(config: platform::SwitchConfig) -> Self {
+        Self {
+            config: Some(config),
+        }
+    }
+}
 
 impl Plugin for SwitchPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(platform::SwitchConfig::default())
-            .add_plugins(window::SwitchWindowPlugin)
-            .add_plugins(input::SwitchInputPlugin)
-            .add_systems(Startup, log_platform_info);
+        app.add_plugins(SwitchPlugins {
+            config: self.config.clone(),
+        });
+    }
+}
+
+/// All Switch compatibility plugins as a group.
+///
+/// Use this instead of [`SwitchPlugin`] to disable or replace individual
+/// parts, e.g. to handle input yourself:
+///
+/// ```rust,no_run
+/// use bevy::prelude::*;
+/// use switchbrew_bevy::prelude::*;
+///
+/// App::new()
+///     .add_plugins(DefaultPlugins)
+///     .add_plugins(
+///         SwitchPlugins::new(SwitchConfig::handheld())
+///             .build()
+///             .disable::<SwitchInputPlugin>(),
+///     )
+///     .run();
+/// ```
+///
+/// The group contains:
+/// - [`SwitchConfigPlugin`](platform::SwitchConfigPlugin)
+/// - [`SwitchLogPlugin`](logging::SwitchLogPlugin)
+/// - [`SwitchWindowPlugin`](window::SwitchWindowPlugin)
+/// - [`SwitchCameraPlugin`](camera::SwitchCameraPlugin) (`std` feature)
+/// - [`SwitchInputPlugin`](input::SwitchInputPlugin)
+/// - [`SwitchDiagnosticsPlugin`](diagnostics::SwitchDiagnosticsPlugin)
+/// - [`SwitchLifecyclePlugin`](lifecycle::SwitchLifecyclePlugin)
+#[derive(Debug, Clone, Default)]
+pub struct SwitchPlugins {
+    /// Config to insert, or `None` for [`SwitchConfig::default`](platform::SwitchConfig::default).
+    pub config: Option<platform::SwitchConfig>,
+}
+
+impl SwitchPlugins {
+    /// Create the plugin group with a config.
+    pub fn new(config: platform::SwitchConfig) -> Self {
+        Self {
+            config: Some(config),
+        }
     }
 }
 
-fn log_platform_info(config: Res<platform::SwitchConfig>) {
-    info!("switchbrew_bevy initialized");
-    info!("Platform: {}", config.platform.name());
-    info!(
-        "Resolution: {}x{}",
-        config.resolution.0, config.resolution.1
-    );
+impl PluginGroup for SwitchPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        let group = PluginGroupBuilder::start::<Self>()
+            .add(platform::SwitchConfigPlugin {
+                config: self.config,
+            })
+            .add(logging::SwitchLogPlugin::default())
+            .add(window::SwitchWindowPlugin);
+
+        #[cfg(feature = "std")]
+        let group = group.add(camera::SwitchCameraPlugin);
+
+        group
+            .add(input::SwitchInputPlugin)
+            .add(diagnostics::SwitchDiagnosticsPlugin)
+            .add(lifecycle::SwitchLifecyclePlugin::default())
+    }
 }
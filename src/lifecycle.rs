@@ -0,0 +1,367 @@
+//! Focus, sleep and exit handling.
+//!
+//! On the console the HOME menu, sleep mode and exit requests arrive as
+//! [`AppletMessage`]s; on desktop the same moments come from the primary
+//! window losing focus, being minimized or closed. The
+//! [`SwitchLifecyclePlugin`] turns both into [`LifecycleEvent`]s and tracks
+//! them in the [`SwitchLifecycle`] resource.
+//!
+//! While the game is out of focus or suspended, virtual time and playing
+//! audio are paused, so gameplay driven by [`Time`] stops too. Systems that
+//! don't use time can be paused with the [`is_running`] run condition:
+//!
+//! ```rust,no_run
+//! use bevy::prelude::*;
+//! use switchbrew_bevy::lifecycle::is_running;
+//! use switchbrew_bevy::prelude::*;
+//!
+//! fn spawn_crabs() {}
+//!
+//! App::new()
+//!     .add_plugins(DefaultPlugins)
+//!     .add_plugins(SwitchPlugin::default())
+//!     .add_systems(Update, spawn_crabs.run_if(is_running))
+//!     .run();
+//! ```
+//!
+//! Before the game exits, the [`BeforeExit`] schedule runs once. With the
+//! `save` feature it commits any staged [`SwitchSave`](crate::save::SwitchSave)
+//! changes; add systems to it for anything else that must reach the SD card.
+
+use bevy::ecs::message::{MessageCursor, Messages};
+use bevy::ecs::schedule::ScheduleLabel;
+use bevy::prelude::*;
+#[cfg(feature = "std")]
+use bevy::{
+    audio::{AudioSink, AudioSinkPlayback, SpatialAudioSink},
+    window::{PrimaryWindow, WindowCloseRequested, WindowFocused, WindowOccluded},
+};
+
+use crate::platform::AppletMessage;
+
+/// Plugin that tracks focus, sleep and exit requests in [`SwitchLifecycle`].
+///
+/// Applet messages are read on every platform. With `std`, focus, occlusion
+/// and close requests of the primary window are mapped to the same
+/// [`LifecycleEvent`]s.
+#[derive(Debug, Clone)]
+pub struct SwitchLifecyclePlugin {
+    /// Pause [`Time<Virtual>`] while the game is paused (default on).
+    pub pause_time: bool,
+    /// Pause playing audio while the game is paused (default on, `std` only).
+    pub pause_audio: bool,
+}
+
+impl Default for SwitchLifecyclePlugin {
+    fn default() -> Self {
+        Self {
+            pause_time: true,
+            pause_audio: true,
+        }
+    }
+}
+
+impl SwitchLifecyclePlugin {
+    /// Pause virtual time while the game is paused, or keep it running.
+    pub fn with_pause_time(mut self, pause_time: bool) -> Self {
+        self.pause_time = pause_time;
+        self
+    }
+
+    /// Pause playing audio while the game is paused, or keep it playing.
+    pub fn with_pause_audio(mut self, pause_audio: bool) -> Self {
+        self.pause_audio = pause_audio;
+        self
+    }
+}
+
+impl Plugin for SwitchLifecyclePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SwitchLifecycle>()
+            .add_message::<AppletMessage>()
+            .add_message::<LifecycleEvent>()
+            .init_schedule(BeforeExit)
+            .add_systems(
+                PreUpdate,
+                track_applet_messages.in_set(LifecycleSystems::Track),
+            )
+            .add_systems(Last, run_before_exit);
+
+        #[cfg(feature = "std")]
+        app.add_message::<WindowFocused>()
+            .add_message::<WindowOccluded>()
+            .add_message::<WindowCloseRequested>()
+            .add_systems(
+                PreUpdate,
+                track_window_messages
+                    .in_set(LifecycleSystems::Track)
+                    .after(track_applet_messages),
+            );
+
+        app.configure_sets(
+            PreUpdate,
+            LifecycleSystems::Pause.after(LifecycleSystems::Track),
+        );
+        if self.pause_time {
+            app.add_systems(PreUpdate, pause_time.in_set(LifecycleSystems::Pause));
+        }
+        #[cfg(feature = "std")]
+        if self.pause_audio {
+            app.add_systems(
+                PreUpdate,
+                (pause_audio::<AudioSink>, pause_audio::<SpatialAudioSink>)
+                    .in_set(LifecycleSystems::Pause),
+            );
+        }
+
+        #[cfg(feature = "save")]
+        app.add_systems(BeforeExit, commit_saves);
+    }
+}
+
+/// Change in focus, sleep or exit state.
+///
+/// Written once per change, in [`PreUpdate`]: losing focus twice, e.g. to the
+/// HOME menu and then to an overlay, sends one [`FocusLost`](Self::FocusLost).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Message)]
+pub enum LifecycleEvent {
+    /// The console is going to sleep, the game moved to the background, or
+    /// the window was minimized
+    Suspended,
+    /// The console woke up, the game left the background, or the window is
+    /// visible again
+    Resumed,
+    /// The HOME menu or an overlay took over, or the window lost focus
+    FocusLost,
+    /// The game is back in the foreground
+    FocusGained,
+    /// The system or the user asked the game to exit
+    ExitRequested,
+}
+
+/// Current focus, sleep and exit state.
+#[derive(Debug, Clone, PartialEq, Eq, Resource)]
+pub struct SwitchLifecycle {
+    focused: bool,
+    suspended: bool,
+    exit_requested: bool,
+    exited: bool,
+}
+
+impl Default for SwitchLifecycle {
+    fn default() -> Self {
+        Self {
+            focused: true,
+            suspended: false,
+            exit_requested: false,
+            exited: false,
+        }
+    }
+}
+
+impl SwitchLifecycle {
+    /// Check if the game is in the foreground.
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    /// Check if the console is asleep or the window minimized.
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
+    /// Check if gameplay should be paused: out of focus or suspended.
+    pub fn is_paused(&self) -> bool {
+        !self.focused || self.suspended
+    }
+
+    /// Check if the system or the user asked the game to exit.
+    pub fn exit_requested(&self) -> bool {
+        self.exit_requested
+    }
+
+    /// Apply an event, returning whether it changed the state.
+    fn apply(&mut self, event: LifecycleEvent) -> bool {
+        let (state, value) = match event {
+            LifecycleEvent::Suspended => (&mut self.suspended, true),
+            LifecycleEvent::Resumed => (&mut self.suspended, false),
+            LifecycleEvent::FocusLost => (&mut self.focused, false),
+            LifecycleEvent::FocusGained => (&mut self.focused, true),
+            LifecycleEvent::ExitRequested => (&mut self.exit_requested, true),
+        };
+        let changed = *state != value;
+        *state = value;
+        changed
+    }
+}
+
+/// Run condition that is true unless the game is [paused](SwitchLifecycle::is_paused).
+pub fn is_running(lifecycle: Res<SwitchLifecycle>) -> bool {
+    !lifecycle.is_paused()
+}
+
+/// Schedule run once before the game exits, from [`Last`].
+///
+/// Runs on the frame an exit is requested or an [`AppExit`] is written, and
+/// not at all if the game is killed or crashes.
+#[derive(ScheduleLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BeforeExit;
+
+/// System sets of the [`SwitchLifecyclePlugin`], in [`PreUpdate`].
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LifecycleSystems {
+    /// Update [`SwitchLifecycle`] and write [`LifecycleEvent`]s.
+    Track,
+    /// Pause or resume time and audio.
+    Pause,
+}
+
+/// Update the state and write an event if it changed.
+fn send(
+    lifecycle: &mut SwitchLifecycle,
+    events: &mut MessageWriter<LifecycleEvent>,
+    event: LifecycleEvent,
+) {
+    if lifecycle.apply(event) {
+        events.write(event);
+    }
+}
+
+/// System to map applet messages to lifecycle events.
+fn track_applet_messages(
+    mut messages: MessageReader<AppletMessage>,
+    mut lifecycle: ResMut<SwitchLifecycle>,
+    mut events: MessageWriter<LifecycleEvent>,
+) {
+    for message in messages.read() {
+        let event = match message {
+            AppletMessage::ExitRequested => LifecycleEvent::ExitRequested,
+            AppletMessage::FocusGained => LifecycleEvent::FocusGained,
+            AppletMessage::FocusLost => LifecycleEvent::FocusLost,
+            AppletMessage::Suspended => LifecycleEvent::Suspended,
+            AppletMessage::Resumed => LifecycleEvent::Resumed,
+            AppletMessage::OperationModeChanged(_) => continue,
+        };
+        send(&mut lifecycle, &mut events, event);
+    }
+}
+
+/// System to map the primary window's focus, occlusion and close requests
+/// to lifecycle events.
+#[cfg(feature = "std")]
+fn track_window_messages(
+    mut focused: MessageReader<WindowFocused>,
+    mut occluded: MessageReader<WindowOccluded>,
+    mut close_requested: MessageReader<WindowCloseRequested>,
+    primary: Query<(), With<PrimaryWindow>>,
+    mut lifecycle: ResMut<SwitchLifecycle>,
+    mut events: MessageWriter<LifecycleEvent>,
+) {
+    for message in focused.read().filter(|m| primary.contains(m.window)) {
+        let event = if message.focused {
+            LifecycleEvent::FocusGained
+        } else {
+            LifecycleEvent::FocusLost
+        };
+        send(&mut lifecycle, &mut events, event);
+    }
+    for message in occluded.read().filter(|m| primary.contains(m.window)) {
+        let event = if message.occluded {
+            LifecycleEvent::Suspended
+        } else {
+            LifecycleEvent::Resumed
+        };
+        send(&mut lifecycle, &mut events, event);
+    }
+    if close_requested
+        .read()
+        .any(|message| primary.contains(message.window))
+    {
+        send(&mut lifecycle, &mut events, LifecycleEvent::ExitRequested);
+    }
+}
+
+/// System to pause virtual time while the game is paused.
+///
+/// Time the game paused itself stays paused on resume.
+fn pause_time(
+    lifecycle: Res<SwitchLifecycle>,
+    time: Option<ResMut<Time<Virtual>>>,
+    mut paused_by_us: Local<bool>,
+) {
+    let Some(mut time) = time else {
+        return;
+    };
+    if lifecycle.is_paused() && !*paused_by_us {
+        if !time.is_paused() {
+            time.pause();
+            *paused_by_us = true;
+        }
+    } else if !lifecycle.is_paused() && *paused_by_us {
+        time.unpause();
+        *paused_by_us = false;
+    }
+}
+
+/// Marks an audio sink paused by the [`SwitchLifecyclePlugin`].
+#[cfg(feature = "std")]
+#[derive(Component)]
+struct PausedByLifecycle;
+
+/// System to pause playing sinks while the game is paused, and play them
+/// again on resume.
+///
+/// Sinks the game paused itself, or that start while paused, are left alone.
+#[cfg(feature = "std")]
+fn pause_audio<S: Component + AudioSinkPlayback>(
+    lifecycle: Res<SwitchLifecycle>,
+    playing: Query<(Entity, &S), Without<PausedByLifecycle>>,
+    paused: Query<(Entity, &S), With<PausedByLifecycle>>,
+    mut was_paused: Local<bool>,
+    mut commands: Commands,
+) {
+    if lifecycle.is_paused() == *was_paused {
+        return;
+    }
+    *was_paused = lifecycle.is_paused();
+
+    if *was_paused {
+        for (entity, sink) in &playing {
+            if !sink.is_paused() {
+                sink.pause();
+                commands.entity(entity).insert(PausedByLifecycle);
+            }
+        }
+    } else {
+        for (entity, sink) in &paused {
+            sink.play();
+            commands.entity(entity).remove::<PausedByLifecycle>();
+        }
+    }
+}
+
+/// System to run [`BeforeExit`] once, when an exit is requested or the app
+/// is about to exit.
+fn run_before_exit(world: &mut World, mut exits: Local<MessageCursor<AppExit>>) {
+    let exiting = world
+        .get_resource::<Messages<AppExit>>()
+        .is_some_and(|messages| exits.read(messages).count() > 0);
+    let mut lifecycle = world.resource_mut::<SwitchLifecycle>();
+    if lifecycle.exited || !(exiting || lifecycle.exit_requested) {
+        return;
+    }
+    lifecycle.exited = true;
+    world.run_schedule(BeforeExit);
+}
+
+/// System to commit staged save data before exit.
+///
+/// On error the changes stay staged, and a warning is logged.
+#[cfg(feature = "save")]
+fn commit_saves(save: Option<ResMut<crate::save::SwitchSave>>) {
+    if let Some(mut save) = save
+        && let Err(error) = save.commit()
+    {
+        log::warn!("Could not commit save data before exit: {error}");
+    }
+}
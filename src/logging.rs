@@ -0,0 +1,548 @@
+//! Logging backend for the console, emulators and desktop.
+//!
+//! [`SwitchLogger`] formats log lines without `std` and sends them to the
+//! HAL's [debug output](crate::platform::SwitchHal::debug_output):
+//! `svcOutputDebugString` on the console, which emulators show in their
+//! debug log, and stdout on desktop. It also keeps the most recent lines in
+//! a ring buffer, written to a log file next to the game's NRO every few
+//! seconds.
+//!
+//! On desktop, Bevy's `tracing` output reaches the logger through
+//! [`fmt_layer`], which replaces Bevy's formatter:
+//!
+//! ```rust,no_run
+//! use bevy::log::LogPlugin;
+//! use bevy::prelude::*;
+//! use switchbrew_bevy::prelude::*;
+//!
+//! App::new()
+//!     .add_plugins(DefaultPlugins.set(LogPlugin {
+//!         fmt_layer: switchbrew_bevy::logging::fmt_layer,
+//!         ..default()
+//!     }))
+//!     .add_plugins(SwitchPlugin::default())
+//!     .run();
+//! ```
+//!
+//! Without `std` there is no `tracing` subscriber, so the
+//! [`SwitchLogPlugin`] installs the logger for the [`log`] facade instead.
+//! Lines below [`SwitchConfig::log_level`] are dropped.
+
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+use core::time::Duration;
+
+use bevy::platform::sync::atomic::{AtomicU8, Ordering};
+use bevy::platform::sync::{Mutex, MutexGuard};
+use bevy::prelude::*;
+use log::Level;
+use serde::{Deserialize, Serialize};
+
+use crate::platform::{FsError, Hal, HostOs, SwitchConfig};
+
+/// Log file written on the console, next to the game's NRO.
+pub const DEFAULT_LOG_FILE: &str = "switchbrew.log";
+
+/// Default size of the ring buffer and the log file, in bytes.
+pub const DEFAULT_LOG_CAPACITY: usize = 64 * 1024;
+
+/// Plugin that provides the [`SwitchLogger`] resource and writes its log file.
+///
+/// The logger's level follows [`SwitchConfig::log_level`] when the app has a
+/// config. A logger inserted before the plugin is added, e.g. by
+/// [`fmt_layer`], is kept.
+#[derive(Debug, Clone)]
+pub struct SwitchLogPlugin {
+    /// Log file written through the HAL, or `None` to only keep lines in
+    /// memory. Defaults to [`DEFAULT_LOG_FILE`] on the console.
+    ///
+    /// Relative paths are [resolved](crate::platform::SwitchHal::game_path)
+    /// against the running NRO's directory.
+    pub file: Option<String>,
+    /// Size of the ring buffer and the log file, in bytes.
+    pub capacity: usize,
+    /// How often the log file is rewritten while new lines arrive.
+    pub flush_interval: Duration,
+}
+
+impl Default for SwitchLogPlugin {
+    fn default() -> Self {
+        Self {
+            file: (HostOs::current() == HostOs::Horizon).then(|| DEFAULT_LOG_FILE.into()),
+            capacity: DEFAULT_LOG_CAPACITY,
+            flush_interval: Duration::from_secs(2),
+        }
+    }
+}
+
+impl SwitchLogPlugin {
+    /// Write the log file to a path, on any platform.
+    pub fn with_file(mut self, path: impl Into<String>) -> Self {
+        self.file = Some(path.into());
+        self
+    }
+
+    /// Set the size of the ring buffer and the log file, in bytes.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+}
+
+impl Plugin for SwitchLogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Hal>().init_resource::<SwitchLogger>();
+
+        let hal = app.world().resource::<Hal>().clone();
+        let logger = app.world().resource::<SwitchLogger>().clone();
+        logger.set_capacity(self.capacity);
+        logger.set_file(self.file.as_deref().map(|file| hal.game_path(file)));
+        if let Some(config) = app.world().get_resource::<SwitchConfig>() {
+            logger.set_level(config.log_level);
+        }
+        #[cfg(not(feature = "std"))]
+        facade::install(logger);
+
+        app.insert_resource(LogFlushTimer {
+            interval: self.flush_interval,
+            last: None,
+        })
+        .add_systems(First, sync_log_level)
+        .add_systems(Last, flush_log_file);
+    }
+}
+
+/// Minimum level of the lines a [`SwitchLogger`] keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    /// Drop every line
+    Off,
+    /// Errors only
+    Error,
+    /// Warnings and errors
+    Warn,
+    /// Informational lines, warnings and errors
+    #[default]
+    Info,
+    /// Everything but trace lines
+    Debug,
+    /// Every line
+    Trace,
+}
+
+impl LogLevel {
+    const ALL: [LogLevel; 6] = [
+        LogLevel::Off,
+        LogLevel::Error,
+        LogLevel::Warn,
+        LogLevel::Info,
+        LogLevel::Debug,
+        LogLevel::Trace,
+    ];
+
+    /// Parse a level name (`off`, `error`, `warn`, `info`, `debug` or
+    /// `trace`, case-insensitive).
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.trim();
+        Self::ALL
+            .into_iter()
+            .find(|level| level.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Get the level's name, as written in config files.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            LogLevel::Off => "off",
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        }
+    }
+
+    /// Get the matching filter for the [`log`] facade.
+    pub const fn filter(&self) -> log::LevelFilter {
+        match self {
+            LogLevel::Off => log::LevelFilter::Off,
+            LogLevel::Error => log::LevelFilter::Error,
+            LogLevel::Warn => log::LevelFilter::Warn,
+            LogLevel::Info => log::LevelFilter::Info,
+            LogLevel::Debug => log::LevelFilter::Debug,
+            LogLevel::Trace => log::LevelFilter::Trace,
+        }
+    }
+
+    /// Check if lines at `level` are kept.
+    pub fn enables(&self, level: Level) -> bool {
+        level <= self.filter()
+    }
+}
+
+/// Format a log line, e.g. `[   12.345] WARN  crab_crossing: low memory`.
+///
+/// `uptime` is the time since the app started, from [`SwitchHal::now`](crate::platform::SwitchHal::now).
+pub fn format_line(
+    out: &mut impl Write,
+    uptime: Duration,
+    level: Level,
+    target: &str,
+    message: fmt::Arguments<'_>,
+) -> fmt::Result {
+    write!(
+        out,
+        "[{:>5}.{:03}] {:<5} {}: {}",
+        uptime.as_secs(),
+        uptime.subsec_millis(),
+        level,
+        target,
+        message
+    )
+}
+
+/// Resource that formats, outputs and buffers log lines.
+///
+/// Clones share their level, buffer and log file.
+#[derive(Resource, Clone)]
+pub struct SwitchLogger {
+    hal: Hal,
+    level: Arc<AtomicU8>,
+    ring: Arc<Mutex<LogRing>>,
+}
+
+#[derive(Debug)]
+struct LogRing {
+    lines: VecDeque<String>,
+    bytes: usize,
+    capacity: usize,
+    file: Option<String>,
+    dirty: bool,
+}
+
+/// Uses the app's [`Hal`], creating it if needed.
+impl FromWorld for SwitchLogger {
+    fn from_world(world: &mut World) -> Self {
+        Self::new(world.get_resource_or_init::<Hal>().clone())
+    }
+}
+
+impl SwitchLogger {
+    /// Create a logger writing to a HAL's debug output, at [`LogLevel::Info`].
+    pub fn new(hal: Hal) -> Self {
+        Self {
+            hal,
+            level: Arc::new(AtomicU8::new(LogLevel::Info as u8)),
+            ring: Arc::new(Mutex::new(LogRing {
+                lines: VecDeque::new(),
+                bytes: 0,
+                capacity: DEFAULT_LOG_CAPACITY,
+                file: None,
+                dirty: false,
+            })),
+        }
+    }
+
+    /// Get the minimum level of the lines kept.
+    pub fn level(&self) -> LogLevel {
+        LogLevel::ALL[usize::from(self.level.load(Ordering::Relaxed))]
+    }
+
+    /// Set the minimum level of the lines kept.
+    pub fn set_level(&self, level: LogLevel) {
+        self.level.store(level as u8, Ordering::Relaxed);
+        #[cfg(not(feature = "std"))]
+        log::set_max_level(level.filter());
+    }
+
+    /// Check if lines at `level` are kept.
+    pub fn enabled(&self, level: Level) -> bool {
+        self.level().enables(level)
+    }
+
+    /// Format a line and send it to the debug output and the ring buffer.
+    pub fn log(&self, level: Level, target: &str, message: fmt::Arguments<'_>) {
+        if !self.enabled(level) {
+            return;
+        }
+        let mut line = String::new();
+        if format_line(&mut line, self.hal.now(), level, target, message).is_err() {
+            return;
+        }
+        self.hal.debug_output(&line);
+        self.ring().push(line);
+    }
+
+    /// Get the most recent `count` lines in the buffer, oldest first.
+    pub fn recent_lines(&self, count: usize) -> Vec<String> {
+        let ring = self.ring();
+        let skip = ring.lines.len().saturating_sub(count);
+        ring.lines.iter().skip(skip).cloned().collect()
+    }
+
+    /// Get the buffered lines as the log file's contents.
+    pub fn contents(&self) -> String {
+        self.ring().contents()
+    }
+
+    /// Set the size of the ring buffer, dropping the oldest lines if needed.
+    pub fn set_capacity(&self, capacity: usize) {
+        let mut ring = self.ring();
+        ring.capacity = capacity;
+        ring.trim();
+    }
+
+    /// Get the log file path.
+    pub fn file(&self) -> Option<String> {
+        self.ring().file.clone()
+    }
+
+    /// Set the log file path, or `None` to only keep lines in memory.
+    pub fn set_file(&self, file: Option<String>) {
+        let mut ring = self.ring();
+        ring.dirty = file.is_some() && !ring.lines.is_empty();
+        ring.file = file;
+    }
+
+    /// Write the buffer to the log file if lines arrived since the last write.
+    pub fn flush(&self) -> Result<(), FsError> {
+        let (path, contents) = {
+            let mut ring = self.ring();
+            let Some(path) = ring.file.clone().filter(|_| ring.dirty) else {
+                return Ok(());
+            };
+            ring.dirty = false;
+            (path, ring.contents())
+        };
+        self.hal.write_file(&path, contents.as_bytes())
+    }
+
+    fn ring(&self) -> MutexGuard<'_, LogRing> {
+        self.ring
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl fmt::Debug for SwitchLogger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SwitchLogger")
+            .field("hal", &self.hal)
+            .field("level", &self.level())
+            .field("file", &self.file())
+            .finish_non_exhaustive()
+    }
+}
+
+impl LogRing {
+    fn push(&mut self, line: String) {
+        self.bytes += line.len() + 1;
+        self.lines.push_back(line);
+        self.dirty = self.file.is_some();
+        self.trim();
+    }
+
+    /// Drop the oldest lines until the buffer fits its capacity.
+    fn trim(&mut self) {
+        while self.bytes > self.capacity {
+            let Some(line) = self.lines.pop_front() else {
+                break;
+            };
+            self.bytes -= line.len() + 1;
+        }
+    }
+
+    fn contents(&self) -> String {
+        let mut contents = String::with_capacity(self.bytes);
+        for line in &self.lines {
+            contents.push_str(line);
+            contents.push('\n');
+        }
+        contents
+    }
+}
+
+/// When the log file was last written.
+#[derive(Resource)]
+struct LogFlushTimer {
+    interval: Duration,
+    last: Option<Duration>,
+}
+
+/// System to apply [`SwitchConfig::log_level`] to the logger.
+fn sync_log_level(config: Option<Res<SwitchConfig>>, logger: Res<SwitchLogger>) {
+    if let Some(config) = config.filter(|config| config.is_changed()) {
+        logger.set_level(config.log_level);
+    }
+}
+
+/// System to write the log file every flush interval, and on exit.
+fn flush_log_file(
+    logger: Res<SwitchLogger>,
+    hal: Res<Hal>,
+    mut timer: ResMut<LogFlushTimer>,
+    mut exit: MessageReader<AppExit>,
+) {
+    let now = hal.now();
+    let exiting = exit.read().count() > 0;
+    let due = timer
+        .last
+        .is_none_or(|last| now.saturating_sub(last) >= timer.interval);
+    if !(due || exiting) {
+        return;
+    }
+    timer.last = Some(now);
+
+    if let Err(error) = logger.flush() {
+        let path = logger.file().unwrap_or_default();
+        logger.set_file(None);
+        hal.log(
+            Level::Warn,
+            &alloc::format!("Could not write log file {path}, disabling it: {error}"),
+        );
+    }
+}
+
+/// Replace Bevy's log formatter with the [`SwitchLogger`], for
+/// [`LogPlugin::fmt_layer`](bevy::log::LogPlugin::fmt_layer).
+///
+/// Creates the app's [`Hal`] and [`SwitchLogger`] if it has none yet, so
+/// insert a custom [`Hal`] before adding the `LogPlugin`. Lines are still
+/// filtered by the `LogPlugin`'s level first.
+#[cfg(feature = "std")]
+pub fn fmt_layer(app: &mut App) -> Option<bevy::log::BoxedFmtLayer> {
+    let logger = app
+        .world_mut()
+        .get_resource_or_init::<SwitchLogger>()
+        .clone();
+    Some(Box::new(SwitchLogLayer::new(logger)))
+}
+
+/// `tracing` layer that sends events to a [`SwitchLogger`].
+///
+/// Fields other than the message are appended as `name=value`.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct SwitchLogLayer {
+    logger: SwitchLogger,
+}
+
+#[cfg(feature = "std")]
+impl SwitchLogLayer {
+    /// Create a layer for a logger.
+    pub fn new(logger: SwitchLogger) -> Self {
+        Self { logger }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: bevy::log::tracing::Subscriber> bevy::log::tracing_subscriber::Layer<S> for SwitchLogLayer {
+    fn on_event(
+        &self,
+        event: &bevy::log::tracing::Event<'_>,
+        _ctx: bevy::log::tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        use bevy::log::tracing::Level as TracingLevel;
+
+        let metadata = event.metadata();
+        let level = match *metadata.level() {
+            TracingLevel::ERROR => Level::Error,
+            TracingLevel::WARN => Level::Warn,
+            TracingLevel::INFO => Level::Info,
+            TracingLevel::DEBUG => Level::Debug,
+            TracingLevel::TRACE => Level::Trace,
+        };
+        if !self.logger.enabled(level) {
+            return;
+        }
+        let mut visitor = EventVisitor::default();
+        event.record(&mut visitor);
+        let target = visitor.target.as_deref().unwrap_or(metadata.target());
+        self.logger
+            .log(level, target, format_args!("{}", visitor.message));
+    }
+}
+
+/// Collects an event's message and fields.
+#[cfg(feature = "std")]
+#[derive(Default)]
+struct EventVisitor {
+    message: String,
+    /// Target of a record forwarded from the `log` facade.
+    target: Option<String>,
+}
+
+#[cfg(feature = "std")]
+impl bevy::log::tracing::field::Visit for EventVisitor {
+    fn record_str(&mut self, field: &bevy::log::tracing::field::Field, value: &str) {
+        if field.name() == "log.target" {
+            self.target = Some(value.into());
+        } else {
+            self.record_debug(field, &value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &bevy::log::tracing::field::Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            "message" => {
+                let _ = write!(self.message, "{value:?}");
+            }
+            // The rest of the `log` facade's metadata
+            name if name.starts_with("log.") => {}
+            name => {
+                let _ = write!(self.message, " {name}={value:?}");
+            }
+        }
+    }
+}
+
+/// The [`log`] facade, for builds without a `tracing` subscriber.
+#[cfg(not(feature = "std"))]
+mod facade {
+    use bevy::platform::sync::Mutex;
+
+    use super::SwitchLogger;
+
+    /// Forwards records to the most recently installed logger.
+    struct Facade(Mutex<Option<SwitchLogger>>);
+
+    static FACADE: Facade = Facade(Mutex::new(None));
+
+    impl Facade {
+        fn logger(&self) -> Option<SwitchLogger> {
+            self.0
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .clone()
+        }
+    }
+
+    impl log::Log for Facade {
+        fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+            self.logger()
+                .is_some_and(|logger| logger.enabled(metadata.level()))
+        }
+
+        fn log(&self, record: &log::Record<'_>) {
+            if let Some(logger) = self.logger() {
+                logger.log(record.level(), record.target(), *record.args());
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    /// Send [`log`] records to `logger`, replacing the previous app's.
+    pub(super) fn install(logger: SwitchLogger) {
+        log::set_max_level(logger.level().filter());
+        *FACADE
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(logger);
+        // Fails if already set, by us for an earlier app or by the game
+        let _ = log::set_logger(&FACADE);
+    }
+}
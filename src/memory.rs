@@ -0,0 +1,489 @@
+//! Heap usage tracking against the Switch memory budget.
+//!
+//! Wrap the global allocator in a [`TrackingAllocator`] and add
+//! [`SwitchMemoryPlugin`] to see current and peak usage in the
+//! [`MemoryUsage`] resource, broken down by [`MemoryTag`]:
+//!
+//! ```rust,no_run
+//! use std::alloc::System;
+//!
+//! use bevy::prelude::*;
+//! use switchbrew_bevy::memory::{SwitchMemoryPlugin, TrackingAllocator};
+//! use switchbrew_bevy::prelude::*;
+//!
+//! #[global_allocator]
+//! static ALLOCATOR: TrackingAllocator<System> = TrackingAllocator::new(System);
+//!
+//! fn main() {
+//!     App::new()
+//!         .add_plugins(DefaultPlugins)
+//!         .add_plugins(SwitchPlugin::default())
+//!         .add_plugins(SwitchMemoryPlugin::new(ALLOCATOR.tracker()))
+//!         .run();
+//! }
+//! ```
+//!
+//! The allocator only uses `core`, so it also works in `no_std` builds with
+//! `alloc` on top of the console's allocator.
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::fmt;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use bevy::prelude::*;
+#[cfg(not(feature = "std"))]
+use log::warn;
+
+use crate::platform::{PlatformInfo, SwitchConfig};
+
+/// Number of distinct [`MemoryTag`]s.
+pub const MAX_TAGS: usize = 16;
+
+/// Bytes reserved in front of each allocation to remember its tag.
+const HEADER_SIZE: usize = core::mem::size_of::<usize>();
+
+/// Plugin that reports [`TrackingAllocator`] usage in the [`MemoryUsage`] resource.
+///
+/// The budget defaults to the memory the system gives the game on the
+/// console, [`PlatformInfo::memory_limit`], and elsewhere to the memory budget
+/// of the configured [`HardwareProfile`](crate::platform::HardwareProfile),
+/// which depends on the [`LaunchMode`](crate::platform::LaunchMode). A
+/// warning is logged when usage crosses [`SwitchMemoryPlugin::warn_fraction`]
+/// of the budget and again when it exceeds the budget.
+pub struct SwitchMemoryPlugin {
+    /// Tracker of the global allocator.
+    pub tracker: &'static AllocationTracker,
+    /// Budget in bytes, or `None` for the system's or hardware profile's.
+    pub budget: Option<usize>,
+    /// Fail allocations that would exceed the budget, like the console would.
+    pub enforce: bool,
+    /// Fraction of the budget that triggers a warning.
+    pub warn_fraction: f32,
+}
+
+impl SwitchMemoryPlugin {
+    /// Report usage of a tracker, e.g. `ALLOCATOR.tracker()`.
+    pub fn new(tracker: &'static AllocationTracker) -> Self {
+        Self {
+            tracker,
+            budget: None,
+            enforce: false,
+            warn_fraction: 0.9,
+        }
+    }
+
+    /// Set the budget instead of using the system's or hardware profile's.
+    pub fn with_budget(mut self, bytes: usize) -> Self {
+        self.budget = Some(bytes);
+        self
+    }
+
+    /// Fail allocations over budget instead of only warning.
+    pub fn with_enforce(mut self, enforce: bool) -> Self {
+        self.enforce = enforce;
+        self
+    }
+}
+
+impl Plugin for SwitchMemoryPlugin {
+    fn build(&self, app: &mut App) {
+        self.tracker.set_enforce(self.enforce);
+
+        app.insert_resource(MemoryTracker {
+            tracker: self.tracker,
+            budget: self.budget,
+            warn_fraction: self.warn_fraction,
+        })
+        .insert_resource(self.tracker.usage())
+        .add_systems(Last, (update_memory_budget, update_memory_usage).chain());
+    }
+}
+
+/// Identifies what an allocation is for, e.g. assets or audio.
+///
+/// Allocations made inside [`with_tag`] are counted under that tag. Tags
+/// above [`MemoryTag::GAME`] are free for game-specific use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct MemoryTag(u8);
+
+impl MemoryTag {
+    /// Allocations outside any [`with_tag`] scope.
+    pub const UNTAGGED: Self = Self(0);
+    /// Engine and ECS data.
+    pub const ENGINE: Self = Self(1);
+    /// Loaded assets.
+    pub const ASSETS: Self = Self(2);
+    /// Audio buffers.
+    pub const AUDIO: Self = Self(3);
+    /// Render data.
+    pub const RENDER: Self = Self(4);
+    /// Game logic.
+    pub const GAME: Self = Self(5);
+
+    /// Create a tag from its index, or `None` if it is not below [`MAX_TAGS`].
+    pub const fn new(index: u8) -> Option<Self> {
+        if (index as usize) < MAX_TAGS {
+            Some(Self(index))
+        } else {
+            None
+        }
+    }
+
+    /// Get the tag's index.
+    pub const fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl fmt::Display for MemoryTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            MemoryTag::UNTAGGED => f.write_str("untagged"),
+            MemoryTag::ENGINE => f.write_str("engine"),
+            MemoryTag::ASSETS => f.write_str("assets"),
+            MemoryTag::AUDIO => f.write_str("audio"),
+            MemoryTag::RENDER => f.write_str("render"),
+            MemoryTag::GAME => f.write_str("game"),
+            MemoryTag(index) => write!(f, "tag {index}"),
+        }
+    }
+}
+
+/// Run `f` with its allocations counted under `tag`.
+///
+/// The tag is per thread with `std`; without it there is a single global tag.
+pub fn with_tag<R>(tag: MemoryTag, f: impl FnOnce() -> R) -> R {
+    struct Restore(MemoryTag);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            current_tag::set(self.0);
+        }
+    }
+
+    let _restore = Restore(current_tag::get());
+    current_tag::set(tag);
+    f()
+}
+
+/// Get the tag new allocations are counted under.
+pub fn current_tag() -> MemoryTag {
+    current_tag::get()
+}
+
+#[cfg(feature = "std")]
+mod current_tag {
+    use core::cell::Cell;
+
+    use super::MemoryTag;
+
+    std::thread_local! {
+        static TAG: Cell<MemoryTag> = const { Cell::new(MemoryTag::UNTAGGED) };
+    }
+
+    pub(super) fn get() -> MemoryTag {
+        // Unavailable while the thread is being torn down
+        TAG.try_with(Cell::get).unwrap_or_default()
+    }
+
+    pub(super) fn set(tag: MemoryTag) {
+        let _ = TAG.try_with(|current| current.set(tag));
+    }
+}
+
+#[cfg(not(feature = "std"))]
+mod current_tag {
+    use core::sync::atomic::{AtomicU8, Ordering};
+
+    use super::MemoryTag;
+
+    static TAG: AtomicU8 = AtomicU8::new(0);
+
+    pub(super) fn get() -> MemoryTag {
+        MemoryTag(TAG.load(Ordering::Relaxed))
+    }
+
+    pub(super) fn set(tag: MemoryTag) {
+        TAG.store(tag.0, Ordering::Relaxed);
+    }
+}
+
+/// Global allocator wrapper that tracks heap usage.
+///
+/// Each allocation carries a small header recording its [`MemoryTag`], so
+/// usage per tag stays correct when memory is freed elsewhere.
+pub struct TrackingAllocator<A> {
+    inner: A,
+    tracker: AllocationTracker,
+}
+
+impl<A> TrackingAllocator<A> {
+    /// Wrap an allocator, e.g. `std::alloc::System`.
+    pub const fn new(inner: A) -> Self {
+        Self {
+            inner,
+            tracker: AllocationTracker::new(),
+        }
+    }
+
+    /// Get the usage counters.
+    pub fn tracker(&self) -> &AllocationTracker {
+        &self.tracker
+    }
+
+    /// Get the layout including the tag header, and the offset of the user data.
+    fn padded(layout: Layout) -> Option<(Layout, usize)> {
+        let offset = layout.align().max(HEADER_SIZE);
+        let size = layout.size().checked_add(offset)?;
+        let padded = Layout::from_size_align(size, offset).ok()?;
+        Some((padded, offset))
+    }
+}
+
+// SAFETY: allocation is delegated to `inner` with a layout that is at least
+// as large and as aligned as requested; the returned pointer is offset by a
+// multiple of the requested alignment and stays within the allocation.
+unsafe impl<A: GlobalAlloc> GlobalAlloc for TrackingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let Some((padded, offset)) = Self::padded(layout) else {
+            return core::ptr::null_mut();
+        };
+        let tag = current_tag::get();
+        if !self.tracker.reserve(tag, layout.size()) {
+            return core::ptr::null_mut();
+        }
+
+        // SAFETY: `padded` has a non-zero size
+        let base = unsafe { self.inner.alloc(padded) };
+        if base.is_null() {
+            self.tracker.release(tag, layout.size());
+            return base;
+        }
+        // SAFETY: the header fits in the `offset` bytes in front of the data
+        unsafe {
+            base.write(tag.0);
+            base.add(offset)
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let Some((padded, offset)) = Self::padded(layout) else {
+            return;
+        };
+        // SAFETY: `ptr` was returned by `alloc` with the same layout
+        unsafe {
+            let base = ptr.sub(offset);
+            self.tracker.release(MemoryTag(base.read()), layout.size());
+            self.inner.dealloc(base, padded);
+        }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let (Some((padded, offset)), Some(new_padded_size)) = (
+            Self::padded(layout),
+            new_size.checked_add(layout.align().max(HEADER_SIZE)),
+        ) else {
+            return core::ptr::null_mut();
+        };
+
+        // SAFETY: `ptr` was returned by `alloc` with the same layout, and the
+        // header stays in place at the start of the reallocated block
+        unsafe {
+            let base = ptr.sub(offset);
+            let tag = MemoryTag(base.read());
+            let grown = new_size.saturating_sub(layout.size());
+            if grown > 0 && !self.tracker.reserve(tag, grown) {
+                return core::ptr::null_mut();
+            }
+
+            let new_base = self.inner.realloc(base, padded, new_padded_size);
+            if new_base.is_null() {
+                self.tracker.release(tag, grown);
+                return new_base;
+            }
+            let shrunk = layout.size().saturating_sub(new_size);
+            self.tracker.release(tag, shrunk);
+            new_base.add(offset)
+        }
+    }
+}
+
+/// Heap usage counters of a [`TrackingAllocator`].
+pub struct AllocationTracker {
+    current: AtomicUsize,
+    peak: AtomicUsize,
+    allocations: AtomicUsize,
+    budget: AtomicUsize,
+    enforce: AtomicBool,
+    denied: AtomicUsize,
+    tags: [AtomicUsize; MAX_TAGS],
+}
+
+impl Default for AllocationTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AllocationTracker {
+    /// Create empty counters with no budget.
+    pub const fn new() -> Self {
+        Self {
+            current: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+            allocations: AtomicUsize::new(0),
+            budget: AtomicUsize::new(usize::MAX),
+            enforce: AtomicBool::new(false),
+            denied: AtomicUsize::new(0),
+            tags: [const { AtomicUsize::new(0) }; MAX_TAGS],
+        }
+    }
+
+    /// Set the budget in bytes (`usize::MAX` for none).
+    pub fn set_budget(&self, bytes: usize) {
+        self.budget.store(bytes, Ordering::Relaxed);
+    }
+
+    /// Fail allocations that would exceed the budget.
+    pub fn set_enforce(&self, enforce: bool) {
+        self.enforce.store(enforce, Ordering::Relaxed);
+    }
+
+    /// Get a snapshot of the counters.
+    pub fn usage(&self) -> MemoryUsage {
+        let budget = self.budget.load(Ordering::Relaxed);
+        MemoryUsage {
+            current: self.current.load(Ordering::Relaxed),
+            peak: self.peak.load(Ordering::Relaxed),
+            allocations: self.allocations.load(Ordering::Relaxed),
+            budget: (budget != usize::MAX).then_some(budget),
+            denied: self.denied.load(Ordering::Relaxed),
+            tags: core::array::from_fn(|index| self.tags[index].load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Count `size` bytes as allocated, unless that would exceed an enforced budget.
+    fn reserve(&self, tag: MemoryTag, size: usize) -> bool {
+        let current = self.current.fetch_add(size, Ordering::Relaxed) + size;
+        if self.enforce.load(Ordering::Relaxed) && current > self.budget.load(Ordering::Relaxed) {
+            self.current.fetch_sub(size, Ordering::Relaxed);
+            self.denied.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+
+        self.peak.fetch_max(current, Ordering::Relaxed);
+        self.allocations.fetch_add(1, Ordering::Relaxed);
+        self.tags[tag.index()].fetch_add(size, Ordering::Relaxed);
+        true
+    }
+
+    /// Count `size` bytes as freed.
+    fn release(&self, tag: MemoryTag, size: usize) {
+        self.current.fetch_sub(size, Ordering::Relaxed);
+        self.tags[tag.index()].fetch_sub(size, Ordering::Relaxed);
+    }
+}
+
+/// Snapshot of heap usage, updated every frame by [`SwitchMemoryPlugin`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Resource)]
+pub struct MemoryUsage {
+    /// Bytes currently allocated.
+    pub current: usize,
+    /// Highest number of bytes allocated at once.
+    pub peak: usize,
+    /// Number of allocations and growing reallocations so far.
+    pub allocations: usize,
+    /// Budget in bytes, if set.
+    pub budget: Option<usize>,
+    /// Number of allocations refused because of the budget.
+    pub denied: usize,
+    /// Bytes currently allocated per [`MemoryTag`] index.
+    pub tags: [usize; MAX_TAGS],
+}
+
+impl MemoryUsage {
+    /// Get the bytes currently allocated under a tag.
+    pub fn tagged(&self, tag: MemoryTag) -> usize {
+        self.tags[tag.index()]
+    }
+
+    /// Get current usage as a fraction of the budget.
+    pub fn budget_fraction(&self) -> Option<f32> {
+        self.budget
+            .map(|budget| self.current as f32 / budget.max(1) as f32)
+    }
+
+    /// Check if usage is above the budget.
+    pub fn over_budget(&self) -> bool {
+        self.budget.is_some_and(|budget| self.current > budget)
+    }
+}
+
+/// Tracker and settings of [`SwitchMemoryPlugin`].
+#[derive(Resource)]
+struct MemoryTracker {
+    tracker: &'static AllocationTracker,
+    budget: Option<usize>,
+    warn_fraction: f32,
+}
+
+/// System to keep the budget in sync with the configured hardware.
+fn update_memory_budget(
+    memory: Res<MemoryTracker>,
+    info: Option<Res<PlatformInfo>>,
+    config: Option<Res<SwitchConfig>>,
+) {
+    let profile_changed = config.as_ref().is_some_and(|config| config.is_changed());
+    if !memory.is_added() && !profile_changed {
+        return;
+    }
+
+    let limit = info
+        .and_then(|info| info.memory_limit)
+        .or_else(|| config.map(|config| config.hardware().memory_budget));
+    let budget = memory
+        .budget
+        .or_else(|| limit.map(|limit| usize::try_from(limit).unwrap_or(usize::MAX)));
+    memory.tracker.set_budget(budget.unwrap_or(usize::MAX));
+}
+
+/// System to publish usage and warn when nearing or exceeding the budget.
+fn update_memory_usage(
+    memory: Res<MemoryTracker>,
+    mut usage: ResMut<MemoryUsage>,
+    mut warned: Local<(bool, bool)>,
+) {
+    let previous = *usage;
+    let current = memory.tracker.usage();
+    if current != previous {
+        *usage = current;
+    }
+
+    let Some(fraction) = current.budget_fraction() else {
+        return;
+    };
+    let (near, over) = &mut *warned;
+    if current.over_budget() && !*over {
+        warn!(
+            "Memory usage {} MiB exceeds the {} MiB budget",
+            current.current / (1024 * 1024),
+            current.budget.unwrap_or_default() / (1024 * 1024)
+        );
+    } else if fraction >= memory.warn_fraction && !*near {
+        warn!(
+            "Memory usage at {:.0}% of the budget ({} MiB)",
+            fraction * 100.0,
+            current.current / (1024 * 1024)
+        );
+    }
+    if current.denied > previous.denied {
+        warn!(
+            "{} allocations refused over the memory budget",
+            current.denied - previous.denied
+        );
+    }
+
+    // Warn again only after dropping back below the threshold
+    *over = current.over_budget();
+    *near = fraction >= memory.warn_fraction;
+}
@@ -0,0 +1,402 @@
+//! NRO executables, the format homebrew ships in.
+//!
+//! An NRO holds the program's code, read-only data and data segments,
+//! taken from a linked ELF, followed by an asset section with the icon, the
+//! [`Nacp`] metadata shown by the homebrew menu, and a RomFS image.
+//!
+//! ```rust,no_run
+//! use switchbrew_bevy::nro::{Nacp, NroBuilder, NroSegments};
+//!
+//! let elf = std::fs::read("target/aarch64-nintendo-switch-freestanding/release/game").unwrap();
+//! let nro = NroBuilder::new(NroSegments::from_elf(&elf).unwrap())
+//!     .with_nacp(&Nacp::new("Crab Crossing", "Ibrahim Cesar", "0.2.0"))
+//!     .unwrap()
+//!     .build();
+//! ```
+//!
+//! [`Nro::parse`] reads one back, with its header, icon, NACP and RomFS,
+//! for tests and `cargo switchbrew inspect`.
+//!
+//! With the `package` feature, [`SwitchMetadata`] reads the NACP fields,
+//! icon and RomFS directory from `[package.metadata.switch]` in
+//! `Cargo.toml`; `cargo switchbrew package` puts it all together.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::romfs::RomFs;
+
+mod elf;
+#[cfg(feature = "package")]
+mod metadata;
+mod nacp;
+
+pub use elf::NroSegments;
+#[cfg(feature = "package")]
+pub use metadata::SwitchMetadata;
+pub use nacp::{Nacp, NACP_SIZE};
+
+/// Offset of the NRO header, after the start of the code segment.
+pub(crate) const HEADER_OFFSET: usize = 0x10;
+
+/// Size of the NRO header.
+pub(crate) const HEADER_SIZE: usize = 0x70;
+
+/// Size of the asset section header.
+pub(crate) const ASSET_HEADER_SIZE: usize = 0x38;
+
+/// Segments are page aligned.
+const PAGE_SIZE: usize = 0x1000;
+
+/// Width and height of the icon in pixels.
+pub const ICON_SIZE: u16 = 256;
+
+/// Error packaging or reading an NRO.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NroError {
+    /// The NRO header or asset section is missing, truncated or out of bounds
+    InvalidNro(String),
+    /// The ELF is not a 64-bit little-endian executable laid out for an NRO
+    InvalidElf(String),
+    /// The icon is not a 256×256 JPEG
+    InvalidIcon(String),
+    /// A NACP field is too long, or the NACP is truncated
+    InvalidNacp(String),
+    /// `[package.metadata.switch]` is missing a field or has an invalid one
+    InvalidMetadata(String),
+}
+
+impl fmt::Display for NroError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NroError::InvalidNro(message) => write!(f, "invalid NRO: {message}"),
+            NroError::InvalidElf(message) => write!(f, "invalid ELF: {message}"),
+            NroError::InvalidIcon(message) => write!(f, "invalid icon: {message}"),
+            NroError::InvalidNacp(message) => write!(f, "invalid NACP: {message}"),
+            NroError::InvalidMetadata(message) => {
+                write!(f, "invalid [package.metadata.switch]: {message}")
+            }
+        }
+    }
+}
+
+impl core::error::Error for NroError {}
+
+/// Assembles an NRO from ELF segments and assets.
+#[derive(Debug, Clone)]
+pub struct NroBuilder {
+    segments: NroSegments,
+    icon: Option<Vec<u8>>,
+    nacp: Option<Vec<u8>>,
+    romfs: Option<Vec<u8>>,
+}
+
+impl NroBuilder {
+    /// Package some segments, without assets.
+    pub fn new(segments: NroSegments) -> Self {
+        Self {
+            segments,
+            icon: None,
+            nacp: None,
+            romfs: None,
+        }
+    }
+
+    /// Add the icon, a 256×256 JPEG.
+    pub fn with_icon(mut self, icon: Vec<u8>) -> Result<Self, NroError> {
+        validate_icon(&icon)?;
+        self.icon = Some(icon);
+        Ok(self)
+    }
+
+    /// Add the NACP metadata.
+    pub fn with_nacp(mut self, nacp: &Nacp) -> Result<Self, NroError> {
+        self.nacp = Some(nacp.to_bytes()?);
+        Ok(self)
+    }
+
+    /// Add a RomFS image, e.g. from [`RomFsBuilder`](crate::romfs::RomFsBuilder).
+    pub fn with_romfs(mut self, romfs: Vec<u8>) -> Self {
+        self.romfs = Some(romfs);
+        self
+    }
+
+    /// Build the NRO.
+    pub fn build(&self) -> Vec<u8> {
+        let segments = &self.segments;
+        let text_len = align(segments.text.len(), PAGE_SIZE);
+        let ro_len = align(segments.ro.len(), PAGE_SIZE);
+        let data_len = align(segments.data.len(), PAGE_SIZE);
+        let size = text_len + ro_len + data_len;
+
+        let mut nro = Vec::with_capacity(size);
+        for (segment, len) in [
+            (&segments.text, text_len),
+            (&segments.ro, ro_len),
+            (&segments.data, data_len),
+        ] {
+            nro.extend_from_slice(segment);
+            nro.resize(nro.len() + len - segment.len(), 0);
+        }
+
+        // The header replaces the placeholder crt0 leaves after its entry point
+        let header = &mut nro[HEADER_OFFSET..HEADER_OFFSET + HEADER_SIZE];
+        header.fill(0);
+        header[..4].copy_from_slice(b"NRO0");
+        write_u32(header, 0x08, size as u32);
+        for (index, (offset, len)) in [
+            (0, text_len),
+            (text_len, ro_len),
+            (text_len + ro_len, data_len),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            write_u32(header, 0x10 + index * 8, offset as u32);
+            write_u32(header, 0x14 + index * 8, len as u32);
+        }
+        write_u32(header, 0x28, align(segments.bss_size, PAGE_SIZE) as u32);
+        let build_id = &segments.build_id[..segments.build_id.len().min(0x20)];
+        header[0x30..0x30 + build_id.len()].copy_from_slice(build_id);
+        for (index, (offset, len)) in [segments.api_info, segments.dynstr, segments.dynsym]
+            .into_iter()
+            .enumerate()
+        {
+            write_u32(header, 0x58 + index * 8, offset);
+            write_u32(header, 0x5c + index * 8, len);
+        }
+
+        if self.icon.is_some() || self.nacp.is_some() || self.romfs.is_some() {
+            self.write_assets(&mut nro);
+        }
+        nro
+    }
+
+    /// Append the asset section: its header, then the icon, NACP and RomFS.
+    fn write_assets(&self, nro: &mut Vec<u8>) {
+        let start = nro.len();
+        nro.resize(start + ASSET_HEADER_SIZE, 0);
+        nro[start..start + 4].copy_from_slice(b"ASET");
+
+        let mut offset = ASSET_HEADER_SIZE;
+        for (index, asset) in [&self.icon, &self.nacp, &self.romfs]
+            .into_iter()
+            .enumerate()
+        {
+            let asset = asset.as_deref().unwrap_or_default();
+            let field = start + 0x08 + index * 0x10;
+            if !asset.is_empty() {
+                write_u64(nro, field, offset as u64);
+                write_u64(nro, field + 8, asset.len() as u64);
+            }
+            nro.extend_from_slice(asset);
+            offset += asset.len();
+        }
+    }
+}
+
+/// Header of an NRO.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NroHeader {
+    /// Size of the segments, where the asset section starts.
+    pub size: u32,
+    /// File offset and size of the code.
+    pub text: (u32, u32),
+    /// File offset and size of the read-only data.
+    pub ro: (u32, u32),
+    /// File offset and size of the data.
+    pub data: (u32, u32),
+    /// Size of the zeroed data after the data segment.
+    pub bss_size: u32,
+    /// GNU build ID, zero padded.
+    pub build_id: [u8; 0x20],
+    /// Offset and size of `.api_info`, relative to the read-only data.
+    pub api_info: (u32, u32),
+    /// Offset and size of `.dynstr`, relative to the read-only data.
+    pub dynstr: (u32, u32),
+    /// Offset and size of `.dynsym`, relative to the read-only data.
+    pub dynsym: (u32, u32),
+}
+
+/// An NRO read back from its bytes.
+#[derive(Debug, Clone)]
+pub struct Nro {
+    /// The NRO header.
+    pub header: NroHeader,
+    /// Icon from the asset section, a JPEG.
+    pub icon: Option<Vec<u8>>,
+    /// Metadata from the asset section.
+    pub nacp: Option<Nacp>,
+    /// RomFS from the asset section.
+    pub romfs: Option<RomFs>,
+}
+
+impl Nro {
+    /// Read an NRO's header and assets.
+    pub fn parse(nro: &[u8]) -> Result<Self, NroError> {
+        let invalid = |message: &str| NroError::InvalidNro(message.into());
+        let header = nro
+            .get(HEADER_OFFSET..HEADER_OFFSET + HEADER_SIZE)
+            .ok_or_else(|| invalid("truncated header"))?;
+        let size = Self::segments_size(nro)?;
+        let field = |offset: usize| read_u32(header, offset).unwrap_or_default();
+        let pair = |offset: usize| (field(offset), field(offset + 4));
+        let header = NroHeader {
+            size: field(0x08),
+            text: pair(0x10),
+            ro: pair(0x18),
+            data: pair(0x20),
+            bss_size: field(0x28),
+            build_id: header[0x30..0x50].try_into().unwrap(),
+            api_info: pair(0x58),
+            dynstr: pair(0x60),
+            dynsym: pair(0x68),
+        };
+        if nro.len() < size {
+            return Err(NroError::InvalidNro(format!(
+                "{} bytes, header says {size}",
+                nro.len()
+            )));
+        }
+
+        let assets = &nro[size..];
+        let section = |range: Option<(usize, usize)>| {
+            range
+                .map(|(offset, len)| {
+                    offset
+                        .checked_add(len)
+                        .and_then(|end| assets.get(offset..end))
+                        .ok_or_else(|| invalid("asset out of bounds"))
+                })
+                .transpose()
+        };
+        let [icon, nacp, romfs] = Self::asset_ranges(assets)?;
+        Ok(Self {
+            header,
+            icon: section(icon)?.map(<[u8]>::to_vec),
+            nacp: section(nacp)?.map(Nacp::parse).transpose()?,
+            romfs: section(romfs)?
+                .map(|romfs| RomFs::new(romfs.to_vec()))
+                .transpose()
+                .map_err(|error| NroError::InvalidNro(format!("{error}")))?,
+        })
+    }
+
+    /// Read the size of the segments, where the asset section starts, from
+    /// the first `HEADER_OFFSET + HEADER_SIZE` bytes of an NRO.
+    pub(crate) fn segments_size(start: &[u8]) -> Result<usize, NroError> {
+        let invalid = |message: &str| NroError::InvalidNro(message.into());
+        let header = start
+            .get(HEADER_OFFSET..HEADER_OFFSET + HEADER_SIZE)
+            .ok_or_else(|| invalid("truncated header"))?;
+        if &header[..4] != b"NRO0" {
+            return Err(invalid("missing NRO0 magic"));
+        }
+        Ok(read_u32(header, 0x08).unwrap_or_default() as usize)
+    }
+
+    /// Read the offsets and sizes of the icon, NACP and RomFS, relative to
+    /// the asset section, from its header. An NRO without assets has an
+    /// empty asset section.
+    pub(crate) fn asset_ranges(header: &[u8]) -> Result<[Option<(usize, usize)>; 3], NroError> {
+        let invalid = |message: &str| NroError::InvalidNro(message.into());
+        let mut ranges = [None; 3];
+        if header.is_empty() {
+            return Ok(ranges);
+        }
+        if header.len() < ASSET_HEADER_SIZE || &header[..4] != b"ASET" {
+            return Err(invalid("invalid asset section header"));
+        }
+        for (index, range) in ranges.iter_mut().enumerate() {
+            let field = |offset: usize| {
+                read_u64(header, 0x08 + index * 0x10 + offset)
+                    .and_then(|value| usize::try_from(value).ok())
+                    .ok_or_else(|| invalid("asset out of bounds"))
+            };
+            let (offset, len) = (field(0)?, field(8)?);
+            if len != 0 {
+                *range = Some((offset, len));
+            }
+        }
+        Ok(ranges)
+    }
+}
+
+/// Check that an icon is a 256×256 JPEG.
+pub fn validate_icon(icon: &[u8]) -> Result<(), NroError> {
+    let (width, height) =
+        jpeg_size(icon).ok_or_else(|| NroError::InvalidIcon("not a JPEG image".into()))?;
+    if (width, height) != (ICON_SIZE, ICON_SIZE) {
+        return Err(NroError::InvalidIcon(format!(
+            "{width}×{height} pixels, expected {ICON_SIZE}×{ICON_SIZE}"
+        )));
+    }
+    Ok(())
+}
+
+/// Read the width and height of a JPEG from its start-of-frame marker.
+pub fn jpeg_size(bytes: &[u8]) -> Option<(u16, u16)> {
+    if !bytes.starts_with(&[0xff, 0xd8]) {
+        return None;
+    }
+    let mut offset = 2;
+    loop {
+        if *bytes.get(offset)? != 0xff {
+            return None;
+        }
+        // Markers may be preceded by any number of fill bytes
+        while *bytes.get(offset + 1)? == 0xff {
+            offset += 1;
+        }
+        let marker = bytes[offset + 1];
+        offset += 2;
+        match marker {
+            // Standalone markers without a length
+            0x01 | 0xd0..=0xd7 => continue,
+            // End of image or start of scan before any frame
+            0xd9 | 0xda => return None,
+            _ => {}
+        }
+        let len = u16::from_be_bytes([*bytes.get(offset)?, *bytes.get(offset + 1)?]) as usize;
+        // Start of frame, except the DHT, JPG and DAC markers in the same range
+        if matches!(marker, 0xc0..=0xcf) && !matches!(marker, 0xc4 | 0xc8 | 0xcc) {
+            let frame = bytes.get(offset + 3..offset + 7)?;
+            let height = u16::from_be_bytes([frame[0], frame[1]]);
+            let width = u16::from_be_bytes([frame[2], frame[3]]);
+            return Some((width, height));
+        }
+        offset += len;
+    }
+}
+
+fn align(value: usize, alignment: usize) -> usize {
+    value.next_multiple_of(alignment)
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        bytes.get(offset..offset.checked_add(2)?)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset.checked_add(4)?)?.try_into().ok()?,
+    ))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        bytes.get(offset..offset.checked_add(8)?)?.try_into().ok()?,
+    ))
+}
+
+fn write_u32(bytes: &mut [u8], offset: usize, value: u32) {
+    bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn write_u64(bytes: &mut [u8], offset: usize, value: u64) {
+    bytes[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+}
@@ -0,0 +1,206 @@
+//! Segments of a linked ELF, as laid out in an NRO.
+
+use alloc::format;
+use alloc::vec::Vec;
+
+use super::{read_u16, read_u32, read_u64, NroError, HEADER_OFFSET, HEADER_SIZE, PAGE_SIZE};
+
+/// `PT_LOAD` program header.
+const PT_LOAD: u32 = 1;
+
+/// `PT_NOTE` program header.
+const PT_NOTE: u32 = 4;
+
+/// `NT_GNU_BUILD_ID` note.
+const NT_GNU_BUILD_ID: u32 = 3;
+
+/// `EM_AARCH64` machine.
+const EM_AARCH64: u16 = 183;
+
+/// Size of an ELF64 program header.
+const PHDR_SIZE: usize = 0x38;
+
+/// Size of an ELF64 section header.
+const SHDR_SIZE: usize = 0x40;
+
+/// Code, read-only data and data of a program, ready to go into an NRO.
+///
+/// NROs are loaded as they are laid out in the file, so the ELF's three
+/// `PT_LOAD` segments must be page aligned and follow each other from
+/// address 0, as with libnx's `switch.ld` linker script.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NroSegments {
+    /// Code, starting with the crt0 entry point.
+    pub text: Vec<u8>,
+    /// Read-only data.
+    pub ro: Vec<u8>,
+    /// Initialized data.
+    pub data: Vec<u8>,
+    /// Size of the zeroed data after `data`.
+    pub bss_size: usize,
+    /// GNU build ID, up to 32 bytes.
+    pub build_id: Vec<u8>,
+    /// Offset and size of `.api_info`, relative to the read-only data.
+    pub api_info: (u32, u32),
+    /// Offset and size of `.dynstr`, relative to the read-only data.
+    pub dynstr: (u32, u32),
+    /// Offset and size of `.dynsym`, relative to the read-only data.
+    pub dynsym: (u32, u32),
+}
+
+/// `PT_LOAD` segment read from the program headers.
+#[derive(Debug, Clone, Copy)]
+struct Load {
+    offset: usize,
+    vaddr: usize,
+    file_size: usize,
+    mem_size: usize,
+}
+
+impl NroSegments {
+    /// Read the segments of an AArch64 ELF executable.
+    pub fn from_elf(elf: &[u8]) -> Result<Self, NroError> {
+        let invalid = |message: &str| NroError::InvalidElf(message.into());
+        if !elf.starts_with(b"\x7fELF") {
+            return Err(invalid("missing ELF magic"));
+        }
+        if elf.get(4..6) != Some(&[2, 1][..]) {
+            return Err(invalid("not a 64-bit little-endian ELF"));
+        }
+        if read_u16(elf, 0x12) != Some(EM_AARCH64) {
+            return Err(invalid("not an AArch64 ELF"));
+        }
+        let field = |offset: usize| -> Result<usize, NroError> {
+            read_u64(elf, offset)
+                .and_then(|value| usize::try_from(value).ok())
+                .ok_or_else(|| invalid("truncated header"))
+        };
+        let phoff = field(0x20)?;
+        let shoff = field(0x28)?;
+        let phnum = read_u16(elf, 0x38).unwrap_or_default() as usize;
+        let shnum = read_u16(elf, 0x3c).unwrap_or_default() as usize;
+        let shstrndx = read_u16(elf, 0x3e).unwrap_or_default() as usize;
+        // Offset of the `index`th header of a table, which may not exist
+        let entry = |table: usize, index: usize, size: usize| {
+            index
+                .checked_mul(size)
+                .and_then(|offset| table.checked_add(offset))
+                .ok_or_else(|| invalid("header offset out of range"))
+        };
+
+        let mut loads = Vec::new();
+        let mut build_id = Vec::new();
+        for index in 0..phnum {
+            let phdr = entry(phoff, index, PHDR_SIZE)?;
+            // Reading the kind bounds `phdr` by the ELF's size
+            let kind = read_u32(elf, phdr).ok_or_else(|| invalid("truncated program header"))?;
+            let load = Load {
+                offset: field(phdr + 0x08)?,
+                vaddr: field(phdr + 0x10)?,
+                file_size: field(phdr + 0x20)?,
+                mem_size: field(phdr + 0x28)?,
+            };
+            match kind {
+                PT_LOAD => loads.push(load),
+                PT_NOTE => {
+                    let note = bytes(elf, load.offset, load.file_size)?;
+                    if let Some(id) = find_build_id(note) {
+                        build_id = id.to_vec();
+                    }
+                }
+                _ => {}
+            }
+        }
+        let [text, ro, data] = loads[..] else {
+            return Err(NroError::InvalidElf(format!(
+                "expected 3 PT_LOAD segments, found {}",
+                loads.len()
+            )));
+        };
+
+        // Each segment must start where the previous one ends, page aligned
+        let mut expected = 0;
+        for (name, load) in [("text", text), ("rodata", ro), ("data", data)] {
+            if load.vaddr != expected {
+                return Err(NroError::InvalidElf(format!(
+                    "{name} segment at {:#x}, expected {expected:#x}",
+                    load.vaddr
+                )));
+            }
+            expected = load
+                .vaddr
+                .checked_add(load.file_size)
+                .and_then(|end| end.checked_next_multiple_of(PAGE_SIZE))
+                .ok_or_else(|| invalid("segment end out of range"))?;
+        }
+        if text.file_size < HEADER_OFFSET + HEADER_SIZE {
+            return Err(invalid("text segment too small for the NRO header"));
+        }
+
+        let mut segments = NroSegments {
+            text: bytes(elf, text.offset, text.file_size)?.to_vec(),
+            ro: bytes(elf, ro.offset, ro.file_size)?.to_vec(),
+            data: bytes(elf, data.offset, data.file_size)?.to_vec(),
+            bss_size: data.mem_size.saturating_sub(data.file_size),
+            build_id,
+            ..Default::default()
+        };
+
+        // Locate the dynamic symbol tables by section name, if the ELF
+        // still has its section headers
+        if shoff == 0 {
+            return Ok(segments);
+        }
+        let names_offset = entry(shoff, shstrndx, SHDR_SIZE)?
+            .checked_add(0x18)
+            .ok_or_else(|| invalid("header offset out of range"))?;
+        let names_offset = field(names_offset)?;
+        for index in 0..shnum {
+            let shdr = entry(shoff, index, SHDR_SIZE)?;
+            let name = read_u32(elf, shdr).ok_or_else(|| invalid("truncated section header"))?;
+            let name = names_offset
+                .checked_add(name as usize)
+                .and_then(|start| elf.get(start..))
+                .and_then(|rest| rest.split(|&byte| byte == 0).next())
+                .unwrap_or_default();
+            let range = match name {
+                b".api_info" => &mut segments.api_info,
+                b".dynstr" => &mut segments.dynstr,
+                b".dynsym" => &mut segments.dynsym,
+                _ => continue,
+            };
+            let addr = field(shdr + 0x10)?;
+            let size = field(shdr + 0x20)?;
+            let relative = addr
+                .checked_sub(ro.vaddr)
+                .ok_or_else(|| invalid("dynamic section outside the rodata segment"))?;
+            *range = (relative as u32, size as u32);
+        }
+        Ok(segments)
+    }
+}
+
+/// Get `len` bytes of the ELF at `offset`.
+fn bytes(elf: &[u8], offset: usize, len: usize) -> Result<&[u8], NroError> {
+    offset
+        .checked_add(len)
+        .and_then(|end| elf.get(offset..end))
+        .ok_or_else(|| NroError::InvalidElf("segment out of bounds".into()))
+}
+
+/// Find the GNU build ID in a note segment.
+fn find_build_id(mut notes: &[u8]) -> Option<&[u8]> {
+    while notes.len() >= 12 {
+        let name_size = read_u32(notes, 0)? as usize;
+        let desc_size = read_u32(notes, 4)? as usize;
+        let kind = read_u32(notes, 8)?;
+        let desc_offset = name_size.checked_next_multiple_of(4)?.checked_add(12)?;
+        let end = desc_offset.checked_add(desc_size.checked_next_multiple_of(4)?)?;
+        let name = notes.get(12..name_size.checked_add(12)?)?;
+        if kind == NT_GNU_BUILD_ID && name == b"GNU\0" {
+            return notes.get(desc_offset..desc_offset.checked_add(desc_size)?);
+        }
+        notes = notes.get(end..)?;
+    }
+    None
+}
@@ -0,0 +1,219 @@
+//! Packaging metadata from `[package.metadata.switch]` in `Cargo.toml`.
+
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use super::{Nacp, NroError};
+use crate::emulator::EmulatorConfig;
+
+/// NRO metadata from a crate's `Cargo.toml`:
+///
+/// ```toml
+/// [package.metadata.switch]
+/// title = "Crab Crossing"          # defaults to the package name
+/// author = "Ibrahim Cesar"         # defaults to the package authors
+/// version = "0.2.0"                # defaults to the package version
+/// icon = "assets/icon.jpg"         # 256×256 JPEG
+/// romfs = "assets"                 # directory packed into the RomFS
+/// title_id = "0x0100000000001000"
+///
+/// [package.metadata.switch.emulator]
+/// path = "Ryujinx"                 # see the `emulator` module
+/// ```
+///
+/// Package authors and versions inherited with `{ workspace = true }` are read
+/// from `[workspace.package]` in the workspace root's `Cargo.toml`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwitchMetadata {
+    /// Title, author, version and title ID.
+    pub nacp: Nacp,
+    /// Icon path, resolved against the manifest's directory.
+    pub icon: Option<PathBuf>,
+    /// RomFS directory, resolved against the manifest's directory.
+    pub romfs: Option<PathBuf>,
+    /// Emulator for `cargo switchbrew run`, with paths resolved against the
+    /// manifest's directory.
+    pub emulator: Option<EmulatorConfig>,
+}
+
+#[derive(Deserialize)]
+struct CargoManifest {
+    package: Option<Package>,
+    workspace: Option<Workspace>,
+}
+
+#[derive(Deserialize)]
+struct Package {
+    name: String,
+    // Either may be inherited from `[workspace.package]` as
+    // `{ workspace = true }`
+    version: Option<toml::Value>,
+    authors: Option<toml::Value>,
+    metadata: Option<PackageMetadata>,
+}
+
+#[derive(Deserialize)]
+struct Workspace {
+    #[serde(default)]
+    package: WorkspacePackage,
+}
+
+#[derive(Default, Deserialize)]
+struct WorkspacePackage {
+    version: Option<String>,
+    authors: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct PackageMetadata {
+    switch: Option<SwitchTable>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SwitchTable {
+    title: Option<String>,
+    author: Option<String>,
+    version: Option<String>,
+    icon: Option<PathBuf>,
+    romfs: Option<PathBuf>,
+    title_id: Option<String>,
+    emulator: Option<EmulatorConfig>,
+}
+
+impl SwitchMetadata {
+    /// Parse a `Cargo.toml`, resolving paths against its directory.
+    pub fn from_cargo_toml(text: &str, manifest_dir: &Path) -> Result<Self, NroError> {
+        let invalid = |message: String| NroError::InvalidMetadata(message);
+        let manifest: CargoManifest =
+            toml::from_str(text).map_err(|error| invalid(error.to_string()))?;
+        let package = manifest
+            .package
+            .ok_or_else(|| invalid("Cargo.toml has no [package]".into()))?;
+        let switch = package
+            .metadata
+            .and_then(|metadata| metadata.switch)
+            .unwrap_or_default();
+
+        // Only look for the workspace root when a value is inherited from it
+        let inherits = |value: &Option<toml::Value>| {
+            value
+                .as_ref()
+                .and_then(|value| value.get("workspace"))
+                .and_then(toml::Value::as_bool)
+                == Some(true)
+        };
+        let workspace = if (switch.author.is_none() && inherits(&package.authors))
+            || (switch.version.is_none() && inherits(&package.version))
+        {
+            match manifest.workspace {
+                // The package is its own workspace root
+                Some(workspace) => Some((manifest_dir.join("Cargo.toml"), workspace.package)),
+                None => find_workspace(manifest_dir)?,
+            }
+        } else {
+            None
+        };
+        let inherited = |field: &str| {
+            let reason = match &workspace {
+                Some((root, _)) => {
+                    format!("{} has no [workspace.package] {field}", root.display())
+                }
+                None => {
+                    format!("no workspace root was found; set {field} in [package.metadata.switch]")
+                }
+            };
+            invalid(format!(
+                "{field} is inherited from the workspace, but {reason}"
+            ))
+        };
+
+        let author = match (switch.author, package.authors) {
+            (Some(author), _) => author,
+            (None, authors) if inherits(&authors) => workspace
+                .as_ref()
+                .and_then(|(_, package)| package.authors.as_ref())
+                .map(|authors| join_authors(authors.iter().map(String::as_str)))
+                .ok_or_else(|| inherited("authors"))?,
+            (None, Some(toml::Value::Array(authors))) => {
+                join_authors(authors.iter().filter_map(toml::Value::as_str))
+            }
+            _ => {
+                return Err(invalid(
+                    "author is not set and no package authors are listed".into(),
+                ))
+            }
+        };
+        let version = match (switch.version, package.version) {
+            (Some(version), _) => version,
+            (None, version) if inherits(&version) => workspace
+                .as_ref()
+                .and_then(|(_, package)| package.version.clone())
+                .ok_or_else(|| inherited("version"))?,
+            (None, Some(toml::Value::String(version))) => version,
+            _ => {
+                return Err(invalid(
+                    "version is not set and the package version is not a string".into(),
+                ))
+            }
+        };
+        let mut nacp = Nacp::new(switch.title.unwrap_or(package.name), author, version);
+        if let Some(title_id) = switch.title_id {
+            let digits = title_id.trim_start_matches("0x");
+            let title_id = u64::from_str_radix(digits, 16)
+                .ok()
+                .filter(|_| digits.len() == 16)
+                .ok_or_else(|| invalid(format!("title_id {title_id:?} is not 16 hex digits")))?;
+            nacp = nacp.with_title_id(title_id);
+        }
+
+        Ok(Self {
+            nacp,
+            icon: switch.icon.map(|icon| manifest_dir.join(icon)),
+            romfs: switch.romfs.map(|romfs| manifest_dir.join(romfs)),
+            emulator: switch.emulator.map(|emulator| EmulatorConfig {
+                // A bare executable name is looked up in the PATH
+                path: if emulator.path.components().count() > 1 {
+                    manifest_dir.join(&emulator.path)
+                } else {
+                    emulator.path
+                },
+                log: emulator.log.map(|log| manifest_dir.join(log)),
+                ..emulator
+            }),
+        })
+    }
+}
+
+/// Join package authors for the NACP, dropping their email addresses.
+fn join_authors<'a>(authors: impl Iterator<Item = &'a str>) -> String {
+    authors
+        .map(|author| author.split(" <").next().unwrap_or(author).trim())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Find the workspace root above a package, like Cargo does: the nearest
+/// parent directory whose `Cargo.toml` has a `[workspace]` table.
+fn find_workspace(manifest_dir: &Path) -> Result<Option<(PathBuf, WorkspacePackage)>, NroError> {
+    let manifest_dir = match manifest_dir.as_os_str().is_empty() {
+        true => Path::new("."),
+        false => manifest_dir,
+    };
+    let manifest_dir = manifest_dir
+        .canonicalize()
+        .unwrap_or_else(|_| manifest_dir.into());
+    for dir in manifest_dir.ancestors().skip(1) {
+        let path = dir.join("Cargo.toml");
+        let Ok(text) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let manifest: CargoManifest = toml::from_str(&text)
+            .map_err(|error| NroError::InvalidMetadata(format!("{}: {error}", path.display())))?;
+        if let Some(workspace) = manifest.workspace {
+            return Ok(Some((path, workspace.package)));
+        }
+    }
+    Ok(None)
+}
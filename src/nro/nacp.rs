@@ -0,0 +1,174 @@
+//! NACP, the application metadata shown by the homebrew menu.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::{read_u64, write_u64, NroError};
+
+/// Size of a NACP.
+pub const NACP_SIZE: usize = 0x4000;
+
+/// Number of title entries, one per system language.
+const LANGUAGES: usize = 16;
+
+/// Size of a title entry: the name, then the publisher.
+const TITLE_ENTRY_SIZE: usize = 0x300;
+
+/// Size of the name in a title entry.
+const NAME_SIZE: usize = 0x200;
+
+/// Size of the publisher in a title entry.
+const PUBLISHER_SIZE: usize = 0x100;
+
+/// Offset of the minimum age for each rating organization.
+const RATING_AGE_OFFSET: usize = 0x3040;
+
+/// Number of rating organizations.
+const RATING_AGES: usize = 0x20;
+
+/// Offset of the display version.
+const DISPLAY_VERSION_OFFSET: usize = 0x3060;
+
+/// Size of the display version.
+const DISPLAY_VERSION_SIZE: usize = 0x10;
+
+/// Offset of the save data owner ID, the title ID.
+const SAVE_DATA_OWNER_ID_OFFSET: usize = 0x3078;
+
+/// Offsets of the IDs set to the title ID: presence group, save data owner
+/// and first local communication ID.
+const TITLE_ID_OFFSETS: [usize; 3] = [0x3038, SAVE_DATA_OWNER_ID_OFFSET, 0x30b0];
+
+/// Offset of the base add-on content ID, the title ID plus `0x1000`.
+const ADD_ON_CONTENT_BASE_ID_OFFSET: usize = 0x3070;
+
+/// Application metadata: title, author and version.
+///
+/// Every language gets the same title and author, so the homebrew menu
+/// shows them whatever the system language.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nacp {
+    /// Title shown in the homebrew menu, up to 511 bytes.
+    pub title: String,
+    /// Author or publisher, up to 255 bytes.
+    pub author: String,
+    /// Version shown in the homebrew menu, up to 15 bytes.
+    pub version: String,
+    /// Title ID, setting the save data owner and related IDs.
+    pub title_id: Option<u64>,
+}
+
+impl Nacp {
+    /// Create metadata without a title ID.
+    pub fn new(
+        title: impl Into<String>,
+        author: impl Into<String>,
+        version: impl Into<String>,
+    ) -> Self {
+        Self {
+            title: title.into(),
+            author: author.into(),
+            version: version.into(),
+            title_id: None,
+        }
+    }
+
+    /// Set the title ID.
+    pub fn with_title_id(mut self, title_id: u64) -> Self {
+        self.title_id = Some(title_id);
+        self
+    }
+
+    /// Read a NACP, taking the title and author from the first language
+    /// that has them.
+    pub fn parse(nacp: &[u8]) -> Result<Self, NroError> {
+        if nacp.len() < NACP_SIZE {
+            return Err(NroError::InvalidNacp(format!(
+                "{} bytes, expected {NACP_SIZE}",
+                nacp.len()
+            )));
+        }
+        let entry = (0..LANGUAGES)
+            .map(|language| language * TITLE_ENTRY_SIZE)
+            .find(|&entry| nacp[entry] != 0)
+            .unwrap_or_default();
+        let title_id = read_u64(nacp, SAVE_DATA_OWNER_ID_OFFSET).filter(|&title_id| title_id != 0);
+        Ok(Self {
+            title: read_str(&nacp[entry..entry + NAME_SIZE]),
+            author: read_str(&nacp[entry + NAME_SIZE..entry + TITLE_ENTRY_SIZE]),
+            version: read_str(
+                &nacp[DISPLAY_VERSION_OFFSET..DISPLAY_VERSION_OFFSET + DISPLAY_VERSION_SIZE],
+            ),
+            title_id,
+        })
+    }
+
+    /// Encode the NACP, checking each field fits with its terminating NUL.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, NroError> {
+        let mut nacp = vec![0; NACP_SIZE];
+        for language in 0..LANGUAGES {
+            let entry = language * TITLE_ENTRY_SIZE;
+            write_str(&mut nacp, entry, NAME_SIZE, "title", &self.title)?;
+            write_str(
+                &mut nacp,
+                entry + NAME_SIZE,
+                PUBLISHER_SIZE,
+                "author",
+                &self.author,
+            )?;
+        }
+        // No age rating for any organization
+        nacp[RATING_AGE_OFFSET..RATING_AGE_OFFSET + RATING_AGES].fill(0xff);
+        write_str(
+            &mut nacp,
+            DISPLAY_VERSION_OFFSET,
+            DISPLAY_VERSION_SIZE,
+            "version",
+            &self.version,
+        )?;
+        if let Some(title_id) = self.title_id {
+            for offset in TITLE_ID_OFFSETS {
+                write_u64(&mut nacp, offset, title_id);
+            }
+            write_u64(
+                &mut nacp,
+                ADD_ON_CONTENT_BASE_ID_OFFSET,
+                title_id.wrapping_add(0x1000),
+            );
+        }
+        Ok(nacp)
+    }
+}
+
+/// Write a NUL-terminated string into a fixed-size field.
+fn write_str(
+    nacp: &mut [u8],
+    offset: usize,
+    size: usize,
+    field: &str,
+    value: &str,
+) -> Result<(), NroError> {
+    if value.is_empty() {
+        return Err(NroError::InvalidNacp(format!("{field} is empty")));
+    }
+    if value.len() >= size {
+        return Err(NroError::InvalidNacp(format!(
+            "{field} is {} bytes, at most {} fit",
+            value.len(),
+            size - 1
+        )));
+    }
+    nacp[offset..offset + value.len()].copy_from_slice(value.as_bytes());
+    Ok(())
+}
+
+/// Read a NUL-terminated string from a fixed-size field.
+fn read_str(field: &[u8]) -> String {
+    let len = field
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..len]).into_owned()
+}
@@ -0,0 +1,77 @@
+//! Desktop frame limiter that simulates Switch clocks.
+
+use core::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::platform::{Hal, PerformanceMode, SwitchConfig};
+
+/// Plugin that caps the frame rate at [`SwitchConfig::target_fps`].
+///
+/// When simulating the Switch on desktop, frames are also stretched by the
+/// [`PerformanceBudget::slowdown`](crate::platform::PerformanceBudget::slowdown)
+/// of the current [`PerformanceMode`], so a game that barely holds 60 FPS
+/// docked drops frames in handheld mode just like on the console.
+///
+/// Not part of [`SwitchPlugins`](crate::SwitchPlugins), since it sleeps on
+/// the main thread; add it to opt in. Frames are not limited until the app
+/// has a [`SwitchConfig`].
+pub struct SwitchFrameLimiterPlugin;
+
+impl Plugin for SwitchFrameLimiterPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Hal>()
+            .init_resource::<PerformanceMode>()
+            .init_resource::<FrameLimiter>()
+            .add_systems(Last, limit_frame_rate);
+    }
+}
+
+/// Frame limiter settings.
+#[derive(Debug, Clone, Copy, PartialEq, Resource)]
+pub struct FrameLimiter {
+    /// Cap the frame rate at the target FPS.
+    pub enabled: bool,
+    /// Stretch frames to emulate the Switch clocks when simulating the Switch.
+    pub simulate_clocks: bool,
+}
+
+impl Default for FrameLimiter {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            simulate_clocks: true,
+        }
+    }
+}
+
+/// System to sleep until the frame budget is used up.
+fn limit_frame_rate(
+    hal: Res<Hal>,
+    limiter: Res<FrameLimiter>,
+    config: Option<Res<SwitchConfig>>,
+    mode: Res<PerformanceMode>,
+    mut frame_start: Local<Option<Duration>>,
+) {
+    let now = hal.now();
+    let Some(start) = frame_start.replace(now) else {
+        return;
+    };
+    let Some(config) = config.filter(|_| limiter.enabled) else {
+        return;
+    };
+
+    let budget = mode.budget(config.display_mode, config.target_fps);
+    let work = now - start;
+    let slowdown = if limiter.simulate_clocks && config.platform.is_switch() {
+        budget.slowdown()
+    } else {
+        1.0
+    };
+
+    let frame_time = budget.frame_time.max(work.mul_f32(slowdown));
+    if let Some(remaining) = frame_time.checked_sub(work) {
+        hal.sleep(remaining);
+        *frame_start = Some(hal.now());
+    }
+}
@@ -1,9 +1,89 @@
 //! Platform detection and configuration for Nintendo Switch.
 
+use alloc::format;
+use alloc::string::String;
+use core::fmt;
+use core::time::Duration;
+
+use bevy::platform::sync::OnceLock;
 use bevy::prelude::*;
+#[cfg(not(feature = "std"))]
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::logging::LogLevel;
+
+mod hal;
+#[cfg(feature = "switch")]
+pub(crate) mod nx;
+
+#[cfg(feature = "std")]
+pub use hal::DesktopHal;
+#[cfg(feature = "switch")]
+pub use hal::HorizonHal;
+pub use hal::{FsError, Hal, HalInput, MockHal, SwitchHal};
+#[cfg(all(feature = "switch", not(target_os = "horizon")))]
+#[doc(hidden)]
+pub use nx::host as horizon_host;
+
+/// Plugin that provides the [`SwitchConfig`] resource.
+///
+/// A config inserted before this plugin is added takes precedence over the
+/// plugin's own, so apps can set it up front.
+#[derive(Debug, Clone, Default)]
+pub struct SwitchConfigPlugin {
+    /// Config to insert, or `None` for [`SwitchConfig::default`].
+    pub config: Option<SwitchConfig>,
+}
+
+impl Plugin for SwitchConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Hal>()
+            .init_resource::<PerformanceMode>()
+            .add_message::<PerformanceModeChanged>()
+            .add_message::<AppletMessage>()
+            .add_systems(PreUpdate, notify_performance_mode_change);
+
+        if !app.world().contains_resource::<PlatformInfo>() {
+            let info = PlatformInfo::from_hal(&**app.world().resource::<Hal>());
+            app.insert_resource(info);
+        }
+        if !app.world().contains_resource::<SwitchConfig>() {
+            let info = app.world().resource::<PlatformInfo>();
+            let config = self
+                .config
+                .clone()
+                .unwrap_or_else(|| SwitchConfig::for_platform(info));
+            app.insert_resource(config);
+        }
+
+        let world = app.world_mut();
+        let config = world.resource::<SwitchConfig>();
+        if config.validate().is_err() {
+            let config = config
+                .clone()
+                .with_valid_values(world.resource::<PlatformInfo>());
+            world.insert_resource(config);
+        }
+    }
+}
+
+/// Environment variable that overrides platform detection.
+///
+/// Accepts `desktop`, `docked`, `handheld` or `tabletop` (case-insensitive,
+/// optionally prefixed with `switch-`), so desktop builds can simulate the
+/// Switch without recompiling:
+///
+/// ```bash
+/// SWITCHBREW_PLATFORM=handheld cargo run --example crab_crossing
+/// ```
+pub const PLATFORM_ENV_VAR: &str = "SWITCHBREW_PLATFORM";
 
 /// Target platform for the game.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
 pub enum Platform {
     /// Desktop development mode (Windows, macOS, Linux)
     #[default]
@@ -15,6 +95,32 @@ pub enum Platform {
 }
 
 impl Platform {
+    /// Detect current platform at runtime.
+    ///
+    /// Shorthand for [`PlatformInfo::detect`]; honors [`PLATFORM_ENV_VAR`].
+    pub fn detect() -> Self {
+        PlatformInfo::detect().platform
+    }
+
+    /// Parse a platform name (`desktop`, `switch_docked` or `switch_handheld`,
+    /// case-insensitive; the `switch_` prefix is optional).
+    pub fn parse(name: &str) -> Option<Self> {
+        match normalize_name(name).as_str() {
+            "desktop" => Some(Platform::Desktop),
+            "docked" => Some(Platform::SwitchDocked),
+            "handheld" => Some(Platform::SwitchHandheld),
+            _ => None,
+        }
+    }
+
+    /// Get the platform for a Switch display mode.
+    pub const fn from_display_mode(mode: DisplayMode) -> Self {
+        match mode {
+            DisplayMode::Docked => Platform::SwitchDocked,
+            DisplayMode::Handheld | DisplayMode::Tabletop => Platform::SwitchHandheld,
+        }
+    }
+
     /// Detect current platform at compile time.
     pub const fn current() -> Self {
         #[cfg(feature = "switch")]
@@ -51,8 +157,206 @@ impl Platform {
     }
 }
 
+/// Normalize a platform or display mode name: trimmed, lowercase and without
+/// the optional `switch-` or `switch_` prefix.
+fn normalize_name(name: &str) -> String {
+    let name = name.trim().to_ascii_lowercase();
+    match name
+        .strip_prefix("switch-")
+        .or_else(|| name.strip_prefix("switch_"))
+    {
+        Some(stripped) => stripped.into(),
+        None => name,
+    }
+}
+
+/// Operating system the game is running on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostOs {
+    /// Nintendo Switch (Horizon OS), on hardware or in an emulator
+    Horizon,
+    /// Windows
+    Windows,
+    /// macOS
+    MacOs,
+    /// Linux
+    Linux,
+    /// Any other OS
+    Other,
+}
+
+impl HostOs {
+    /// Get the OS this binary was built for.
+    pub const fn current() -> Self {
+        if cfg!(target_os = "horizon") {
+            HostOs::Horizon
+        } else if cfg!(target_os = "windows") {
+            HostOs::Windows
+        } else if cfg!(target_os = "macos") {
+            HostOs::MacOs
+        } else if cfg!(target_os = "linux") {
+            HostOs::Linux
+        } else {
+            HostOs::Other
+        }
+    }
+
+    /// Get human-readable name for the OS.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            HostOs::Horizon => "Horizon",
+            HostOs::Windows => "Windows",
+            HostOs::MacOs => "macOS",
+            HostOs::Linux => "Linux",
+            HostOs::Other => "Other",
+        }
+    }
+}
+
+/// Platform detected at runtime.
+///
+/// Inserted as a resource by [`SwitchConfigPlugin`], detected through the
+/// app's [`Hal`], and used for the [`SwitchConfig`] defaults.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Resource)]
+pub struct PlatformInfo {
+    /// Platform the game should behave as.
+    pub platform: Platform,
+    /// Operating system the game is running on.
+    pub os: HostOs,
+    /// Whether the game is running in a Switch emulator.
+    pub emulator: bool,
+    /// Current display mode.
+    pub display_mode: DisplayMode,
+    /// Console model (always [`SwitchModel::Original`] off the console).
+    pub model: SwitchModel,
+    /// How the game was started (always [`LaunchMode::Application`] off the
+    /// console).
+    pub launch_mode: LaunchMode,
+    /// Memory the system gives the game in bytes, or `None` off the console.
+    pub memory_limit: Option<u64>,
+    /// Whether the platform was forced through [`PLATFORM_ENV_VAR`].
+    pub simulated: bool,
+}
+
+impl Default for PlatformInfo {
+    fn default() -> Self {
+        Self::detect()
+    }
+}
+
+impl PlatformInfo {
+    /// Detect the platform, honoring [`PLATFORM_ENV_VAR`].
+    ///
+    /// The host is only detected on the first call; later calls, including
+    /// every [`SwitchConfig::default`], return the same info, so an invalid
+    /// override is only reported once.
+    pub fn detect() -> Self {
+        static DETECTED: OnceLock<PlatformInfo> = OnceLock::new();
+        *DETECTED.get_or_init(|| Self::from_hal(&*Hal::default()))
+    }
+
+    /// Detect the platform through a HAL, honoring [`PLATFORM_ENV_VAR`].
+    pub fn from_hal(hal: &dyn SwitchHal) -> Self {
+        Self::resolve(hal, hal.env_var(PLATFORM_ENV_VAR).as_deref())
+    }
+
+    /// Detect the platform with an explicit override value.
+    ///
+    /// Invalid override values are logged and ignored.
+    pub fn detect_with(env_override: Option<&str>) -> Self {
+        Self::resolve(&*Hal::default(), env_override)
+    }
+
+    /// Detect the platform through a HAL, applying an override value.
+    fn resolve(hal: &dyn SwitchHal, env_override: Option<&str>) -> Self {
+        let detected = Self::detect_host(hal);
+
+        let Some(value) = env_override
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+        else {
+            return detected;
+        };
+        let Some((platform, display_mode)) = Self::parse_override(value) else {
+            warn!(
+                "Ignoring invalid {}={:?}, expected desktop, docked, handheld or tabletop",
+                PLATFORM_ENV_VAR, value
+            );
+            return detected;
+        };
+
+        Self {
+            platform,
+            display_mode,
+            simulated: true,
+            ..detected
+        }
+    }
+
+    /// Parse an override value into the platform and display mode it simulates.
+    pub(crate) fn parse_override(value: &str) -> Option<(Platform, DisplayMode)> {
+        // Tabletop is a display mode of its own, not a platform
+        if normalize_name(value) == "tabletop" {
+            return Some((Platform::SwitchHandheld, DisplayMode::Tabletop));
+        }
+        match Platform::parse(value)? {
+            Platform::SwitchHandheld => Some((Platform::SwitchHandheld, DisplayMode::Handheld)),
+            platform => Some((platform, DisplayMode::Docked)),
+        }
+    }
+
+    /// Detect the platform from the host, without overrides.
+    fn detect_host(hal: &dyn SwitchHal) -> Self {
+        let (platform, display_mode) = match hal.display_mode() {
+            Some(mode) => (Platform::from_display_mode(mode), mode),
+            // Desktop builds with the `switch` feature behave as docked
+            None => (Platform::current(), DisplayMode::Docked),
+        };
+
+        Self {
+            platform,
+            os: HostOs::current(),
+            emulator: hal.is_emulator(),
+            display_mode,
+            model: hal.model(),
+            launch_mode: hal.launch_mode(),
+            memory_limit: hal.memory_limit(),
+            simulated: false,
+        }
+    }
+}
+
+/// Message from the system applet to the game.
+///
+/// On the console these come from the applet message queue and are delivered
+/// by the [`SwitchRunner`](crate::runner::SwitchRunner) from the app's
+/// [`Hal`]. The runner also reacts
+/// to them: updates pause while the game is out of focus or suspended,
+/// operation mode changes update [`SwitchConfig`], and an exit request ends
+/// the app after one last update.
+///
+/// Games usually read [`LifecycleEvent`](crate::lifecycle::LifecycleEvent)s
+/// instead, which also cover the desktop window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Message)]
+pub enum AppletMessage {
+    /// The system asked the game to exit, e.g. from the HOME menu
+    ExitRequested,
+    /// The game is back in the foreground
+    FocusGained,
+    /// The HOME menu or an overlay took over the screen
+    FocusLost,
+    /// The console is going to sleep, or the HOME menu or another app moved
+    /// the game to the background
+    Suspended,
+    /// The console woke up from sleep, or the game left the background
+    Resumed,
+    /// The console was docked or undocked
+    OperationModeChanged(DisplayMode),
+}
+
 /// Display mode for the Switch.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Resource)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Resource, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum DisplayMode {
     /// Docked mode - TV output at 1080p
     #[default]
@@ -64,6 +368,16 @@ pub enum DisplayMode {
 }
 
 impl DisplayMode {
+    /// Parse a display mode name (`docked`, `handheld` or `tabletop`, case-insensitive).
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "docked" => Some(DisplayMode::Docked),
+            "handheld" => Some(DisplayMode::Handheld),
+            "tabletop" => Some(DisplayMode::Tabletop),
+            _ => None,
+        }
+    }
+
     /// Get resolution for this display mode.
     pub const fn resolution(&self) -> (u32, u32) {
         match self {
@@ -73,8 +387,130 @@ impl DisplayMode {
     }
 }
 
+/// Nintendo Switch console model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwitchModel {
+    /// Original Switch with a 6.2" LCD
+    #[default]
+    Original,
+    /// Switch Lite - handheld only, built-in controls
+    Lite,
+    /// Switch OLED with a 7" screen
+    Oled,
+}
+
+impl SwitchModel {
+    /// Parse a model name (`original`, `lite` or `oled`, case-insensitive).
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "original" => Some(SwitchModel::Original),
+            "lite" => Some(SwitchModel::Lite),
+            "oled" => Some(SwitchModel::Oled),
+            _ => None,
+        }
+    }
+
+    /// Get human-readable name for the model.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            SwitchModel::Original => "Nintendo Switch",
+            SwitchModel::Lite => "Nintendo Switch Lite",
+            SwitchModel::Oled => "Nintendo Switch (OLED model)",
+        }
+    }
+
+    /// Get the hardware capabilities of this model.
+    pub const fn profile(&self) -> HardwareProfile {
+        let (screen_inches, supports_docking, detachable_joycons) = match self {
+            SwitchModel::Original => (6.2, true, true),
+            SwitchModel::Lite => (5.5, false, false),
+            SwitchModel::Oled => (7.0, true, true),
+        };
+
+        HardwareProfile {
+            model: *self,
+            screen_inches,
+            screen_resolution: (1280, 720),
+            supports_docking,
+            // The Joy-Cons can only be used apart when they detach
+            supports_tabletop: detachable_joycons,
+            detachable_joycons,
+            max_controllers: 8,
+            memory_budget: HardwareProfile::APPLICATION_MEMORY,
+        }
+    }
+}
+
+/// How the homebrew loader started the game, which sets how much memory it
+/// gets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LaunchMode {
+    /// Started as an application, e.g. by holding R while opening a game,
+    /// with the memory of a retail game
+    #[default]
+    Application,
+    /// Started as a library applet, e.g. from the Album, sharing memory with
+    /// the running game
+    Applet,
+}
+
+impl LaunchMode {
+    /// Get the memory available to the game in this mode, in bytes.
+    pub const fn memory_budget(&self) -> u64 {
+        match self {
+            LaunchMode::Application => HardwareProfile::APPLICATION_MEMORY,
+            LaunchMode::Applet => HardwareProfile::APPLET_MEMORY,
+        }
+    }
+}
+
+/// Hardware capabilities of a Switch model.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HardwareProfile {
+    /// Model this profile describes.
+    pub model: SwitchModel,
+    /// Built-in screen diagonal in inches.
+    pub screen_inches: f32,
+    /// Built-in screen resolution.
+    pub screen_resolution: (u32, u32),
+    /// Whether the console can output to a TV.
+    pub supports_docking: bool,
+    /// Whether the console can stand on a table with controllers detached.
+    pub supports_tabletop: bool,
+    /// Whether the Joy-Cons detach, allowing single Joy-Con and split play.
+    pub detachable_joycons: bool,
+    /// Maximum number of wireless controllers.
+    pub max_controllers: u8,
+    /// Memory available to the game, in bytes.
+    pub memory_budget: u64,
+}
+
+impl HardwareProfile {
+    /// Memory available to applications on all models (3.2 GiB of 4 GiB).
+    pub const APPLICATION_MEMORY: u64 = 3_276 * 1024 * 1024;
+
+    /// Memory typically available to homebrew started as an applet, e.g.
+    /// from the Album. It varies with the firmware and the running game.
+    pub const APPLET_MEMORY: u64 = 442 * 1024 * 1024;
+
+    /// Check if a display mode is available on this model.
+    pub const fn supports(&self, mode: DisplayMode) -> bool {
+        match mode {
+            DisplayMode::Docked => self.supports_docking,
+            DisplayMode::Handheld => true,
+            DisplayMode::Tabletop => self.supports_tabletop,
+        }
+    }
+}
+
 /// Configuration resource for Switch compatibility.
-#[derive(Debug, Clone, Resource)]
+///
+/// Serializable so it can be loaded from a config file; missing fields take
+/// their [`Default`] values.
+#[derive(Debug, Clone, PartialEq, Resource, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SwitchConfig {
     /// Current platform.
     pub platform: Platform,
@@ -86,23 +522,45 @@ pub struct SwitchConfig {
     pub target_fps: u32,
     /// Enable performance profiling overlay.
     pub show_perf_overlay: bool,
+    /// Desktop window scale, e.g. 0.5 to preview 1080p in a 960x540 window.
+    pub preview_scale: f32,
+    /// Draw a handheld bezel around the screen on desktop (handheld modes only).
+    pub bezel: bool,
+    /// Console model, selectable to simulate e.g. the Switch Lite on desktop.
+    pub model: SwitchModel,
+    /// Minimum level of the lines kept by the [`SwitchLogger`](crate::logging::SwitchLogger).
+    pub log_level: LogLevel,
+    /// How the game was started, which sets its memory budget.
+    pub launch_mode: LaunchMode,
 }
 
 impl Default for SwitchConfig {
     fn default() -> Self {
-        let platform = Platform::current();
-        let display_mode = DisplayMode::default();
+        Self::for_platform(&PlatformInfo::detect())
+    }
+}
+
+impl SwitchConfig {
+    /// Default config for a detected platform.
+    ///
+    /// Falls back to handheld mode if the model does not support the detected
+    /// display mode, e.g. a Switch Lite reported as docked.
+    pub fn for_platform(info: &PlatformInfo) -> Self {
         Self {
-            platform,
-            display_mode,
-            resolution: platform.resolution(),
+            platform: info.platform,
+            display_mode: info.display_mode,
+            resolution: info.display_mode.resolution(),
             target_fps: 60,
             show_perf_overlay: false,
+            preview_scale: 1.0,
+            bezel: false,
+            model: info.model,
+            log_level: LogLevel::Info,
+            launch_mode: info.launch_mode,
         }
+        .with_supported_display_mode()
     }
-}
 
-impl SwitchConfig {
     /// Create config for docked mode.
     pub fn docked() -> Self {
         Self {
@@ -128,4 +586,359 @@ impl SwitchConfig {
         self.target_fps = fps;
         self
     }
+
+    /// Set desktop preview scale (1.0 = native Switch resolution).
+    pub fn with_preview_scale(mut self, scale: f32) -> Self {
+        self.preview_scale = scale;
+        self
+    }
+
+    /// Show or hide the handheld bezel on desktop.
+    pub fn with_bezel(mut self, bezel: bool) -> Self {
+        self.bezel = bezel;
+        self
+    }
+
+    /// Set the console model.
+    pub fn with_model(mut self, model: SwitchModel) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// Set the minimum log level.
+    pub fn with_log_level(mut self, level: LogLevel) -> Self {
+        self.log_level = level;
+        self
+    }
+
+    /// Set the launch mode, e.g. to test with an applet's memory budget.
+    pub fn with_launch_mode(mut self, mode: LaunchMode) -> Self {
+        self.launch_mode = mode;
+        self
+    }
+
+    /// Get the hardware capabilities of the configured model, with the
+    /// memory budget of the launch mode.
+    pub const fn hardware(&self) -> HardwareProfile {
+        HardwareProfile {
+            memory_budget: self.launch_mode.memory_budget(),
+            ..self.model.profile()
+        }
+    }
+
+    /// Set the display mode, updating platform and resolution to match.
+    ///
+    /// Desktop stays desktop, so windows still follow the mode's resolution.
+    pub fn with_display_mode(mut self, mode: DisplayMode) -> Self {
+        self.display_mode = mode;
+        self.resolution = mode.resolution();
+        if self.platform.is_switch() {
+            self.platform = Platform::from_display_mode(mode);
+        }
+        self
+    }
+
+    /// Switch to handheld mode if the model does not support the current
+    /// display mode.
+    ///
+    /// Handheld mode is available on every model, so use this after changing
+    /// the model without picking a display mode.
+    pub fn with_supported_display_mode(self) -> Self {
+        if self.hardware().supports(self.display_mode) {
+            self
+        } else {
+            self.with_display_mode(DisplayMode::Handheld)
+        }
+    }
+
+    /// Replace each invalid value with its default for a platform, keeping
+    /// the valid ones and logging a warning per replaced value.
+    pub fn with_valid_values(mut self, info: &PlatformInfo) -> Self {
+        let defaults = Self::for_platform(info);
+        while let Err(error) = self.validate() {
+            self = match error.field {
+                ConfigField::DisplayMode => {
+                    warn!("{error}; falling back to handheld mode");
+                    self.with_display_mode(DisplayMode::Handheld)
+                }
+                ConfigField::Resolution => {
+                    let resolution = self.display_mode.resolution();
+                    warn!("{error}; using {}x{}", resolution.0, resolution.1);
+                    Self { resolution, ..self }
+                }
+                ConfigField::TargetFps => {
+                    warn!("{error}; using {}", defaults.target_fps);
+                    Self {
+                        target_fps: defaults.target_fps,
+                        ..self
+                    }
+                }
+                ConfigField::PreviewScale => {
+                    warn!("{error}; using {}", defaults.preview_scale);
+                    Self {
+                        preview_scale: defaults.preview_scale,
+                        ..self
+                    }
+                }
+                // Checked by `SwitchSettings`, not part of the config
+                ConfigField::StickDeadzone | ConfigField::UiScale => {
+                    error!("{error}");
+                    break;
+                }
+            };
+        }
+        self
+    }
+
+    /// Check that the config values are usable.
+    ///
+    /// Also rejects display modes the configured model does not support,
+    /// e.g. docked mode on the Switch Lite.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !self.hardware().supports(self.display_mode) {
+            return Err(ConfigError::new(
+                ConfigField::DisplayMode,
+                format!(
+                    "{:?} mode is not supported on the {}",
+                    self.display_mode,
+                    self.model.name()
+                ),
+            ));
+        }
+        if self.resolution.0 == 0 || self.resolution.1 == 0 {
+            return Err(ConfigError::new(
+                ConfigField::Resolution,
+                format!(
+                    "{}x{} has a zero dimension",
+                    self.resolution.0, self.resolution.1
+                ),
+            ));
+        }
+        if !(1..=240).contains(&self.target_fps) {
+            return Err(ConfigError::new(
+                ConfigField::TargetFps,
+                format!("{} is outside 1-240", self.target_fps),
+            ));
+        }
+        if !(self.preview_scale > 0.0 && self.preview_scale <= 4.0) {
+            return Err(ConfigError::new(
+                ConfigField::PreviewScale,
+                format!("{} is outside (0, 4]", self.preview_scale),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Config value checked by validation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConfigField {
+    /// [`SwitchConfig::display_mode`]
+    DisplayMode,
+    /// [`SwitchConfig::resolution`]
+    Resolution,
+    /// [`SwitchConfig::target_fps`]
+    TargetFps,
+    /// [`SwitchConfig::preview_scale`]
+    PreviewScale,
+    /// [`SwitchInputSettings::stick_deadzone`](crate::input::SwitchInputSettings::stick_deadzone)
+    StickDeadzone,
+    /// [`SwitchUiScale`](crate::window::SwitchUiScale)
+    UiScale,
+}
+
+impl ConfigField {
+    /// Get the field's name in config files.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            ConfigField::DisplayMode => "display_mode",
+            ConfigField::Resolution => "resolution",
+            ConfigField::TargetFps => "target_fps",
+            ConfigField::PreviewScale => "preview_scale",
+            ConfigField::StickDeadzone => "input.stick_deadzone",
+            ConfigField::UiScale => "ui_scale",
+        }
+    }
+}
+
+/// Error returned when a config value is invalid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    /// The invalid field.
+    pub field: ConfigField,
+    /// Why the value was rejected.
+    pub message: String,
+}
+
+impl ConfigError {
+    /// Create an error for a field.
+    pub fn new(field: ConfigField, message: impl Into<String>) -> Self {
+        Self {
+            field,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid `{}`: {}", self.field.name(), self.message)
+    }
+}
+
+impl core::error::Error for ConfigError {}
+
+/// Performance mode requested by the game.
+///
+/// Games switch modes at runtime, e.g. [`PerformanceMode::Boost`] during
+/// loading screens. The resulting clocks depend on the [`DisplayMode`], see
+/// [`PerformanceMode::budget`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Resource, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PerformanceMode {
+    /// Standard clocks for the current display mode
+    #[default]
+    Normal,
+    /// Raised CPU clock with a minimal GPU clock, for loading screens
+    Boost,
+    /// Lowest handheld GPU clock to extend battery life
+    BatterySaver,
+}
+
+impl PerformanceMode {
+    /// Get the clocks used in this mode for a display mode.
+    pub const fn clocks(&self, display_mode: DisplayMode) -> ClockProfile {
+        let docked = matches!(display_mode, DisplayMode::Docked);
+        match self {
+            PerformanceMode::Boost => ClockProfile::BOOST,
+            PerformanceMode::Normal if docked => ClockProfile::DOCKED,
+            PerformanceMode::Normal => ClockProfile::HANDHELD,
+            // The console is on mains power when docked
+            PerformanceMode::BatterySaver if docked => ClockProfile::DOCKED,
+            PerformanceMode::BatterySaver => ClockProfile::BATTERY_SAVER,
+        }
+    }
+
+    /// Get the frame budget for a display mode and target frame rate.
+    pub fn budget(&self, display_mode: DisplayMode, target_fps: u32) -> PerformanceBudget {
+        PerformanceBudget {
+            clocks: self.clocks(display_mode),
+            frame_time: Duration::from_secs(1) / target_fps.max(1),
+        }
+    }
+
+    /// Get human-readable name for the mode.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            PerformanceMode::Normal => "Normal",
+            PerformanceMode::Boost => "Boost",
+            PerformanceMode::BatterySaver => "Battery Saver",
+        }
+    }
+}
+
+/// CPU, GPU and memory clocks of a performance configuration, in MHz.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClockProfile {
+    /// CPU clock.
+    pub cpu_mhz: f32,
+    /// GPU clock.
+    pub gpu_mhz: f32,
+    /// Memory clock.
+    pub memory_mhz: f32,
+}
+
+impl ClockProfile {
+    /// Docked clocks.
+    pub const DOCKED: Self = Self {
+        cpu_mhz: 1020.0,
+        gpu_mhz: 768.0,
+        memory_mhz: 1600.0,
+    };
+    /// Handheld and tabletop clocks.
+    pub const HANDHELD: Self = Self {
+        cpu_mhz: 1020.0,
+        gpu_mhz: 384.0,
+        memory_mhz: 1331.2,
+    };
+    /// Handheld clocks in battery saver mode.
+    pub const BATTERY_SAVER: Self = Self {
+        cpu_mhz: 1020.0,
+        gpu_mhz: 307.2,
+        memory_mhz: 1331.2,
+    };
+    /// Boost mode clocks, in any display mode.
+    pub const BOOST: Self = Self {
+        cpu_mhz: 1785.0,
+        gpu_mhz: 76.8,
+        memory_mhz: 1600.0,
+    };
+}
+
+/// Frame budget for a performance mode and display mode.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PerformanceBudget {
+    /// Clocks the budget applies to.
+    pub clocks: ClockProfile,
+    /// Time available per frame at the target frame rate.
+    pub frame_time: Duration,
+}
+
+impl PerformanceBudget {
+    /// How much slower frames run than with docked clocks.
+    ///
+    /// Based on the GPU clock, which is what differs between display modes.
+    /// Clocks with a raised CPU, i.e. boost mode for loading screens, are
+    /// CPU-bound, so they are based on the CPU clock instead. Used to
+    /// simulate the Switch on desktop.
+    pub fn slowdown(&self) -> f32 {
+        if self.clocks.cpu_mhz > ClockProfile::DOCKED.cpu_mhz {
+            ClockProfile::DOCKED.cpu_mhz / self.clocks.cpu_mhz
+        } else {
+            ClockProfile::DOCKED.gpu_mhz / self.clocks.gpu_mhz
+        }
+    }
+}
+
+/// Message sent when the performance mode or the clocks it implies change.
+#[derive(Debug, Clone, Copy, PartialEq, Message)]
+pub struct PerformanceModeChanged {
+    /// Mode before the change.
+    pub previous: PerformanceMode,
+    /// Mode after the change.
+    pub mode: PerformanceMode,
+    /// Display mode the clocks apply to.
+    pub display_mode: DisplayMode,
+    /// Clocks after the change.
+    pub clocks: ClockProfile,
+}
+
+/// System to send [`PerformanceModeChanged`] when the mode or display mode changes.
+fn notify_performance_mode_change(
+    mode: Res<PerformanceMode>,
+    config: Res<SwitchConfig>,
+    mut last: Local<Option<(PerformanceMode, DisplayMode)>>,
+    mut messages: MessageWriter<PerformanceModeChanged>,
+) {
+    let current = (*mode, config.display_mode);
+    let Some(previous) = last.replace(current) else {
+        return;
+    };
+    if previous == current {
+        return;
+    }
+
+    let clocks = mode.clocks(config.display_mode);
+    info!(
+        "Performance mode: {} ({} MHz CPU, {} MHz GPU)",
+        mode.name(),
+        clocks.cpu_mhz,
+        clocks.gpu_mhz
+    );
+    messages.write(PerformanceModeChanged {
+        previous: previous.0,
+        mode: *mode,
+        display_mode: config.display_mode,
+        clocks,
+    });
 }
@@ -0,0 +1,852 @@
+//! Host services behind one trait.
+//!
+//! Plugins talk to the platform through [`SwitchHal`] instead of `cfg`
+//! blocks: the [`Hal`] resource holds a [`DesktopHal`] on desktop, a
+//! [`HorizonHal`] on the console (with the `switch` feature) and can be
+//! replaced with a [`MockHal`] in tests.
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Deref;
+use core::time::Duration;
+
+use bevy::platform::sync::{Mutex, MutexGuard};
+use bevy::prelude::*;
+use log::Level;
+
+use super::{AppletMessage, DisplayMode, LaunchMode, SwitchModel};
+use crate::input::SwitchButton;
+
+/// Platform services used by the plugins.
+///
+/// Covers the clock, native input, the filesystem, logging, applet messages
+/// and the display mode. Paths are `/`-separated; the desktop HAL resolves
+/// relative paths against the working directory.
+pub trait SwitchHal: Send + Sync + 'static {
+    /// Short platform name for logs.
+    fn name(&self) -> &'static str;
+
+    /// Monotonic time since the HAL was created.
+    fn now(&self) -> Duration;
+
+    /// Block the current thread.
+    fn sleep(&self, duration: Duration);
+
+    /// Native controller state, or `None` when input comes from Bevy's input
+    /// plugins.
+    fn input(&self) -> Option<HalInput> {
+        None
+    }
+
+    /// Read a whole file.
+    fn read_file(&self, path: &str) -> Result<Vec<u8>, FsError>;
+
+    /// Read up to `len` bytes of a file from `offset`, fewer at its end.
+    ///
+    /// The default reads the whole file; HALs with seekable files read only
+    /// the range, e.g. the RomFS of a large NRO.
+    fn read_file_range(&self, path: &str, offset: u64, len: usize) -> Result<Vec<u8>, FsError> {
+        let data = self.read_file(path)?;
+        Ok(file_range(&data, offset, len).to_vec())
+    }
+
+    /// Create or replace a file, creating missing parent directories.
+    ///
+    /// The data must be durable once this returns.
+    fn write_file(&self, path: &str, data: &[u8]) -> Result<(), FsError>;
+
+    /// Rename a file, replacing the destination.
+    fn rename(&self, from: &str, to: &str) -> Result<(), FsError>;
+
+    /// Delete a file.
+    fn remove_file(&self, path: &str) -> Result<(), FsError>;
+
+    /// List the names of the files and directories in a directory.
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, FsError>;
+
+    /// Make the renames and removals under a directory durable.
+    ///
+    /// Console save data only keeps changes once its device is committed,
+    /// and desktop filesystems may lose renames until the directory is
+    /// synced. The default does nothing, for filesystems where every call is
+    /// durable once it returns.
+    fn sync(&self, _path: &str) -> Result<(), FsError> {
+        Ok(())
+    }
+
+    /// Write a log line.
+    fn log(&self, level: Level, message: &str);
+
+    /// Write a formatted line to the platform's debug output, e.g.
+    /// `svcOutputDebugString` on the console or stdout on desktop.
+    ///
+    /// Used by the [`SwitchLogger`](crate::logging::SwitchLogger), so unlike
+    /// [`log`](Self::log) it must not go through the logger again.
+    fn debug_output(&self, _line: &str) {}
+
+    /// Show an error to the player, e.g. after a crash.
+    fn show_error(&self, _title: &str, _message: &str) {}
+
+    /// Take the next pending applet message, if any.
+    fn poll_applet_message(&self) -> Option<AppletMessage> {
+        None
+    }
+
+    /// Wait for the next applet message and take it.
+    ///
+    /// Called by the [`SwitchRunner`](crate::runner::SwitchRunner) while the
+    /// game is paused, instead of polling in a loop. The default sleeps for
+    /// one 60 FPS frame and then polls, so it may return `None`.
+    fn wait_applet_message(&self) -> Option<AppletMessage> {
+        self.sleep(Duration::from_micros(16_667));
+        self.poll_applet_message()
+    }
+
+    /// Called after each frame, e.g. to wait for vsync.
+    fn end_frame(&self) {}
+
+    /// Console operation mode, or `None` when not running on a console.
+    fn display_mode(&self) -> Option<DisplayMode> {
+        None
+    }
+
+    /// Console model.
+    fn model(&self) -> SwitchModel {
+        SwitchModel::Original
+    }
+
+    /// Whether the game is running in an emulator.
+    fn is_emulator(&self) -> bool {
+        false
+    }
+
+    /// How the game was started.
+    fn launch_mode(&self) -> LaunchMode {
+        LaunchMode::Application
+    }
+
+    /// Memory the system gives the game in bytes, or `None` if it cannot be
+    /// queried, e.g. on desktop.
+    fn memory_limit(&self) -> Option<u64> {
+        None
+    }
+
+    /// Look up an environment variable.
+    fn env_var(&self, _name: &str) -> Option<String> {
+        None
+    }
+
+    /// Path of the running NRO, or `None` when not started from one, e.g. on
+    /// desktop.
+    fn executable_path(&self) -> Option<String> {
+        None
+    }
+
+    /// Resolve a path against the running NRO's directory, e.g. `crash.txt`
+    /// to `sdmc:/switch/crab/crash.txt` for `sdmc:/switch/crab/crab.nro`.
+    ///
+    /// Absolute paths, paths with a device, and every path when not started
+    /// from an NRO are returned as they are.
+    fn game_path(&self, path: &str) -> String {
+        let absolute = path.starts_with('/')
+            || path
+                .split_once(":/")
+                .is_some_and(|(device, _)| !device.contains('/'));
+        match self.executable_path() {
+            Some(executable) if !absolute => match executable.rsplit_once('/') {
+                Some((dir, _)) => alloc::format!("{dir}/{path}"),
+                None => path.into(),
+            },
+            _ => path.into(),
+        }
+    }
+}
+
+/// Native controller state reported by [`SwitchHal::input`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HalInput {
+    /// Currently pressed buttons.
+    pub buttons: Vec<SwitchButton>,
+    /// Left stick position (-1.0 to 1.0)
+    pub left_stick: Vec2,
+    /// Right stick position (-1.0 to 1.0)
+    pub right_stick: Vec2,
+}
+
+/// Error returned by [`SwitchHal`] filesystem operations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FsError {
+    /// The file does not exist
+    NotFound,
+    /// The platform has no filesystem access
+    Unsupported,
+    /// Any other error, with the platform's message
+    Other(String),
+}
+
+impl fmt::Display for FsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FsError::NotFound => f.write_str("file not found"),
+            FsError::Unsupported => f.write_str("filesystem not available"),
+            FsError::Other(message) => f.write_str(message),
+        }
+    }
+}
+
+impl core::error::Error for FsError {}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for FsError {
+    fn from(error: std::io::Error) -> Self {
+        match error.kind() {
+            std::io::ErrorKind::NotFound => FsError::NotFound,
+            std::io::ErrorKind::Unsupported => FsError::Unsupported,
+            _ => FsError::Other(alloc::format!("{error}")),
+        }
+    }
+}
+
+/// Resource holding the app's [`SwitchHal`].
+///
+/// Inserted by [`SwitchConfigPlugin`](super::SwitchConfigPlugin) unless the
+/// app already has one, so insert your own first to replace it:
+///
+/// ```rust
+/// use bevy::prelude::*;
+/// use switchbrew_bevy::platform::{Hal, MockHal};
+/// use switchbrew_bevy::prelude::*;
+///
+/// let mut app = App::new();
+/// app.insert_resource(Hal::new(MockHal::new()))
+///     .add_plugins(SwitchPlugin::default());
+/// ```
+#[derive(Resource, Clone)]
+pub struct Hal(Arc<dyn SwitchHal>);
+
+impl Hal {
+    /// Wrap a HAL implementation.
+    pub fn new(hal: impl SwitchHal) -> Self {
+        Self(Arc::new(hal))
+    }
+}
+
+impl Default for Hal {
+    /// Pick the HAL for the current platform.
+    ///
+    /// Without `std`, off the console, this is an empty [`MockHal`].
+    fn default() -> Self {
+        #[cfg(feature = "switch")]
+        if super::HostOs::current() == super::HostOs::Horizon {
+            return Self::new(HorizonHal::new());
+        }
+        Self::new(host_hal())
+    }
+}
+
+#[cfg(feature = "std")]
+fn host_hal() -> DesktopHal {
+    DesktopHal::new()
+}
+
+#[cfg(not(feature = "std"))]
+fn host_hal() -> MockHal {
+    MockHal::new()
+}
+
+impl Deref for Hal {
+    type Target = dyn SwitchHal;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.0
+    }
+}
+
+impl fmt::Debug for Hal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Hal").field(&self.0.name()).finish()
+    }
+}
+
+/// HAL for desktop builds, backed by `std`.
+///
+/// Files are resolved relative to the working directory, log lines go
+/// through Bevy's logger and debug output goes to stdout. Errors are shown
+/// in a native dialog where one is available.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct DesktopHal {
+    start: std::time::Instant,
+}
+
+#[cfg(feature = "std")]
+impl DesktopHal {
+    /// Create the HAL, starting its clock.
+    pub fn new() -> Self {
+        Self {
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for DesktopHal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl SwitchHal for DesktopHal {
+    fn name(&self) -> &'static str {
+        "desktop"
+    }
+
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+
+    fn read_file(&self, path: &str) -> Result<Vec<u8>, FsError> {
+        Ok(std::fs::read(path)?)
+    }
+
+    fn read_file_range(&self, path: &str, offset: u64, len: usize) -> Result<Vec<u8>, FsError> {
+        use std::io::{Read, Seek, SeekFrom};
+
+        let mut file = std::fs::File::open(path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut data = Vec::new();
+        file.take(len as u64).read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    fn write_file(&self, path: &str, data: &[u8]) -> Result<(), FsError> {
+        use std::io::Write;
+
+        if let Some(parent) = std::path::Path::new(path).parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::File::create(path)?;
+        file.write_all(data)?;
+        Ok(file.sync_all()?)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<(), FsError> {
+        Ok(std::fs::rename(from, to)?)
+    }
+
+    fn remove_file(&self, path: &str) -> Result<(), FsError> {
+        Ok(std::fs::remove_file(path)?)
+    }
+
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, FsError> {
+        let mut names = Vec::new();
+        for entry in std::fs::read_dir(path)? {
+            names.push(entry?.file_name().to_string_lossy().into_owned());
+        }
+        Ok(names)
+    }
+
+    fn sync(&self, path: &str) -> Result<(), FsError> {
+        fn sync_dir(path: &std::path::Path) -> std::io::Result<()> {
+            for entry in std::fs::read_dir(path)? {
+                let entry = entry?;
+                if entry.file_type()?.is_dir() {
+                    sync_dir(&entry.path())?;
+                }
+            }
+            // Only Unix can open a directory to sync it; NTFS journals renames
+            #[cfg(unix)]
+            std::fs::File::open(path)?.sync_all()?;
+            Ok(())
+        }
+
+        match sync_dir(std::path::Path::new(path)) {
+            // Nothing was written there yet
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            result => Ok(result?),
+        }
+    }
+
+    fn log(&self, level: Level, message: &str) {
+        match level {
+            Level::Error => error!("{message}"),
+            Level::Warn => warn!("{message}"),
+            Level::Info => info!("{message}"),
+            Level::Debug => debug!("{message}"),
+            Level::Trace => trace!("{message}"),
+        }
+    }
+
+    fn debug_output(&self, line: &str) {
+        use std::io::Write;
+
+        // Unlike `println!`, don't panic if stdout is closed
+        let _ = writeln!(std::io::stdout().lock(), "{line}");
+    }
+
+    /// Open a dialog with `zenity`, `osascript` or PowerShell without waiting
+    /// for it, so it stays up if the game aborts.
+    fn show_error(&self, title: &str, message: &str) {
+        use std::process::{Command, Stdio};
+
+        eprintln!("{title}: {message}");
+        let mut command = if cfg!(target_os = "windows") {
+            let mut command = Command::new("powershell");
+            command.args([
+                "-NoProfile",
+                "-Command",
+                "Add-Type -AssemblyName PresentationFramework; \
+                 [System.Windows.MessageBox]::Show($env:SWITCHBREW_ERROR_MESSAGE, \
+                 $env:SWITCHBREW_ERROR_TITLE, 'OK', 'Error')",
+            ]);
+            command
+        } else if cfg!(target_os = "macos") {
+            let mut command = Command::new("osascript");
+            command.args([
+                "-e",
+                "display alert (system attribute \"SWITCHBREW_ERROR_TITLE\") \
+                 message (system attribute \"SWITCHBREW_ERROR_MESSAGE\") as critical",
+            ]);
+            command
+        } else {
+            let mut command = Command::new("zenity");
+            command.args([
+                "--error",
+                "--no-markup",
+                "--title",
+                title,
+                "--text",
+                message,
+            ]);
+            command
+        };
+        // Passed through the environment so they need no quoting
+        let _ = command
+            .env("SWITCHBREW_ERROR_TITLE", title)
+            .env("SWITCHBREW_ERROR_MESSAGE", message)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+    }
+
+    fn env_var(&self, name: &str) -> Option<String> {
+        std::env::var(name).ok()
+    }
+}
+
+/// HAL for the console, backed by libnx.
+///
+/// The clock reads the system tick counter, applet messages, the operation
+/// mode, launch mode, console model and emulator detection come from the
+/// applet and settings services, the memory limit from the kernel, and files
+/// go through the devices the homebrew runtime mounts:
+/// `sdmc:/switch/game/save.json` is on the SD card, as is any path without a
+/// `device:/` prefix. Native input is not wired up, so input comes
+/// from Bevy's input plugins, and logs go to the [`log`] facade. Debug output
+/// goes to `svcOutputDebugString`, shown in emulators' debug logs. Errors are
+/// shown in the error applet, with a copy in the debug output.
+#[cfg(feature = "switch")]
+#[derive(Debug, Clone)]
+pub struct HorizonHal {
+    start: u64,
+}
+
+#[cfg(feature = "switch")]
+impl HorizonHal {
+    /// Create the HAL.
+    pub fn new() -> Self {
+        Self {
+            start: super::nx::system_tick(),
+        }
+    }
+}
+
+#[cfg(feature = "switch")]
+impl Default for HorizonHal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "switch")]
+impl SwitchHal for HorizonHal {
+    fn name(&self) -> &'static str {
+        "horizon"
+    }
+
+    fn now(&self) -> Duration {
+        super::nx::ticks_to_duration(super::nx::system_tick().saturating_sub(self.start))
+    }
+
+    fn sleep(&self, duration: Duration) {
+        super::nx::sleep(duration);
+    }
+
+    fn read_file(&self, path: &str) -> Result<Vec<u8>, FsError> {
+        super::nx::read_file(path)
+    }
+
+    fn read_file_range(&self, path: &str, offset: u64, len: usize) -> Result<Vec<u8>, FsError> {
+        super::nx::read_file_range(path, offset, len)
+    }
+
+    fn write_file(&self, path: &str, data: &[u8]) -> Result<(), FsError> {
+        super::nx::write_file(path, data)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<(), FsError> {
+        super::nx::rename(from, to)
+    }
+
+    fn remove_file(&self, path: &str) -> Result<(), FsError> {
+        super::nx::remove_file(path)
+    }
+
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, FsError> {
+        super::nx::list_dir(path)
+    }
+
+    fn sync(&self, path: &str) -> Result<(), FsError> {
+        super::nx::commit(path)
+    }
+
+    fn log(&self, level: Level, message: &str) {
+        log::log!(level, "{message}");
+    }
+
+    fn debug_output(&self, line: &str) {
+        #[cfg(all(target_os = "horizon", target_arch = "aarch64"))]
+        // SAFETY: svcOutputDebugString (SVC 0x27) only reads `len` bytes from
+        // the pointer, and returns its result code in x0
+        unsafe {
+            core::arch::asm!(
+                "svc 0x27",
+                inout("x0") line.as_ptr() => _,
+                in("x1") line.len(),
+                options(nostack),
+            );
+        }
+        #[cfg(not(all(target_os = "horizon", target_arch = "aarch64")))]
+        let _ = line;
+    }
+
+    /// Write the error to the debug output, which outlives the game, then
+    /// show it in the error applet.
+    fn show_error(&self, title: &str, message: &str) {
+        self.debug_output(title);
+        for line in message.lines() {
+            self.debug_output(line);
+        }
+        if let Err(result) = super::nx::show_error(title, message) {
+            self.debug_output(&alloc::format!(
+                "Failed to show the error applet: {result:#x}"
+            ));
+        }
+    }
+
+    fn poll_applet_message(&self) -> Option<AppletMessage> {
+        super::nx::poll_applet_message()
+    }
+
+    fn wait_applet_message(&self) -> Option<AppletMessage> {
+        super::nx::wait_applet_message()
+    }
+
+    fn display_mode(&self) -> Option<DisplayMode> {
+        Some(super::nx::operation_mode())
+    }
+
+    fn model(&self) -> SwitchModel {
+        super::nx::product_model()
+    }
+
+    fn is_emulator(&self) -> bool {
+        super::nx::is_emulator()
+    }
+
+    fn launch_mode(&self) -> LaunchMode {
+        super::nx::launch_mode()
+    }
+
+    fn memory_limit(&self) -> Option<u64> {
+        super::nx::memory_limit()
+    }
+
+    fn executable_path(&self) -> Option<String> {
+        super::nx::executable_path()
+    }
+}
+
+/// In-memory HAL for tests.
+///
+/// Clones share their state, so keep one to script the platform after
+/// handing another to [`Hal::new`]:
+///
+/// ```rust
+/// use switchbrew_bevy::platform::{AppletMessage, Hal, MockHal, SwitchHal};
+///
+/// let mock = MockHal::new().with_file("switch.toml", "target_fps = 30");
+/// let hal = Hal::new(mock.clone());
+///
+/// mock.push_message(AppletMessage::FocusLost);
+/// assert_eq!(hal.poll_applet_message(), Some(AppletMessage::FocusLost));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MockHal {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    now: Duration,
+    frames: u64,
+    input: Option<HalInput>,
+    files: BTreeMap<String, Vec<u8>>,
+    logs: Vec<(Level, String)>,
+    debug_output: Vec<String>,
+    errors: Vec<(String, String)>,
+    messages: VecDeque<AppletMessage>,
+    display_mode: Option<DisplayMode>,
+    model: SwitchModel,
+    emulator: bool,
+    launch_mode: LaunchMode,
+    memory_limit: Option<u64>,
+    env: BTreeMap<String, String>,
+    executable_path: Option<String>,
+}
+
+impl MockHal {
+    /// Create an empty mock that behaves like a desktop host.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Report a console operation mode, as if running on a console.
+    pub fn with_display_mode(self, mode: DisplayMode) -> Self {
+        self.state().display_mode = Some(mode);
+        self
+    }
+
+    /// Set the console model.
+    pub fn with_model(self, model: SwitchModel) -> Self {
+        self.state().model = model;
+        self
+    }
+
+    /// Report running in an emulator.
+    pub fn with_emulator(self, emulator: bool) -> Self {
+        self.state().emulator = emulator;
+        self
+    }
+
+    /// Report being started in a launch mode.
+    pub fn with_launch_mode(self, mode: LaunchMode) -> Self {
+        self.state().launch_mode = mode;
+        self
+    }
+
+    /// Report the memory the system gives the game, as on the console.
+    pub fn with_memory_limit(self, bytes: u64) -> Self {
+        self.state().memory_limit = Some(bytes);
+        self
+    }
+
+    /// Set an environment variable.
+    pub fn with_env(self, name: &str, value: &str) -> Self {
+        self.state().env.insert(name.into(), value.into());
+        self
+    }
+
+    /// Report running from an NRO, e.g. one added with
+    /// [`with_file`](Self::with_file).
+    pub fn with_executable_path(self, path: &str) -> Self {
+        self.state().executable_path = Some(path.into());
+        self
+    }
+
+    /// Add a file.
+    pub fn with_file(self, path: &str, data: impl Into<Vec<u8>>) -> Self {
+        self.state().files.insert(path.into(), data.into());
+        self
+    }
+
+    /// Set the native controller state.
+    pub fn set_input(&self, input: Option<HalInput>) {
+        self.state().input = input;
+    }
+
+    /// Queue an applet message.
+    pub fn push_message(&self, message: AppletMessage) {
+        self.state().messages.push_back(message);
+    }
+
+    /// Move the clock forward.
+    pub fn advance(&self, duration: Duration) {
+        self.state().now += duration;
+    }
+
+    /// Get a file's contents.
+    pub fn file(&self, path: &str) -> Option<Vec<u8>> {
+        self.state().files.get(path).cloned()
+    }
+
+    /// Get all file paths.
+    pub fn paths(&self) -> Vec<String> {
+        self.state().files.keys().cloned().collect()
+    }
+
+    /// Get the log lines written so far.
+    pub fn logs(&self) -> Vec<(Level, String)> {
+        self.state().logs.clone()
+    }
+
+    /// Get the debug output lines written so far.
+    pub fn debug_lines(&self) -> Vec<String> {
+        self.state().debug_output.clone()
+    }
+
+    /// Get the errors shown so far, as `(title, message)`.
+    pub fn errors(&self) -> Vec<(String, String)> {
+        self.state().errors.clone()
+    }
+
+    /// Get the number of frames ended so far.
+    pub fn frames(&self) -> u64 {
+        self.state().frames
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl SwitchHal for MockHal {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn now(&self) -> Duration {
+        self.state().now
+    }
+
+    /// Move the clock forward instead of blocking.
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+
+    fn input(&self) -> Option<HalInput> {
+        self.state().input.clone()
+    }
+
+    fn read_file(&self, path: &str) -> Result<Vec<u8>, FsError> {
+        self.file(path).ok_or(FsError::NotFound)
+    }
+
+    fn read_file_range(&self, path: &str, offset: u64, len: usize) -> Result<Vec<u8>, FsError> {
+        let state = self.state();
+        let data = state.files.get(path).ok_or(FsError::NotFound)?;
+        Ok(file_range(data, offset, len).to_vec())
+    }
+
+    fn write_file(&self, path: &str, data: &[u8]) -> Result<(), FsError> {
+        self.state().files.insert(path.into(), data.to_vec());
+        Ok(())
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<(), FsError> {
+        let mut state = self.state();
+        let data = state.files.remove(from).ok_or(FsError::NotFound)?;
+        state.files.insert(to.into(), data);
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &str) -> Result<(), FsError> {
+        self.state()
+            .files
+            .remove(path)
+            .map(drop)
+            .ok_or(FsError::NotFound)
+    }
+
+    /// Directories exist implicitly as prefixes of file paths.
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, FsError> {
+        let prefix = alloc::format!("{}/", path.trim_end_matches('/'));
+        let mut names: Vec<String> = self
+            .state()
+            .files
+            .keys()
+            .filter_map(|file| file.strip_prefix(&prefix))
+            .map(|rest| rest.split('/').next().unwrap_or(rest).into())
+            .collect();
+        if names.is_empty() {
+            return Err(FsError::NotFound);
+        }
+        names.dedup();
+        Ok(names)
+    }
+
+    fn log(&self, level: Level, message: &str) {
+        self.state().logs.push((level, message.into()));
+    }
+
+    fn debug_output(&self, line: &str) {
+        self.state().debug_output.push(line.into());
+    }
+
+    fn show_error(&self, title: &str, message: &str) {
+        self.state().errors.push((title.into(), message.into()));
+    }
+
+    fn poll_applet_message(&self) -> Option<AppletMessage> {
+        self.state().messages.pop_front()
+    }
+
+    fn end_frame(&self) {
+        self.state().frames += 1;
+    }
+
+    fn display_mode(&self) -> Option<DisplayMode> {
+        self.state().display_mode
+    }
+
+    fn model(&self) -> SwitchModel {
+        self.state().model
+    }
+
+    fn is_emulator(&self) -> bool {
+        self.state().emulator
+    }
+
+    fn launch_mode(&self) -> LaunchMode {
+        self.state().launch_mode
+    }
+
+    fn memory_limit(&self) -> Option<u64> {
+        self.state().memory_limit
+    }
+
+    fn env_var(&self, name: &str) -> Option<String> {
+        self.state().env.get(name).cloned()
+    }
+
+    fn executable_path(&self) -> Option<String> {
+        self.state().executable_path.clone()
+    }
+}
+
+/// Get up to `len` bytes of a file's contents from `offset`.
+fn file_range(data: &[u8], offset: u64, len: usize) -> &[u8] {
+    let start = usize::try_from(offset).map_or(data.len(), |start| start.min(data.len()));
+    &data[start..start.saturating_add(len).min(data.len())]
+}
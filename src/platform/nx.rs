@@ -0,0 +1,1048 @@
+//! Bindings to the libnx services used by [`HorizonHal`](super::HorizonHal).
+//!
+//! On Horizon these call into libnx, which the homebrew runtime links and
+//! initializes before `main`. On other targets the same functions behave
+//! like a console whose services are all unavailable, so the `switch`
+//! feature still builds and runs on the host.
+
+use alloc::collections::VecDeque;
+use alloc::ffi::CString;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use bevy::platform::sync::{Mutex, MutexGuard};
+
+use super::{AppletMessage, DisplayMode, FsError, LaunchMode, SwitchModel};
+
+/// libnx result code, 0 on success.
+type NxResult = u32;
+
+/// Frequency of the system tick counter.
+const TICKS_PER_SECOND: u64 = 19_200_000;
+
+/// `AppletMessage_ExitRequest`
+const MESSAGE_EXIT_REQUEST: u32 = 4;
+/// `AppletMessage_FocusStateChanged`
+const MESSAGE_FOCUS_STATE_CHANGED: u32 = 15;
+/// `AppletMessage_Resume`
+const MESSAGE_RESUME: u32 = 16;
+/// `AppletMessage_RequestToPrepareSleep`
+const MESSAGE_REQUEST_TO_PREPARE_SLEEP: u32 = 25;
+/// `AppletMessage_OperationModeChanged`
+const MESSAGE_OPERATION_MODE_CHANGED: u32 = 30;
+/// `AppletFocusState_InFocus`
+const FOCUS_STATE_IN_FOCUS: u32 = 1;
+/// `AppletFocusState_OutOfFocus`
+const FOCUS_STATE_OUT_OF_FOCUS: u32 = 2;
+/// `AppletFocusState_Background`: the HOME menu or another app took over and
+/// the game is suspended.
+const FOCUS_STATE_BACKGROUND: u32 = 3;
+
+/// `AppletOperationMode_Handheld`
+const OPERATION_MODE_HANDHELD: u32 = 0;
+
+/// `AppletFocusHandlingMode_NoSuspend`: keep running out of focus and report
+/// focus changes as messages, so the runner pauses the game itself.
+const FOCUS_HANDLING_MODE_NO_SUSPEND: u32 = 1;
+
+/// `SetSysProductModel_Hoag`, the Switch Lite.
+const PRODUCT_MODEL_HOAG: i32 = 4;
+/// `SetSysProductModel_Aula`, the OLED model.
+const PRODUCT_MODEL_AULA: i32 = 6;
+
+/// `svcGetInfo` type answered by Atmosphère's kernel with its version.
+const INFO_TYPE_MESOSPHERE_META: u32 = 65000;
+/// `InfoType_TotalMemorySize`
+const INFO_TYPE_TOTAL_MEMORY_SIZE: u32 = 6;
+/// `CUR_PROCESS_HANDLE`, the running process.
+const CUR_PROCESS_HANDLE: u32 = 0xFFFF_8001;
+
+/// `AppletType_LibraryApplet`, which homebrew started from the Album runs as.
+const APPLET_TYPE_LIBRARY_APPLET: i32 = 2;
+
+/// `FsOpenMode_Read`
+const OPEN_MODE_READ: u32 = 1;
+/// `FsOpenMode_Write`
+const OPEN_MODE_WRITE: u32 = 2;
+/// `FsDirOpenMode_ReadDirs | FsDirOpenMode_ReadFiles`
+const DIR_OPEN_MODE_ALL: u32 = 3;
+/// `FsWriteOption_Flush`
+const WRITE_OPTION_FLUSH: u32 = 1;
+/// `FsError_PathNotFound`
+const RESULT_PATH_NOT_FOUND: NxResult = 0x202;
+/// `FsError_PathAlreadyExists`
+const RESULT_PATH_ALREADY_EXISTS: NxResult = 0x402;
+
+/// Device for paths without a `device:/` prefix.
+const DEFAULT_DEVICE: &str = "sdmc";
+/// Directory entries read per `fsDirRead` call.
+const DIR_ENTRIES_PER_READ: usize = 16;
+
+/// libnx `Service`, the IPC session behind filesystem objects.
+#[repr(C)]
+#[allow(dead_code)] // Fields are only read by libnx
+#[derive(Debug, Default)]
+struct Service {
+    session: u32,
+    own_handle: u32,
+    object_id: u32,
+    pointer_buffer_size: u16,
+}
+
+/// libnx `FsFileSystem`
+#[repr(C)]
+#[allow(dead_code)] // Fields are only read by libnx
+struct FsFileSystem {
+    s: Service,
+}
+
+/// libnx `FsFile`
+#[repr(C)]
+#[allow(dead_code)] // Fields are only read by libnx
+#[derive(Debug, Default)]
+struct FsFile {
+    s: Service,
+}
+
+/// libnx `FsDir`
+#[repr(C)]
+#[allow(dead_code)] // Fields are only read by libnx
+#[derive(Debug, Default)]
+struct FsDir {
+    s: Service,
+}
+
+/// libnx `FsDirectoryEntry`, 0x310 bytes.
+#[repr(C)]
+#[allow(dead_code)] // Fields are only read by libnx
+struct FsDirectoryEntry {
+    name: [u8; 0x301],
+    pad: [u8; 3],
+    kind: i8,
+    pad2: [u8; 3],
+    file_size: i64,
+}
+
+impl FsDirectoryEntry {
+    const EMPTY: Self = Self {
+        name: [0; 0x301],
+        pad: [0; 3],
+        kind: 0,
+        pad2: [0; 3],
+        file_size: 0,
+    };
+
+    fn name(&self) -> String {
+        let len = self
+            .name
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(0x301);
+        String::from_utf8_lossy(&self.name[..len]).into_owned()
+    }
+}
+
+/// libnx `ErrorApplicationConfig`, filled in by `errorApplicationCreate`.
+#[repr(C, align(8))]
+struct ErrorApplicationConfig {
+    data: [u8; 0x1214],
+}
+
+/// libnx `Event`, only handled by pointer.
+#[repr(C)]
+struct Event {
+    _private: [u8; 0],
+}
+
+#[cfg(target_os = "horizon")]
+mod sys {
+    use core::ffi::c_char;
+
+    use super::{
+        ErrorApplicationConfig, Event, FsDir, FsDirectoryEntry, FsFile, FsFileSystem, NxResult,
+    };
+
+    #[link(name = "nx")]
+    unsafe extern "C" {
+        pub fn appletInitialize() -> NxResult;
+        pub fn appletExit();
+        pub fn appletSetFocusHandlingMode(mode: u32) -> NxResult;
+        pub fn appletSetOperationModeChangedNotification(flag: bool) -> NxResult;
+        pub fn appletGetOperationMode() -> u32;
+        pub fn appletGetAppletType() -> i32;
+        pub fn setsysInitialize() -> NxResult;
+        pub fn setsysExit();
+        pub fn setsysGetProductModel(out: *mut i32) -> NxResult;
+        pub fn svcGetInfo(out: *mut u64, id0: u32, handle: u32, id1: u64) -> NxResult;
+        pub fn svcSleepThread(nano: i64);
+        pub fn appletGetMessage(out: *mut u32) -> NxResult;
+        pub fn appletProcessMessage(msg: u32) -> bool;
+        pub fn appletGetMessageEvent() -> *mut Event;
+        pub fn appletGetFocusState() -> u32;
+        pub fn errorApplicationCreate(
+            config: *mut ErrorApplicationConfig,
+            dialog_message: *const c_char,
+            fullscreen_message: *const c_char,
+        );
+        pub fn errorApplicationShow(config: *mut ErrorApplicationConfig) -> NxResult;
+        pub fn eventWait(event: *mut Event, timeout: u64) -> NxResult;
+        pub fn fsdevGetDeviceFileSystem(name: *const c_char) -> *mut FsFileSystem;
+        pub fn fsFsCreateFile(
+            fs: *mut FsFileSystem,
+            path: *const c_char,
+            size: i64,
+            option: u32,
+        ) -> NxResult;
+        pub fn fsFsDeleteFile(fs: *mut FsFileSystem, path: *const c_char) -> NxResult;
+        pub fn fsFsCommit(fs: *mut FsFileSystem) -> NxResult;
+        pub fn fsFsCreateDirectory(fs: *mut FsFileSystem, path: *const c_char) -> NxResult;
+        pub fn fsFsRenameFile(
+            fs: *mut FsFileSystem,
+            from: *const c_char,
+            to: *const c_char,
+        ) -> NxResult;
+        pub fn fsFsOpenFile(
+            fs: *mut FsFileSystem,
+            path: *const c_char,
+            mode: u32,
+            out: *mut FsFile,
+        ) -> NxResult;
+        pub fn fsFsOpenDirectory(
+            fs: *mut FsFileSystem,
+            path: *const c_char,
+            mode: u32,
+            out: *mut FsDir,
+        ) -> NxResult;
+        pub fn fsFileRead(
+            file: *mut FsFile,
+            offset: i64,
+            buf: *mut u8,
+            size: u64,
+            option: u32,
+            bytes_read: *mut u64,
+        ) -> NxResult;
+        pub fn fsFileWrite(
+            file: *mut FsFile,
+            offset: i64,
+            buf: *const u8,
+            size: u64,
+            option: u32,
+        ) -> NxResult;
+        pub fn fsFileFlush(file: *mut FsFile) -> NxResult;
+        pub fn fsFileSetSize(file: *mut FsFile, size: i64) -> NxResult;
+        pub fn fsFileGetSize(file: *mut FsFile, out: *mut i64) -> NxResult;
+        pub fn fsFileClose(file: *mut FsFile);
+        pub fn fsDirRead(
+            dir: *mut FsDir,
+            total_entries: *mut i64,
+            max_entries: usize,
+            buf: *mut FsDirectoryEntry,
+        ) -> NxResult;
+        pub fn fsDirClose(dir: *mut FsDir);
+
+        static __system_argc: i32;
+        static __system_argv: *mut *mut c_char;
+    }
+
+    /// First argument the homebrew loader passed, or null without arguments.
+    pub unsafe fn argv0() -> *const c_char {
+        // SAFETY: the homebrew runtime sets the arguments up before `main`
+        // and never changes them
+        unsafe {
+            if __system_argc > 0 && !__system_argv.is_null() {
+                *__system_argv
+            } else {
+                core::ptr::null()
+            }
+        }
+    }
+}
+
+/// Host stand-ins with the same signatures, reporting every service but the
+/// applet messages scripted through [`host`] as unavailable.
+#[cfg(not(target_os = "horizon"))]
+#[allow(non_snake_case, clippy::missing_safety_doc)]
+mod sys {
+    use alloc::collections::VecDeque;
+    use core::ffi::c_char;
+    use core::ptr;
+
+    use bevy::platform::sync::{Mutex, MutexGuard};
+
+    use super::{
+        ErrorApplicationConfig, Event, FsDir, FsDirectoryEntry, FsFile, FsFileSystem, NxResult,
+    };
+
+    /// Any failing result code.
+    const UNAVAILABLE: NxResult = 1;
+
+    /// Applet state, kept like libnx: the focus state and operation mode it
+    /// returns are cached, and only updated when a message is processed.
+    pub struct Applet {
+        pub messages: VecDeque<u32>,
+        pub focus: u32,
+        pub operation_mode: u32,
+        cached_focus: u32,
+        cached_operation_mode: u32,
+    }
+
+    static APPLET: Mutex<Applet> = Mutex::new(Applet {
+        messages: VecDeque::new(),
+        focus: super::FOCUS_STATE_IN_FOCUS,
+        operation_mode: OPERATION_MODE_CONSOLE,
+        cached_focus: super::FOCUS_STATE_IN_FOCUS,
+        cached_operation_mode: OPERATION_MODE_CONSOLE,
+    });
+
+    /// `AppletOperationMode_Console`, so the host reads as docked.
+    const OPERATION_MODE_CONSOLE: u32 = 1;
+
+    pub fn applet() -> MutexGuard<'static, Applet> {
+        APPLET
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub unsafe fn appletInitialize() -> NxResult {
+        UNAVAILABLE
+    }
+
+    pub unsafe fn appletExit() {}
+
+    pub unsafe fn appletSetFocusHandlingMode(_mode: u32) -> NxResult {
+        UNAVAILABLE
+    }
+
+    pub unsafe fn appletSetOperationModeChangedNotification(_flag: bool) -> NxResult {
+        UNAVAILABLE
+    }
+
+    pub unsafe fn appletGetOperationMode() -> u32 {
+        applet().cached_operation_mode
+    }
+
+    /// `AppletType_None`, as before the applet service is initialized.
+    pub unsafe fn appletGetAppletType() -> i32 {
+        -2
+    }
+
+    pub unsafe fn setsysInitialize() -> NxResult {
+        UNAVAILABLE
+    }
+
+    pub unsafe fn setsysExit() {}
+
+    pub unsafe fn setsysGetProductModel(_out: *mut i32) -> NxResult {
+        UNAVAILABLE
+    }
+
+    pub unsafe fn svcGetInfo(_out: *mut u64, _id0: u32, _handle: u32, _id1: u64) -> NxResult {
+        UNAVAILABLE
+    }
+
+    pub unsafe fn svcSleepThread(_nano: i64) {}
+
+    pub unsafe fn argv0() -> *const c_char {
+        ptr::null()
+    }
+
+    pub unsafe fn appletGetMessage(out: *mut u32) -> NxResult {
+        match applet().messages.pop_front() {
+            Some(message) => {
+                // SAFETY: the caller passes a valid pointer
+                unsafe { *out = message };
+                0
+            }
+            None => UNAVAILABLE,
+        }
+    }
+
+    pub unsafe fn appletProcessMessage(msg: u32) -> bool {
+        let mut applet = applet();
+        match msg {
+            super::MESSAGE_EXIT_REQUEST => return false,
+            super::MESSAGE_FOCUS_STATE_CHANGED => applet.cached_focus = applet.focus,
+            super::MESSAGE_OPERATION_MODE_CHANGED => {
+                applet.cached_operation_mode = applet.operation_mode
+            }
+            _ => {}
+        }
+        true
+    }
+
+    pub unsafe fn appletGetMessageEvent() -> *mut Event {
+        ptr::null_mut()
+    }
+
+    pub unsafe fn appletGetFocusState() -> u32 {
+        applet().cached_focus
+    }
+
+    pub unsafe fn errorApplicationCreate(
+        _config: *mut ErrorApplicationConfig,
+        _dialog_message: *const c_char,
+        _fullscreen_message: *const c_char,
+    ) {
+    }
+
+    pub unsafe fn errorApplicationShow(_config: *mut ErrorApplicationConfig) -> NxResult {
+        UNAVAILABLE
+    }
+
+    pub unsafe fn eventWait(_event: *mut Event, _timeout: u64) -> NxResult {
+        UNAVAILABLE
+    }
+
+    /// No devices are mounted, so the filesystem calls below are never made.
+    pub unsafe fn fsdevGetDeviceFileSystem(_name: *const c_char) -> *mut FsFileSystem {
+        ptr::null_mut()
+    }
+
+    pub unsafe fn fsFsCreateFile(
+        _fs: *mut FsFileSystem,
+        _path: *const c_char,
+        _size: i64,
+        _option: u32,
+    ) -> NxResult {
+        UNAVAILABLE
+    }
+
+    pub unsafe fn fsFsDeleteFile(_fs: *mut FsFileSystem, _path: *const c_char) -> NxResult {
+        UNAVAILABLE
+    }
+
+    pub unsafe fn fsFsCommit(_fs: *mut FsFileSystem) -> NxResult {
+        UNAVAILABLE
+    }
+
+    pub unsafe fn fsFsCreateDirectory(_fs: *mut FsFileSystem, _path: *const c_char) -> NxResult {
+        UNAVAILABLE
+    }
+
+    pub unsafe fn fsFsRenameFile(
+        _fs: *mut FsFileSystem,
+        _from: *const c_char,
+        _to: *const c_char,
+    ) -> NxResult {
+        UNAVAILABLE
+    }
+
+    pub unsafe fn fsFsOpenFile(
+        _fs: *mut FsFileSystem,
+        _path: *const c_char,
+        _mode: u32,
+        _out: *mut FsFile,
+    ) -> NxResult {
+        UNAVAILABLE
+    }
+
+    pub unsafe fn fsFsOpenDirectory(
+        _fs: *mut FsFileSystem,
+        _path: *const c_char,
+        _mode: u32,
+        _out: *mut FsDir,
+    ) -> NxResult {
+        UNAVAILABLE
+    }
+
+    pub unsafe fn fsFileRead(
+        _file: *mut FsFile,
+        _offset: i64,
+        _buf: *mut u8,
+        _size: u64,
+        _option: u32,
+        _bytes_read: *mut u64,
+    ) -> NxResult {
+        UNAVAILABLE
+    }
+
+    pub unsafe fn fsFileWrite(
+        _file: *mut FsFile,
+        _offset: i64,
+        _buf: *const u8,
+        _size: u64,
+        _option: u32,
+    ) -> NxResult {
+        UNAVAILABLE
+    }
+
+    pub unsafe fn fsFileFlush(_file: *mut FsFile) -> NxResult {
+        UNAVAILABLE
+    }
+
+    pub unsafe fn fsFileSetSize(_file: *mut FsFile, _size: i64) -> NxResult {
+        UNAVAILABLE
+    }
+
+    pub unsafe fn fsFileGetSize(_file: *mut FsFile, _out: *mut i64) -> NxResult {
+        UNAVAILABLE
+    }
+
+    pub unsafe fn fsFileClose(_file: *mut FsFile) {}
+
+    pub unsafe fn fsDirRead(
+        _dir: *mut FsDir,
+        _total_entries: *mut i64,
+        _max_entries: usize,
+        _buf: *mut FsDirectoryEntry,
+    ) -> NxResult {
+        UNAVAILABLE
+    }
+
+    pub unsafe fn fsDirClose(_dir: *mut FsDir) {}
+}
+
+/// Take a reference to the applet service and have it report focus and
+/// operation mode changes as messages.
+///
+/// Returns the failing result code; on success, balance with
+/// [`exit_applet`].
+pub fn init_applet() -> Result<(), NxResult> {
+    // SAFETY: the settings calls are only made after a successful initialize,
+    // which is undone if they fail
+    unsafe {
+        let result = sys::appletInitialize();
+        if result != 0 {
+            return Err(result);
+        }
+        for result in [
+            sys::appletSetFocusHandlingMode(FOCUS_HANDLING_MODE_NO_SUSPEND),
+            sys::appletSetOperationModeChangedNotification(true),
+        ] {
+            if result != 0 {
+                sys::appletExit();
+                return Err(result);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Release the reference taken by [`init_applet`].
+pub fn exit_applet() {
+    // SAFETY: only called after a successful `init_applet`
+    unsafe { sys::appletExit() }
+}
+
+/// Get the current operation mode.
+///
+/// The system only tells handheld and docked apart, so tabletop mode reads as
+/// handheld.
+pub fn operation_mode() -> DisplayMode {
+    // SAFETY: the applet service is initialized by the homebrew runtime
+    match unsafe { sys::appletGetOperationMode() } {
+        OPERATION_MODE_HANDHELD => DisplayMode::Handheld,
+        _ => DisplayMode::Docked,
+    }
+}
+
+/// Get the console model, or [`SwitchModel::Original`] if `set:sys` is not
+/// available.
+pub fn product_model() -> SwitchModel {
+    let mut model = 0;
+    // SAFETY: the service is only used between a successful initialize and
+    // its matching exit, and the call writes one integer to `model`
+    let result = unsafe {
+        if sys::setsysInitialize() != 0 {
+            return SwitchModel::Original;
+        }
+        let result = sys::setsysGetProductModel(&mut model);
+        sys::setsysExit();
+        result
+    };
+
+    match (result, model) {
+        (0, PRODUCT_MODEL_HOAG) => SwitchModel::Lite,
+        (0, PRODUCT_MODEL_AULA) => SwitchModel::Oled,
+        _ => SwitchModel::Original,
+    }
+}
+
+/// Check whether the game runs in an emulator.
+///
+/// Homebrew on hardware runs under Atmosphère, whose kernel answers a custom
+/// `svcGetInfo` query. Emulators only implement Nintendo's info types, so
+/// the query fails there. This is a heuristic: an emulator that mimics
+/// Atmosphère reads as hardware.
+pub fn is_emulator() -> bool {
+    let mut version = 0;
+    // SAFETY: svcGetInfo only writes the answer to `version`
+    let result = unsafe { sys::svcGetInfo(&mut version, INFO_TYPE_MESOSPHERE_META, 0, 0) };
+    result != 0
+}
+
+/// Get how the homebrew loader started the game.
+pub fn launch_mode() -> LaunchMode {
+    // SAFETY: the applet service is initialized by the homebrew runtime
+    match unsafe { sys::appletGetAppletType() } {
+        APPLET_TYPE_LIBRARY_APPLET => LaunchMode::Applet,
+        _ => LaunchMode::Application,
+    }
+}
+
+/// Get the memory the system gives the game, in bytes.
+pub fn memory_limit() -> Option<u64> {
+    let mut size = 0;
+    // SAFETY: svcGetInfo only writes the answer to `size`
+    let result = unsafe {
+        sys::svcGetInfo(
+            &mut size,
+            INFO_TYPE_TOTAL_MEMORY_SIZE,
+            CUR_PROCESS_HANDLE,
+            0,
+        )
+    };
+    (result == 0).then_some(size)
+}
+
+/// Get the path of the running NRO, which the homebrew loader passes as the
+/// first argument, e.g. `sdmc:/switch/game.nro`.
+pub fn executable_path() -> Option<String> {
+    // SAFETY: the argument is null or a nul-terminated string that lives
+    // until the runtime exits
+    let path = unsafe {
+        let path = sys::argv0();
+        if path.is_null() {
+            return None;
+        }
+        core::ffi::CStr::from_ptr(path)
+    };
+    Some(path.to_string_lossy().into_owned()).filter(|path| !path.is_empty())
+}
+
+/// Show the error applet with a short message and its details, blocking
+/// until the player closes it.
+///
+/// Returns the failing result code.
+pub fn show_error(message: &str, details: &str) -> Result<(), NxResult> {
+    // libnx copies the strings, cutting them at their first nul
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    let details = CString::new(details.replace('\0', "")).unwrap_or_default();
+    let mut config = ErrorApplicationConfig { data: [0; 0x1214] };
+    // SAFETY: the config is only used by libnx during these calls, and the
+    // strings are nul-terminated
+    let result = unsafe {
+        sys::errorApplicationCreate(&mut config, message.as_ptr(), details.as_ptr());
+        sys::errorApplicationShow(&mut config)
+    };
+    match result {
+        0 => Ok(()),
+        _ => Err(result),
+    }
+}
+
+/// Read the system tick counter.
+pub fn system_tick() -> u64 {
+    #[cfg(all(target_os = "horizon", target_arch = "aarch64"))]
+    {
+        let ticks: u64;
+        // SAFETY: CNTPCT_EL0 is readable from user mode and has no side effects
+        unsafe {
+            core::arch::asm!("mrs {}, cntpct_el0", out(reg) ticks, options(nomem, nostack));
+        }
+        ticks
+    }
+    #[cfg(not(all(target_os = "horizon", target_arch = "aarch64")))]
+    0
+}
+
+/// Convert system ticks to a duration.
+pub fn ticks_to_duration(ticks: u64) -> Duration {
+    let nanos = u128::from(ticks) * 1_000_000_000 / u128::from(TICKS_PER_SECOND);
+    Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX))
+}
+
+/// Put the current thread to sleep.
+pub fn sleep(duration: Duration) {
+    let nanos = i64::try_from(duration.as_nanos()).unwrap_or(i64::MAX);
+    // SAFETY: svcSleepThread takes no pointers
+    unsafe { sys::svcSleepThread(nanos) }
+}
+
+/// Focus and sleep state, turned into the messages the runner handles.
+///
+/// libnx reports focus states and sleep separately, and only reports focus
+/// changes as a new state, so one libnx message can make up to two messages.
+struct AppletState {
+    /// Focus state from the last focus change.
+    focus: u32,
+    /// Whether the console is going to sleep.
+    asleep: bool,
+    /// Focus last reported to the runner.
+    focused: bool,
+    /// Suspension last reported to the runner.
+    suspended: bool,
+    /// Messages not taken yet.
+    pending: VecDeque<AppletMessage>,
+}
+
+impl AppletState {
+    /// Queue the messages for the focus and suspension changes since the last
+    /// report.
+    fn report(&mut self) {
+        let suspended = self.asleep || self.focus == FOCUS_STATE_BACKGROUND;
+        // The game keeps its focus while suspended, so resuming only reports
+        // a focus change if there was one
+        let focused = match self.focus {
+            FOCUS_STATE_IN_FOCUS => true,
+            FOCUS_STATE_OUT_OF_FOCUS => false,
+            _ => self.focused,
+        };
+
+        if suspended != self.suspended {
+            self.suspended = suspended;
+            self.pending.push_back(if suspended {
+                AppletMessage::Suspended
+            } else {
+                AppletMessage::Resumed
+            });
+        }
+        if focused != self.focused {
+            self.focused = focused;
+            self.pending.push_back(if focused {
+                AppletMessage::FocusGained
+            } else {
+                AppletMessage::FocusLost
+            });
+        }
+    }
+
+    /// Handle a libnx message, queueing the messages it makes.
+    fn handle(&mut self, message: u32) {
+        // SAFETY: the applet service is initialized by the homebrew runtime.
+        // libnx caches the focus state and operation mode, and only updates
+        // them when processing the message that changes them
+        unsafe { sys::appletProcessMessage(message) };
+        match message {
+            MESSAGE_EXIT_REQUEST => self.pending.push_back(AppletMessage::ExitRequested),
+            MESSAGE_FOCUS_STATE_CHANGED => {
+                // SAFETY: the applet service is initialized by the homebrew
+                // runtime
+                self.focus = unsafe { sys::appletGetFocusState() };
+                self.report();
+            }
+            MESSAGE_REQUEST_TO_PREPARE_SLEEP => {
+                self.asleep = true;
+                self.report();
+            }
+            MESSAGE_RESUME => {
+                // Applications are usually not told before the console
+                // sleeps, so report the sleep once it is over
+                if !self.asleep {
+                    self.asleep = true;
+                    self.report();
+                }
+                self.asleep = false;
+                self.report();
+            }
+            MESSAGE_OPERATION_MODE_CHANGED => self
+                .pending
+                .push_back(AppletMessage::OperationModeChanged(operation_mode())),
+            _ => {}
+        }
+    }
+}
+
+static APPLET: Mutex<AppletState> = Mutex::new(AppletState {
+    focus: FOCUS_STATE_IN_FOCUS,
+    asleep: false,
+    focused: true,
+    suspended: false,
+    pending: VecDeque::new(),
+});
+
+fn applet() -> MutexGuard<'static, AppletState> {
+    APPLET
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Take the next applet message the runner handles, skipping the others.
+pub fn poll_applet_message() -> Option<AppletMessage> {
+    let mut applet = applet();
+    loop {
+        if let Some(message) = applet.pending.pop_front() {
+            return Some(message);
+        }
+        let mut message = 0;
+        // SAFETY: the call writes one integer to `message`, and fails once the
+        // queue is empty
+        if unsafe { sys::appletGetMessage(&mut message) } != 0 {
+            return None;
+        }
+        applet.handle(message);
+    }
+}
+
+/// Block until the applet posts a message, then take it.
+///
+/// Returns `None` if the message was one the runner does not handle.
+pub fn wait_applet_message() -> Option<AppletMessage> {
+    if let Some(message) = applet().pending.pop_front() {
+        return Some(message);
+    }
+    // SAFETY: the message event belongs to the applet service, which the
+    // homebrew runtime initializes before `main`
+    unsafe {
+        sys::eventWait(sys::appletGetMessageEvent(), u64::MAX);
+    }
+    poll_applet_message()
+}
+
+/// Script the host stand-ins for libnx, to test [`HorizonHal`] off the
+/// console.
+///
+/// [`HorizonHal`]: super::HorizonHal
+#[cfg(not(target_os = "horizon"))]
+#[doc(hidden)]
+pub mod host {
+    /// Move the focus to a libnx `AppletFocusState` and post the message
+    /// telling the game.
+    pub fn change_focus(state: u32) {
+        let mut applet = super::sys::applet();
+        applet.focus = state;
+        applet
+            .messages
+            .push_back(super::MESSAGE_FOCUS_STATE_CHANGED);
+    }
+
+    /// Switch to a libnx `AppletOperationMode` and post the message telling
+    /// the game.
+    pub fn change_operation_mode(mode: u32) {
+        let mut applet = super::sys::applet();
+        applet.operation_mode = mode;
+        applet
+            .messages
+            .push_back(super::MESSAGE_OPERATION_MODE_CHANGED);
+    }
+}
+
+/// Read a whole file.
+pub fn read_file(path: &str) -> Result<Vec<u8>, FsError> {
+    read_file_range(path, 0, usize::MAX)
+}
+
+/// Read up to `len` bytes of a file from `offset`, fewer at its end.
+pub fn read_file_range(path: &str, offset: u64, len: usize) -> Result<Vec<u8>, FsError> {
+    let (fs, path) = resolve(path)?;
+    let mut file = File::open(fs, &path, OPEN_MODE_READ)?;
+
+    let mut size = 0;
+    // SAFETY: the file is open and the call writes one integer to `size`
+    check(unsafe { sys::fsFileGetSize(&mut file.0, &mut size) })?;
+    let available = u64::try_from(size)
+        .unwrap_or_default()
+        .saturating_sub(offset);
+    let len = usize::try_from(available).map_or(len, |available| available.min(len));
+    let mut data = alloc::vec![0; len];
+
+    let mut filled = 0;
+    while filled < data.len() {
+        let remaining = &mut data[filled..];
+        let position = i64::try_from(offset + filled as u64).map_err(|_| too_large())?;
+        let mut read = 0;
+        // SAFETY: the call writes at most `remaining.len()` bytes to it
+        check(unsafe {
+            sys::fsFileRead(
+                &mut file.0,
+                position,
+                remaining.as_mut_ptr(),
+                remaining.len() as u64,
+                0,
+                &mut read,
+            )
+        })?;
+        if read == 0 {
+            // The file shrank while it was read
+            data.truncate(filled);
+            break;
+        }
+        filled += read as usize;
+    }
+    Ok(data)
+}
+
+/// Create or replace a file and flush it, creating missing parent
+/// directories.
+pub fn write_file(path: &str, data: &[u8]) -> Result<(), FsError> {
+    let (fs, path) = resolve(path)?;
+    create_parents(fs, &path)?;
+
+    // SAFETY: `fs` is a mounted device and `path` a nul-terminated string
+    let result = unsafe { sys::fsFsCreateFile(fs, path.as_ptr(), 0, 0) };
+    if result != RESULT_PATH_ALREADY_EXISTS {
+        check(result)?;
+    }
+
+    let mut file = File::open(fs, &path, OPEN_MODE_WRITE)?;
+    let size = i64::try_from(data.len()).map_err(|_| too_large())?;
+    // SAFETY: the file is open for writing and the write only reads `data`
+    unsafe {
+        check(sys::fsFileSetSize(&mut file.0, size))?;
+        check(sys::fsFileWrite(
+            &mut file.0,
+            0,
+            data.as_ptr(),
+            data.len() as u64,
+            WRITE_OPTION_FLUSH,
+        ))?;
+        check(sys::fsFileFlush(&mut file.0))
+    }
+}
+
+/// Rename a file, replacing the destination.
+///
+/// The filesystem cannot replace a file in one step, so the destination is
+/// deleted first.
+pub fn rename(from: &str, to: &str) -> Result<(), FsError> {
+    let (fs, from) = resolve(from)?;
+    let (to_fs, to) = resolve(to)?;
+    if fs != to_fs {
+        return Err(FsError::Other(String::from(
+            "cannot rename a file to another device",
+        )));
+    }
+
+    // SAFETY: `fs` is a mounted device and the paths nul-terminated strings
+    unsafe {
+        let result = sys::fsFsDeleteFile(fs, to.as_ptr());
+        if result != RESULT_PATH_NOT_FOUND {
+            check(result)?;
+        }
+        check(sys::fsFsRenameFile(fs, from.as_ptr(), to.as_ptr()))
+    }
+}
+
+/// Delete a file.
+pub fn remove_file(path: &str) -> Result<(), FsError> {
+    let (fs, path) = resolve(path)?;
+    // SAFETY: `fs` is a mounted device and `path` a nul-terminated string
+    check(unsafe { sys::fsFsDeleteFile(fs, path.as_ptr()) })
+}
+
+/// Commit the changes on the device a path is on, e.g. a mounted save data
+/// device, which drops uncommitted changes when the game exits.
+pub fn commit(path: &str) -> Result<(), FsError> {
+    let (fs, _) = resolve(path)?;
+    // SAFETY: `fs` is a mounted device
+    check(unsafe { sys::fsFsCommit(fs) })
+}
+
+/// List the names of the files and directories in a directory.
+pub fn list_dir(path: &str) -> Result<Vec<String>, FsError> {
+    let (fs, path) = resolve(path)?;
+    let mut dir = FsDir::default();
+    // SAFETY: `fs` is a mounted device, `path` a nul-terminated string and
+    // the directory is closed by `Dir` once opened
+    check(unsafe { sys::fsFsOpenDirectory(fs, path.as_ptr(), DIR_OPEN_MODE_ALL, &mut dir) })?;
+    let mut dir = Dir(dir);
+
+    let mut entries: Vec<_> = (0..DIR_ENTRIES_PER_READ)
+        .map(|_| FsDirectoryEntry::EMPTY)
+        .collect();
+    let mut names = Vec::new();
+    loop {
+        let mut count = 0;
+        // SAFETY: the call writes at most `entries.len()` entries
+        check(unsafe {
+            sys::fsDirRead(&mut dir.0, &mut count, entries.len(), entries.as_mut_ptr())
+        })?;
+        if count <= 0 {
+            return Ok(names);
+        }
+        names.extend(
+            entries
+                .iter()
+                .take(count as usize)
+                .map(FsDirectoryEntry::name),
+        );
+    }
+}
+
+/// Open file, closed on drop.
+struct File(FsFile);
+
+impl File {
+    fn open(fs: *mut FsFileSystem, path: &CString, mode: u32) -> Result<Self, FsError> {
+        let mut file = FsFile::default();
+        // SAFETY: `fs` is a mounted device and `path` a nul-terminated string
+        check(unsafe { sys::fsFsOpenFile(fs, path.as_ptr(), mode, &mut file) })?;
+        Ok(Self(file))
+    }
+}
+
+impl Drop for File {
+    fn drop(&mut self) {
+        // SAFETY: the file was opened successfully and is closed once
+        unsafe { sys::fsFileClose(&mut self.0) }
+    }
+}
+
+/// Open directory, closed on drop.
+struct Dir(FsDir);
+
+impl Drop for Dir {
+    fn drop(&mut self) {
+        // SAFETY: the directory was opened successfully and is closed once
+        unsafe { sys::fsDirClose(&mut self.0) }
+    }
+}
+
+/// Find the device a path is on, e.g. `sdmc` for `sdmc:/saves/1.sav`, and
+/// the absolute path on it. Paths without a device are on the SD card.
+fn resolve(path: &str) -> Result<(*mut FsFileSystem, CString), FsError> {
+    let (device, path) = match path.split_once(":/") {
+        Some((device, path)) if !device.contains('/') => (device, path),
+        _ => (DEFAULT_DEVICE, path),
+    };
+    let device = c_string(device)?;
+    // SAFETY: `device` is a nul-terminated string, and the returned file
+    // system stays mounted until the runtime exits
+    let fs = unsafe { sys::fsdevGetDeviceFileSystem(device.as_ptr()) };
+    if fs.is_null() {
+        return Err(FsError::Unsupported);
+    }
+    Ok((fs, c_string(&format!("/{}", path.trim_start_matches('/')))?))
+}
+
+/// Create the missing directories above a path.
+fn create_parents(fs: *mut FsFileSystem, path: &CString) -> Result<(), FsError> {
+    let path = path.to_bytes();
+    for (end, _) in path
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter(|(_, byte)| **byte == b'/')
+    {
+        let parent = CString::new(&path[..end]).map_err(|_| nul_in_path())?;
+        // SAFETY: `fs` is a mounted device and `parent` a nul-terminated string
+        let result = unsafe { sys::fsFsCreateDirectory(fs, parent.as_ptr()) };
+        if result != RESULT_PATH_ALREADY_EXISTS {
+            check(result)?;
+        }
+    }
+    Ok(())
+}
+
+fn c_string(text: &str) -> Result<CString, FsError> {
+    CString::new(text).map_err(|_| nul_in_path())
+}
+
+fn nul_in_path() -> FsError {
+    FsError::Other(String::from("path contains a nul byte"))
+}
+
+fn too_large() -> FsError {
+    FsError::Other(String::from("file is too large"))
+}
+
+/// Convert a filesystem result code.
+fn check(result: NxResult) -> Result<(), FsError> {
+    match result {
+        0 => Ok(()),
+        RESULT_PATH_NOT_FOUND => Err(FsError::NotFound),
+        _ => Err(FsError::Other(format!("filesystem error {result:#x}"))),
+    }
+}
@@ -0,0 +1,661 @@
+//! RomFS images, the read-only filesystem that ships assets inside an NRO.
+//!
+//! [`RomFsBuilder`] packs files into an image and [`RomFs`] reads one back.
+//! Images use the console's layout (hashed directory and file tables
+//! followed by the file data), so they can be embedded into an NRO or
+//! opened by other Switch tools.
+//!
+//! ```rust
+//! use switchbrew_bevy::romfs::{RomFs, RomFsBuilder};
+//!
+//! let mut builder = RomFsBuilder::new();
+//! builder.add_file("sprites/crab.png", b"png".to_vec()).unwrap();
+//!
+//! let romfs = RomFs::new(builder.build()).unwrap();
+//! assert_eq!(romfs.file("sprites/crab.png"), Some(&b"png"[..]));
+//! assert!(romfs.is_dir("sprites"));
+//! ```
+//!
+//! On the console, [`RomFs::from_executable`] reads the image packed into the
+//! running NRO. With the `std` feature, [`RomFsAssetPlugin`] registers an
+//! image as a Bevy asset source.
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::nro::{self, Nro};
+use crate::platform::{FsError, SwitchHal};
+
+#[cfg(feature = "std")]
+mod asset;
+
+#[cfg(feature = "std")]
+pub use asset::{RomFsAssetPlugin, RomFsAssetReader};
+
+/// Size of the image header.
+const HEADER_SIZE: usize = 0x50;
+
+/// Offset of the file data, after the header and padding.
+const DATA_OFFSET: usize = 0x200;
+
+/// Alignment of each file in the data.
+const FILE_ALIGN: usize = 0x10;
+
+/// Marks a missing entry in the tables.
+const EMPTY: u32 = u32::MAX;
+
+/// Size of a directory entry without its name.
+const DIR_ENTRY_SIZE: usize = 0x18;
+
+/// Size of a file entry without its name.
+const FILE_ENTRY_SIZE: usize = 0x20;
+
+/// Error reading or building a RomFS image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RomFsError {
+    /// The header or tables are truncated or out of bounds
+    InvalidImage,
+    /// A path is empty or has an empty, `.` or `..` component
+    InvalidPath(String),
+    /// A path is added twice, or as both a file and a directory
+    Conflict(String),
+}
+
+impl fmt::Display for RomFsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomFsError::InvalidImage => f.write_str("invalid RomFS image"),
+            RomFsError::InvalidPath(path) => write!(f, "invalid RomFS path {path:?}"),
+            RomFsError::Conflict(path) => write!(f, "RomFS path {path:?} is already used"),
+        }
+    }
+}
+
+impl core::error::Error for RomFsError {}
+
+/// Entry of a RomFS directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomFsEntry {
+    /// Name within the directory.
+    pub name: String,
+    /// Size in bytes, or `None` for a directory.
+    pub size: Option<u64>,
+}
+
+impl RomFsEntry {
+    /// Whether the entry is a directory.
+    pub fn is_dir(&self) -> bool {
+        self.size.is_none()
+    }
+}
+
+/// A RomFS image in memory.
+#[derive(Clone)]
+pub struct RomFs {
+    image: Vec<u8>,
+    dir_hashes: Table,
+    dirs: Table,
+    file_hashes: Table,
+    files: Table,
+    data_offset: usize,
+}
+
+/// Byte range of a table in the image.
+#[derive(Debug, Clone, Copy)]
+struct Table {
+    offset: usize,
+    size: usize,
+}
+
+/// Directory entry read from the image.
+struct DirEntry<'a> {
+    parent: u32,
+    sibling: u32,
+    child_dir: u32,
+    child_file: u32,
+    hash_next: u32,
+    name: &'a [u8],
+}
+
+/// File entry read from the image.
+struct FileEntry<'a> {
+    parent: u32,
+    sibling: u32,
+    offset: u64,
+    size: u64,
+    hash_next: u32,
+    name: &'a [u8],
+}
+
+impl RomFs {
+    /// Read an image, checking its header and table bounds.
+    pub fn new(image: Vec<u8>) -> Result<Self, RomFsError> {
+        let field = |index: usize| -> Result<usize, RomFsError> {
+            read_u64(&image, index * 8)
+                .and_then(|value| usize::try_from(value).ok())
+                .ok_or(RomFsError::InvalidImage)
+        };
+        if field(0)? != HEADER_SIZE {
+            return Err(RomFsError::InvalidImage);
+        }
+        let table = |index: usize| -> Result<Table, RomFsError> {
+            let table = Table {
+                offset: field(index)?,
+                size: field(index + 1)?,
+            };
+            match table.offset.checked_add(table.size) {
+                Some(end) if end <= image.len() => Ok(table),
+                _ => Err(RomFsError::InvalidImage),
+            }
+        };
+        let romfs = Self {
+            dir_hashes: table(1)?,
+            dirs: table(3)?,
+            file_hashes: table(5)?,
+            files: table(7)?,
+            data_offset: field(9)?,
+            image: Vec::new(),
+        };
+        if romfs.dir_hashes.size < 4 || romfs.file_hashes.size < 4 || romfs.dirs.size == 0 {
+            return Err(RomFsError::InvalidImage);
+        }
+        Ok(Self { image, ..romfs })
+    }
+
+    /// Read the RomFS from the asset section of the running NRO.
+    ///
+    /// The NRO is found through [`SwitchHal::executable_path`]. Only its
+    /// headers and the RomFS image are read, not the program.
+    pub fn from_executable(hal: &dyn SwitchHal) -> Result<Self, FsError> {
+        let path = hal.executable_path().ok_or(FsError::Unsupported)?;
+        let invalid = |error: &dyn fmt::Display| FsError::Other(format!("{path}: {error}"));
+
+        let start = hal.read_file_range(&path, 0, nro::HEADER_OFFSET + nro::HEADER_SIZE)?;
+        let assets = Nro::segments_size(&start).map_err(|error| invalid(&error))? as u64;
+        let header = hal.read_file_range(&path, assets, nro::ASSET_HEADER_SIZE)?;
+        let [_, _, romfs] = Nro::asset_ranges(&header).map_err(|error| invalid(&error))?;
+        let (offset, len) = romfs.ok_or_else(|| FsError::Other(format!("{path} has no RomFS")))?;
+
+        let image = hal.read_file_range(&path, assets.saturating_add(offset as u64), len)?;
+        if image.len() < len {
+            return Err(invalid(&"RomFS out of bounds"));
+        }
+        Self::new(image).map_err(|error| invalid(&error))
+    }
+
+    /// Get the raw image.
+    pub fn image(&self) -> &[u8] {
+        &self.image
+    }
+
+    /// Get the contents of a file, or `None` if there is no such file.
+    pub fn file(&self, path: &str) -> Option<&[u8]> {
+        let (dir, name) = match path.trim_matches('/').rsplit_once('/') {
+            Some((dir, name)) => (self.find_dir(dir)?, name),
+            None => (0, path.trim_matches('/')),
+        };
+        let entry = self.find_file(dir, name.as_bytes())?;
+        let start = self
+            .data_offset
+            .checked_add(usize::try_from(entry.offset).ok()?)?;
+        self.image
+            .get(start..start.checked_add(usize::try_from(entry.size).ok()?)?)
+    }
+
+    /// Whether a directory exists. The root is `""`.
+    pub fn is_dir(&self, path: &str) -> bool {
+        self.find_dir(path).is_some()
+    }
+
+    /// List a directory, subdirectories first, or `None` if there is no
+    /// such directory.
+    pub fn read_dir(&self, path: &str) -> Option<Vec<RomFsEntry>> {
+        let dir = self.dir(self.find_dir(path)?)?;
+        let mut entries = Vec::new();
+        let mut next = dir.child_dir;
+        while next != EMPTY && entries.len() < self.dirs.size {
+            let child = self.dir(next)?;
+            entries.push(RomFsEntry {
+                name: String::from_utf8_lossy(child.name).into_owned(),
+                size: None,
+            });
+            next = child.sibling;
+        }
+        let mut next = dir.child_file;
+        while next != EMPTY && entries.len() < self.dirs.size + self.files.size {
+            let child = self.file_entry(next)?;
+            entries.push(RomFsEntry {
+                name: String::from_utf8_lossy(child.name).into_owned(),
+                size: Some(child.size),
+            });
+            next = child.sibling;
+        }
+        Some(entries)
+    }
+
+    /// Get the paths of all files, sorted.
+    pub fn paths(&self) -> Vec<String> {
+        let mut paths = Vec::new();
+        let mut dirs = vec![String::new()];
+        while let Some(dir) = dirs.pop() {
+            for entry in self.read_dir(&dir).unwrap_or_default() {
+                let is_dir = entry.is_dir();
+                let path = if dir.is_empty() {
+                    entry.name
+                } else {
+                    format!("{dir}/{}", entry.name)
+                };
+                if is_dir {
+                    dirs.push(path);
+                } else {
+                    paths.push(path);
+                }
+            }
+        }
+        paths.sort();
+        paths
+    }
+
+    /// Find a directory's entry offset by its path.
+    fn find_dir(&self, path: &str) -> Option<u32> {
+        let mut dir = 0;
+        for name in path.split('/').filter(|name| !name.is_empty()) {
+            let mut next = self.bucket(self.dir_hashes, dir, name.as_bytes())?;
+            let mut found = None;
+            // Bounded, in case a corrupt chain loops
+            for _ in 0..self.dirs.size {
+                let entry = self.dir(next)?;
+                if entry.parent == dir && entry.name == name.as_bytes() {
+                    found = Some(next);
+                    break;
+                }
+                next = entry.hash_next;
+            }
+            dir = found?;
+        }
+        Some(dir)
+    }
+
+    /// Find a file in a directory by its name.
+    fn find_file(&self, dir: u32, name: &[u8]) -> Option<FileEntry<'_>> {
+        let mut next = self.bucket(self.file_hashes, dir, name)?;
+        for _ in 0..self.files.size {
+            let entry = self.file_entry(next)?;
+            if entry.parent == dir && entry.name == name {
+                return Some(entry);
+            }
+            next = entry.hash_next;
+        }
+        None
+    }
+
+    /// Get the first entry in the hash bucket of a name.
+    fn bucket(&self, table: Table, parent: u32, name: &[u8]) -> Option<u32> {
+        let buckets = table.size / 4;
+        let index = path_hash(parent, name) as usize % buckets;
+        read_u32(&self.image, table.offset + index * 4).filter(|&offset| offset != EMPTY)
+    }
+
+    fn dir(&self, offset: u32) -> Option<DirEntry<'_>> {
+        let table = self.table(self.dirs)?;
+        let entry = table.get(offset as usize..)?;
+        let name_len = read_u32(entry, 0x14)? as usize;
+        Some(DirEntry {
+            parent: read_u32(entry, 0)?,
+            sibling: read_u32(entry, 4)?,
+            child_dir: read_u32(entry, 8)?,
+            child_file: read_u32(entry, 0xc)?,
+            hash_next: read_u32(entry, 0x10)?,
+            name: entry.get(DIR_ENTRY_SIZE..DIR_ENTRY_SIZE.checked_add(name_len)?)?,
+        })
+    }
+
+    fn file_entry(&self, offset: u32) -> Option<FileEntry<'_>> {
+        let table = self.table(self.files)?;
+        let entry = table.get(offset as usize..)?;
+        let name_len = read_u32(entry, 0x1c)? as usize;
+        Some(FileEntry {
+            parent: read_u32(entry, 0)?,
+            sibling: read_u32(entry, 4)?,
+            offset: read_u64(entry, 8)?,
+            size: read_u64(entry, 0x10)?,
+            hash_next: read_u32(entry, 0x18)?,
+            name: entry.get(FILE_ENTRY_SIZE..FILE_ENTRY_SIZE.checked_add(name_len)?)?,
+        })
+    }
+
+    fn table(&self, table: Table) -> Option<&[u8]> {
+        self.image.get(table.offset..table.offset + table.size)
+    }
+}
+
+impl fmt::Debug for RomFs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RomFs")
+            .field("size", &self.image.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Packs files into a RomFS image.
+#[derive(Debug, Clone, Default)]
+pub struct RomFsBuilder {
+    root: DirNode,
+}
+
+#[derive(Debug, Clone, Default)]
+struct DirNode {
+    dirs: BTreeMap<String, DirNode>,
+    files: BTreeMap<String, Vec<u8>>,
+}
+
+impl RomFsBuilder {
+    /// Create an empty image.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file, creating its parent directories.
+    pub fn add_file(&mut self, path: &str, data: Vec<u8>) -> Result<&mut Self, RomFsError> {
+        let names = split_path(path)?;
+        let (name, parents) = names.split_last().unwrap();
+        let dir = self.dir_mut(path, parents)?;
+        if dir.dirs.contains_key(*name) || dir.files.contains_key(*name) {
+            return Err(RomFsError::Conflict(path.into()));
+        }
+        dir.files.insert(name.to_string(), data);
+        Ok(self)
+    }
+
+    /// Add a directory and its parents, e.g. to keep an empty directory.
+    pub fn add_dir(&mut self, path: &str) -> Result<&mut Self, RomFsError> {
+        let names = split_path(path)?;
+        self.dir_mut(path, &names)?;
+        Ok(self)
+    }
+
+    /// Add all files under a host directory, keeping their relative paths.
+    #[cfg(feature = "std")]
+    pub fn add_host_dir(&mut self, dir: impl AsRef<std::path::Path>) -> std::io::Result<&mut Self> {
+        use std::io::{Error, ErrorKind};
+
+        let mut pending = vec![(dir.as_ref().to_path_buf(), String::new())];
+        while let Some((host, path)) = pending.pop() {
+            if !path.is_empty() {
+                self.add_dir(&path)
+                    .map_err(|error| Error::new(ErrorKind::InvalidInput, error))?;
+            }
+            for entry in std::fs::read_dir(&host)? {
+                let entry = entry?;
+                let name = entry.file_name().into_string().map_err(|name| {
+                    Error::new(ErrorKind::InvalidData, format!("{name:?} is not UTF-8"))
+                })?;
+                let child = if path.is_empty() {
+                    name
+                } else {
+                    format!("{path}/{name}")
+                };
+                if entry.file_type()?.is_dir() {
+                    pending.push((entry.path(), child));
+                } else {
+                    self.add_file(&child, std::fs::read(entry.path())?)
+                        .map_err(|error| Error::new(ErrorKind::InvalidInput, error))?;
+                }
+            }
+        }
+        Ok(self)
+    }
+
+    /// Number of files added.
+    pub fn file_count(&self) -> usize {
+        fn count(dir: &DirNode) -> usize {
+            dir.files.len() + dir.dirs.values().map(count).sum::<usize>()
+        }
+        count(&self.root)
+    }
+
+    fn dir_mut(&mut self, path: &str, names: &[&str]) -> Result<&mut DirNode, RomFsError> {
+        let mut dir = &mut self.root;
+        for name in names {
+            if dir.files.contains_key(*name) {
+                return Err(RomFsError::Conflict(path.into()));
+            }
+            dir = dir.dirs.entry(name.to_string()).or_default();
+        }
+        Ok(dir)
+    }
+
+    /// Build the image.
+    pub fn build(&self) -> Vec<u8> {
+        // Lay out the entries depth first, in name order
+        let mut dirs = Vec::new();
+        let mut files = Vec::new();
+        collect(&self.root, "", EMPTY, &mut dirs, &mut files);
+
+        let mut dir_offset = 0;
+        for dir in &mut dirs {
+            dir.offset = dir_offset;
+            dir_offset += entry_len(DIR_ENTRY_SIZE, dir.name) as u32;
+        }
+        let mut file_offset = 0;
+        let mut data_len = 0;
+        for file in &mut files {
+            file.offset = file_offset;
+            file_offset += entry_len(FILE_ENTRY_SIZE, file.name) as u32;
+            file.data_offset = data_len;
+            data_len = align(data_len + file.data.len(), FILE_ALIGN);
+        }
+        // Parents of the root and of top-level entries are the root itself
+        let parent_offset = |parent: u32| match parent {
+            EMPTY => 0,
+            index => dirs[index as usize].offset,
+        };
+
+        // Siblings are linked in name order
+        let mut dir_siblings = vec![EMPTY; dirs.len()];
+        let mut file_siblings = vec![EMPTY; files.len()];
+        for dir in &dirs {
+            for pair in dir.child_dirs.windows(2) {
+                dir_siblings[pair[0]] = dirs[pair[1]].offset;
+            }
+            for pair in dir.child_files.windows(2) {
+                file_siblings[pair[0]] = files[pair[1]].offset;
+            }
+        }
+
+        let mut dir_table = vec![0; dir_offset as usize];
+        let mut dir_buckets = vec![EMPTY; bucket_count(dirs.len())];
+        for (index, dir) in dirs.iter().enumerate() {
+            let parent = parent_offset(dir.parent);
+            let bucket = path_hash(parent, dir.name.as_bytes()) as usize % dir_buckets.len();
+            let entry = &mut dir_table[dir.offset as usize..];
+            write_u32(entry, 0, parent);
+            write_u32(entry, 4, dir_siblings[index]);
+            write_u32(
+                entry,
+                8,
+                dir.child_dirs.first().map_or(EMPTY, |&i| dirs[i].offset),
+            );
+            write_u32(
+                entry,
+                0xc,
+                dir.child_files.first().map_or(EMPTY, |&i| files[i].offset),
+            );
+            write_u32(entry, 0x10, dir_buckets[bucket]);
+            write_u32(entry, 0x14, dir.name.len() as u32);
+            entry[DIR_ENTRY_SIZE..][..dir.name.len()].copy_from_slice(dir.name.as_bytes());
+            dir_buckets[bucket] = dir.offset;
+        }
+
+        let mut file_table = vec![0; file_offset as usize];
+        let mut file_buckets = vec![EMPTY; bucket_count(files.len())];
+        for (index, file) in files.iter().enumerate() {
+            let parent = parent_offset(file.parent);
+            let bucket = path_hash(parent, file.name.as_bytes()) as usize % file_buckets.len();
+            let entry = &mut file_table[file.offset as usize..];
+            write_u32(entry, 0, parent);
+            write_u32(entry, 4, file_siblings[index]);
+            write_u64(entry, 8, file.data_offset as u64);
+            write_u64(entry, 0x10, file.data.len() as u64);
+            write_u32(entry, 0x18, file_buckets[bucket]);
+            write_u32(entry, 0x1c, file.name.len() as u32);
+            entry[FILE_ENTRY_SIZE..][..file.name.len()].copy_from_slice(file.name.as_bytes());
+            file_buckets[bucket] = file.offset;
+        }
+
+        // Header, file data, then the tables
+        let mut image = vec![0; DATA_OFFSET];
+        for file in &files {
+            image.resize(DATA_OFFSET + file.data_offset, 0);
+            image.extend_from_slice(file.data);
+        }
+        image.resize(align(image.len(), 4), 0);
+
+        let mut header = [0u64; HEADER_SIZE / 8];
+        header[0] = HEADER_SIZE as u64;
+        for (index, table) in [
+            bytes_of(&dir_buckets),
+            dir_table,
+            bytes_of(&file_buckets),
+            file_table,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            header[1 + index * 2] = image.len() as u64;
+            header[2 + index * 2] = table.len() as u64;
+            image.extend_from_slice(&table);
+        }
+        header[9] = DATA_OFFSET as u64;
+        for (index, value) in header.iter().enumerate() {
+            write_u64(&mut image, index * 8, *value);
+        }
+        image
+    }
+}
+
+/// Directory laid out for [`RomFsBuilder::build`].
+struct DirLayout<'a> {
+    name: &'a str,
+    /// Index of the parent in the layout, [`EMPTY`] for the root.
+    parent: u32,
+    child_dirs: Vec<usize>,
+    child_files: Vec<usize>,
+    offset: u32,
+}
+
+/// File laid out for [`RomFsBuilder::build`].
+struct FileLayout<'a> {
+    name: &'a str,
+    parent: u32,
+    data: &'a [u8],
+    offset: u32,
+    data_offset: usize,
+}
+
+/// Add a directory and everything below it, returning its index.
+fn collect<'a>(
+    node: &'a DirNode,
+    name: &'a str,
+    parent: u32,
+    dirs: &mut Vec<DirLayout<'a>>,
+    files: &mut Vec<FileLayout<'a>>,
+) -> usize {
+    let index = dirs.len();
+    dirs.push(DirLayout {
+        name,
+        parent,
+        child_dirs: Vec::new(),
+        child_files: Vec::new(),
+        offset: 0,
+    });
+    for (name, data) in &node.files {
+        dirs[index].child_files.push(files.len());
+        files.push(FileLayout {
+            name,
+            parent: index as u32,
+            data,
+            offset: 0,
+            data_offset: 0,
+        });
+    }
+    for (name, child) in &node.dirs {
+        let child = collect(child, name, index as u32, dirs, files);
+        dirs[index].child_dirs.push(child);
+    }
+    index
+}
+
+/// Split a path into its names, rejecting empty, `.` and `..` names.
+fn split_path(path: &str) -> Result<Vec<&str>, RomFsError> {
+    let names: Vec<_> = path.trim_start_matches('/').split('/').collect();
+    if names
+        .iter()
+        .any(|name| name.is_empty() || *name == "." || *name == "..")
+    {
+        return Err(RomFsError::InvalidPath(path.into()));
+    }
+    Ok(names)
+}
+
+/// Hash of a name in a directory, as used by the console.
+fn path_hash(parent: u32, name: &[u8]) -> u32 {
+    name.iter().fold(parent ^ 123_456_789, |hash, &byte| {
+        hash.rotate_right(5) ^ u32::from(byte)
+    })
+}
+
+/// Number of hash buckets for a number of entries, as used by the console.
+fn bucket_count(entries: usize) -> usize {
+    match entries {
+        0..3 => 3,
+        3..19 => entries | 1,
+        _ => (entries..)
+            .find(|count| {
+                [2, 3, 5, 7, 11, 13, 17]
+                    .iter()
+                    .all(|prime| count % prime != 0)
+            })
+            .unwrap(),
+    }
+}
+
+/// Size of a table entry with its name, padded to 4 bytes.
+fn entry_len(base: usize, name: &str) -> usize {
+    base + align(name.len(), 4)
+}
+
+fn align(value: usize, alignment: usize) -> usize {
+    value.next_multiple_of(alignment)
+}
+
+fn bytes_of(values: &[u32]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset.checked_add(4)?)?.try_into().ok()?,
+    ))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        bytes.get(offset..offset.checked_add(8)?)?.try_into().ok()?,
+    ))
+}
+
+fn write_u32(bytes: &mut [u8], offset: usize, value: u32) {
+    bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn write_u64(bytes: &mut [u8], offset: usize, value: u64) {
+    bytes[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+}
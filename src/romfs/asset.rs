@@ -0,0 +1,192 @@
+//! Bevy asset source backed by a RomFS.
+
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use bevy::asset::io::{
+    AssetReader, AssetReaderError, AssetSource, AssetSourceId, PathStream, Reader, VecReader,
+};
+use bevy::prelude::*;
+use bevy::tasks::futures_lite::stream;
+
+use super::RomFs;
+use crate::platform::Hal;
+
+/// Plugin that registers a [`RomFsAssetReader`] as an asset source.
+///
+/// Replaces the default `assets` directory unless
+/// [`with_source_name`](Self::with_source_name) is used, which also allows
+/// adding the plugin more than once. Must be added
+/// before `AssetPlugin` (part of `DefaultPlugins`):
+///
+/// ```rust,no_run
+/// use bevy::prelude::*;
+/// use switchbrew_bevy::romfs::RomFsAssetPlugin;
+///
+/// App::new()
+///     .add_plugins(RomFsAssetPlugin::image("target/romfs.bin"))
+///     .add_plugins(DefaultPlugins)
+///     .run();
+/// ```
+#[derive(Debug, Clone)]
+pub struct RomFsAssetPlugin {
+    source: RomFsSource,
+    name: Option<&'static str>,
+}
+
+#[derive(Debug, Clone)]
+enum RomFsSource {
+    Image(PathBuf),
+    Executable,
+    Loaded(Arc<RomFs>),
+}
+
+impl RomFsAssetPlugin {
+    /// Read assets from a RomFS image file, e.g. one packed with
+    /// `cargo switchbrew romfs`, to test packed assets on desktop.
+    pub fn image(path: impl Into<PathBuf>) -> Self {
+        Self {
+            source: RomFsSource::Image(path.into()),
+            name: None,
+        }
+    }
+
+    /// Read assets from the RomFS packed into the running NRO, found through
+    /// the app's [`Hal`] with [`RomFs::from_executable`].
+    pub fn executable() -> Self {
+        Self {
+            source: RomFsSource::Executable,
+            name: None,
+        }
+    }
+
+    /// Read assets from an image already in memory.
+    pub fn new(romfs: RomFs) -> Self {
+        Self {
+            source: RomFsSource::Loaded(Arc::new(romfs)),
+            name: None,
+        }
+    }
+
+    /// Register as a named source, loaded with `<name>://path`, instead of
+    /// replacing the default one.
+    pub fn with_source_name(mut self, name: &'static str) -> Self {
+        self.name = Some(name);
+        self
+    }
+}
+
+impl Plugin for RomFsAssetPlugin {
+    fn build(&self, app: &mut App) {
+        let reader = match &self.source {
+            RomFsSource::Image(path) => match RomFsAssetReader::open(path) {
+                Ok(reader) => reader,
+                Err(error) => {
+                    error!("Failed to open RomFS image {}: {error}", path.display());
+                    return;
+                }
+            },
+            RomFsSource::Executable => {
+                let hal = app
+                    .world()
+                    .get_resource::<Hal>()
+                    .cloned()
+                    .unwrap_or_default();
+                match RomFs::from_executable(&*hal) {
+                    Ok(romfs) => RomFsAssetReader(Arc::new(romfs)),
+                    Err(error) => {
+                        error!("Failed to read the RomFS of the running NRO: {error}");
+                        return;
+                    }
+                }
+            }
+            RomFsSource::Loaded(romfs) => RomFsAssetReader(romfs.clone()),
+        };
+        let id = match self.name {
+            Some(name) => AssetSourceId::Name(name.into()),
+            None => AssetSourceId::Default,
+        };
+        app.register_asset_source(
+            id,
+            AssetSource::build().with_reader(move || Box::new(reader.clone())),
+        );
+    }
+
+    /// Each instance adds a source, so several can be added.
+    fn is_unique(&self) -> bool {
+        false
+    }
+}
+
+/// Asset reader for a RomFS image in memory.
+#[derive(Debug, Clone)]
+pub struct RomFsAssetReader(Arc<RomFs>);
+
+impl RomFsAssetReader {
+    /// Read an image file into memory.
+    pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let romfs = RomFs::new(std::fs::read(path)?)
+            .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))?;
+        Ok(Self(Arc::new(romfs)))
+    }
+
+    fn read_bytes(&self, path: &Path) -> Result<Vec<u8>, AssetReaderError> {
+        let not_found = || AssetReaderError::NotFound(path.to_path_buf());
+        let romfs_path = romfs_path(path).ok_or_else(not_found)?;
+        self.0
+            .file(&romfs_path)
+            .map(<[u8]>::to_vec)
+            .ok_or_else(not_found)
+    }
+
+    /// Names in a directory, or `None` if it is not a directory.
+    fn dir_names(&self, path: &Path) -> Result<Option<Vec<String>>, AssetReaderError> {
+        let Some(romfs_path) = romfs_path(path) else {
+            return Ok(None);
+        };
+        Ok(self
+            .0
+            .read_dir(&romfs_path)
+            .map(|entries| entries.into_iter().map(|entry| entry.name).collect()))
+    }
+}
+
+impl AssetReader for RomFsAssetReader {
+    async fn read<'a>(&'a self, path: &'a Path) -> Result<impl Reader + 'a, AssetReaderError> {
+        self.read_bytes(path).map(VecReader::new)
+    }
+
+    async fn read_meta<'a>(&'a self, path: &'a Path) -> Result<impl Reader + 'a, AssetReaderError> {
+        let mut meta = path.as_os_str().to_owned();
+        meta.push(".meta");
+        self.read_bytes(Path::new(&meta)).map(VecReader::new)
+    }
+
+    async fn read_directory<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> Result<Box<PathStream>, AssetReaderError> {
+        let names = self
+            .dir_names(path)?
+            .ok_or_else(|| AssetReaderError::NotFound(path.to_path_buf()))?;
+        let paths: Vec<_> = names.into_iter().map(|name| path.join(name)).collect();
+        Ok(Box::new(stream::iter(paths)))
+    }
+
+    async fn is_directory<'a>(&'a self, path: &'a Path) -> Result<bool, AssetReaderError> {
+        Ok(self.dir_names(path)?.is_some())
+    }
+}
+
+/// Convert an asset path to a `/`-separated RomFS path.
+fn romfs_path(path: &Path) -> Option<String> {
+    let mut names = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => names.push(name.to_str()?),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    Some(names.join("/"))
+}
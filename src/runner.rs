@@ -0,0 +1,250 @@
+//! App runner and entry point for the console.
+//!
+//! Bevy's default runners rely on `winit` or `std` timing, neither of which
+//! exists on Horizon. [`SwitchRunner`] drives [`App::update`] in a loop
+//! instead, feeding it [`AppletMessage`]s from a [`RunnerPlatform`]. The
+//! platform is a trait so the loop can be tested on desktop with a fake one;
+//! on the console it is the app's [`Hal`].
+
+use bevy::app::PluginsState;
+use bevy::prelude::*;
+
+use crate::platform::{AppletMessage, Hal, SwitchConfig};
+#[cfg(feature = "switch")]
+use crate::platform::{HorizonHal, SwitchHal};
+
+#[doc(hidden)]
+pub use bevy::app::AppExit;
+
+/// Source of applet messages and frame pacing for [`SwitchRunner`].
+pub trait RunnerPlatform {
+    /// Take the next pending applet message, if any.
+    fn poll_message(&mut self) -> Option<AppletMessage>;
+
+    /// Wait for the next applet message and take it.
+    ///
+    /// Called while the game is paused and no message is pending, so the
+    /// runner sleeps instead of spinning. Platforms that can block should do
+    /// so; the default polls once.
+    fn wait_message(&mut self) -> Option<AppletMessage> {
+        self.poll_message()
+    }
+
+    /// Called after each frame, e.g. to wait for vsync.
+    fn end_frame(&mut self) {}
+}
+
+/// Runner that updates the app until it exits.
+///
+/// Each frame the runner drains the platform's messages and writes them as
+/// [`AppletMessage`]s, then updates the app unless it is paused:
+///
+/// - [`FocusLost`](AppletMessage::FocusLost) and
+///   [`Suspended`](AppletMessage::Suspended) pause updates until
+///   [`FocusGained`](AppletMessage::FocusGained) and
+///   [`Resumed`](AppletMessage::Resumed). The frame a message arrives on is
+///   always updated so systems can react to it. While paused the runner
+///   waits for the next message with [`RunnerPlatform::wait_message`].
+/// - [`OperationModeChanged`](AppletMessage::OperationModeChanged) switches
+///   [`SwitchConfig`] to the new display mode.
+/// - [`ExitRequested`](AppletMessage::ExitRequested) writes
+///   [`AppExit::Success`] and ends the app after that update.
+pub struct SwitchRunner<P> {
+    platform: P,
+    focused: bool,
+    suspended: bool,
+}
+
+impl<P: RunnerPlatform> SwitchRunner<P> {
+    /// Create a runner for the given platform.
+    pub fn new(platform: P) -> Self {
+        Self {
+            platform,
+            focused: true,
+            suspended: false,
+        }
+    }
+
+    /// Get the platform.
+    pub fn platform(&self) -> &P {
+        &self.platform
+    }
+
+    /// Get the platform mutably.
+    pub fn platform_mut(&mut self) -> &mut P {
+        &mut self.platform
+    }
+
+    /// Check if updates are paused because the game lost focus or is
+    /// suspended.
+    pub fn is_paused(&self) -> bool {
+        !self.focused || self.suspended
+    }
+
+    /// Run one frame and return the exit status once the app should exit.
+    pub fn step(&mut self, app: &mut App) -> Option<AppExit> {
+        let mut received = false;
+        while let Some(message) = self.platform.poll_message() {
+            self.handle_message(app, message);
+            received = true;
+        }
+        if !received
+            && self.is_paused()
+            && let Some(message) = self.platform.wait_message()
+        {
+            self.handle_message(app, message);
+            received = true;
+        }
+
+        if received || !self.is_paused() {
+            app.update();
+        }
+
+        let exit = app.should_exit();
+        self.platform.end_frame();
+        exit
+    }
+
+    /// Run the app until it exits.
+    pub fn run(mut self, mut app: App) -> AppExit {
+        if app.plugins_state() != PluginsState::Cleaned {
+            app.finish();
+            app.cleanup();
+        }
+
+        loop {
+            if let Some(exit) = self.step(&mut app) {
+                return exit;
+            }
+        }
+    }
+
+    fn handle_message(&mut self, app: &mut App, message: AppletMessage) {
+        let world = app.world_mut();
+        match message {
+            AppletMessage::ExitRequested => {
+                world.write_message(AppExit::Success);
+            }
+            AppletMessage::FocusGained => self.focused = true,
+            AppletMessage::FocusLost => self.focused = false,
+            AppletMessage::Suspended => self.suspended = true,
+            AppletMessage::Resumed => self.suspended = false,
+            AppletMessage::OperationModeChanged(mode) => {
+                if let Some(mut config) = world.get_resource_mut::<SwitchConfig>()
+                    && config.display_mode != mode
+                {
+                    *config = config.clone().with_display_mode(mode);
+                }
+            }
+        }
+        world.write_message(message);
+    }
+}
+
+/// Console services the runner relies on, held for the lifetime of the app.
+///
+/// The homebrew runtime starts libnx's default services before `main`. This
+/// takes its own reference to the applet service and asks it to report focus
+/// and operation mode changes as [`AppletMessage`]s, so the runner pauses the
+/// game instead of the system suspending it. Created by
+/// [`switch_main!`](crate::switch_main) before the app is built, and
+/// released on drop.
+#[cfg(feature = "switch")]
+#[derive(Debug)]
+pub struct SwitchServices(());
+
+#[cfg(feature = "switch")]
+impl SwitchServices {
+    /// Initialize the services, returning the failing libnx result code.
+    pub fn init() -> Result<Self, u32> {
+        crate::platform::nx::init_applet().inspect_err(|result| {
+            HorizonHal::new().debug_output(&alloc::format!(
+                "Failed to initialize the applet service: {result:#x}"
+            ));
+        })?;
+        Ok(Self(()))
+    }
+}
+
+#[cfg(feature = "switch")]
+impl Drop for SwitchServices {
+    fn drop(&mut self) {
+        crate::platform::nx::exit_applet();
+    }
+}
+
+/// Run the app on the console and return the process exit code.
+///
+/// Messages come from the app's [`Hal`], which is created if the app has
+/// none yet. Used by [`switch_main!`](crate::switch_main).
+pub fn run_switch(mut app: App) -> i32 {
+    let hal = app.world_mut().get_resource_or_init::<Hal>().clone();
+    exit_code(SwitchRunner::new(hal).run(app))
+}
+
+/// Convert an exit status to a process exit code.
+pub fn exit_code(exit: AppExit) -> i32 {
+    match exit {
+        AppExit::Success => 0,
+        AppExit::Error(code) => i32::from(code.get()),
+    }
+}
+
+/// The HAL's applet message queue and frame pacing.
+impl RunnerPlatform for Hal {
+    fn poll_message(&mut self) -> Option<AppletMessage> {
+        self.poll_applet_message()
+    }
+
+    fn wait_message(&mut self) -> Option<AppletMessage> {
+        self.wait_applet_message()
+    }
+
+    fn end_frame(&mut self) {
+        (**self).end_frame();
+    }
+}
+
+/// Define the program entry point for desktop and the console.
+///
+/// Takes a function or closure that builds the [`App`]. On desktop this
+/// expands to a regular `main` calling [`App::run`]. On Horizon it exports
+/// the C `main` that the homebrew runtime calls, which initializes the
+/// `SwitchServices` and runs the app with [`run_switch`], so the crate root
+/// needs `#![no_main]` and the `switch` feature there:
+///
+/// ```rust,ignore
+/// #![cfg_attr(target_os = "horizon", no_std, no_main)]
+///
+/// use bevy::prelude::*;
+/// use switchbrew_bevy::prelude::*;
+///
+/// fn build_app() -> App {
+///     let mut app = App::new();
+///     app.add_plugins(SwitchPlugin::default());
+///     app
+/// }
+///
+/// switchbrew_bevy::switch_main!(build_app);
+/// ```
+#[macro_export]
+macro_rules! switch_main {
+    ($build_app:expr) => {
+        #[cfg(target_os = "horizon")]
+        #[unsafe(no_mangle)]
+        pub extern "C" fn main(
+            _argc: ::core::ffi::c_int,
+            _argv: *const *const ::core::ffi::c_char,
+        ) -> ::core::ffi::c_int {
+            let Ok(_services) = $crate::runner::SwitchServices::init() else {
+                return 1;
+            };
+            $crate::runner::run_switch(($build_app)())
+        }
+
+        #[cfg(not(target_os = "horizon"))]
+        fn main() -> $crate::runner::AppExit {
+            ($build_app)().run()
+        }
+    };
+}
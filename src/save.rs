@@ -0,0 +1,544 @@
+//! Typed per-user save data.
+//!
+//! Works like Switch save data: each user account has its own slots, and
+//! changes are staged until [`SwitchSave::commit`] writes them all at once.
+//! The [`JournaledSaveBackend`] used on desktop journals commits and keeps
+//! checksummed backups, so a crash or corrupted file never loses a save.
+//!
+//! ```rust
+//! use bevy::prelude::*;
+//! use serde::{Deserialize, Serialize};
+//! use switchbrew_bevy::save::{SaveData, SwitchSave, UserId};
+//!
+//! #[derive(Default, Serialize, Deserialize)]
+//! struct Progress {
+//!     level: u32,
+//! }
+//!
+//! impl SaveData for Progress {
+//!     const SLOT: &'static str = "progress";
+//! }
+//!
+//! fn finish_level(mut save: ResMut<SwitchSave>) {
+//!     let user = UserId::DEFAULT;
+//!     let mut progress: Progress = save.load_or_default(user).unwrap();
+//!     progress.level += 1;
+//!     save.store(user, &progress).unwrap();
+//!     save.commit().unwrap();
+//! }
+//! ```
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+
+use bevy::platform::sync::{Mutex, MutexGuard};
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "std")]
+use crate::platform::DesktopHal;
+use crate::platform::FsError;
+
+mod journal;
+
+pub use journal::{JournaledSaveBackend, DEFAULT_BACKUPS};
+
+/// Untyped save data, as passed to [`Migration`]s.
+pub use serde_json::Value as SaveValue;
+
+/// Plugin that provides the [`SwitchSave`] resource.
+///
+/// A [`SwitchSave`] inserted before the plugin is added takes precedence.
+pub struct SwitchSavePlugin {
+    backend: Arc<dyn SaveBackend>,
+}
+
+impl SwitchSavePlugin {
+    /// Store saves in a backend.
+    pub fn new(backend: impl SaveBackend) -> Self {
+        Self {
+            backend: Arc::new(backend),
+        }
+    }
+
+    /// Store saves under the user's data directory, e.g.
+    /// `~/.local/share/<app_name>/saves` on Linux.
+    ///
+    /// Falls back to memory (with a warning) if there is no data directory.
+    #[cfg(feature = "std")]
+    pub fn desktop(app_name: &str) -> Self {
+        match DesktopSaveBackend::for_app(app_name) {
+            Some(backend) => Self::new(backend),
+            None => {
+                warn!("No data directory found, saves will not be persisted");
+                Self::new(MemorySaveBackend::new())
+            }
+        }
+    }
+}
+
+impl Plugin for SwitchSavePlugin {
+    fn build(&self, app: &mut App) {
+        if !app.world().contains_resource::<SwitchSave>() {
+            app.insert_resource(SwitchSave {
+                backend: self.backend.clone(),
+                pending: BTreeMap::new(),
+            });
+        }
+    }
+}
+
+/// Type stored in a save slot.
+///
+/// Data is saved along with its schema version, the number of
+/// [`MIGRATIONS`](Self::MIGRATIONS) plus one. Older saves are migrated to the
+/// current version when loaded.
+pub trait SaveData: Serialize + DeserializeOwned {
+    /// Slot name: ASCII letters, digits, `-` and `_`.
+    const SLOT: &'static str;
+
+    /// Upgrades from older versions, in order: the first migrates version 1
+    /// to 2, the second 2 to 3, and so on.
+    const MIGRATIONS: &'static [Migration] = &[];
+
+    /// Current schema version.
+    fn version() -> u32 {
+        Self::MIGRATIONS.len() as u32 + 1
+    }
+}
+
+/// Upgrade untyped save data to the next schema version.
+pub type Migration = fn(&mut SaveValue) -> Result<(), String>;
+
+/// User account that owns save data.
+///
+/// Matches the console's 128-bit account IDs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct UserId(pub u128);
+
+impl UserId {
+    /// Account used when there is only one user, e.g. on desktop.
+    pub const DEFAULT: Self = Self(1);
+}
+
+impl fmt::Display for UserId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:032x}", self.0)
+    }
+}
+
+/// Resource for reading and writing save data.
+///
+/// Stores and deletes are staged: later loads see them, but nothing reaches
+/// the backend until [`commit`](Self::commit).
+#[derive(Resource)]
+pub struct SwitchSave {
+    backend: Arc<dyn SaveBackend>,
+    pending: BTreeMap<SlotKey, Option<Vec<u8>>>,
+}
+
+impl SwitchSave {
+    /// Create save storage in a backend.
+    pub fn new(backend: impl SaveBackend) -> Self {
+        Self {
+            backend: Arc::new(backend),
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// Get the backend.
+    pub fn backend(&self) -> &dyn SaveBackend {
+        &*self.backend
+    }
+
+    /// Load a user's slot, migrating older versions, or `Ok(None)` if it is
+    /// empty.
+    pub fn load<T: SaveData>(&self, user: UserId) -> Result<Option<T>, SaveError> {
+        let key = SlotKey::new::<T>(user)?;
+        let bytes = match self.pending.get(&key) {
+            Some(staged) => staged.clone(),
+            None => self.backend.read(user, T::SLOT)?,
+        };
+        bytes.map(|bytes| decode::<T>(&bytes)).transpose()
+    }
+
+    /// Load a user's slot, or the default value if it is empty.
+    pub fn load_or_default<T: SaveData + Default>(&self, user: UserId) -> Result<T, SaveError> {
+        Ok(self.load(user)?.unwrap_or_default())
+    }
+
+    /// Stage a value for a user's slot.
+    pub fn store<T: SaveData>(&mut self, user: UserId, value: &T) -> Result<(), SaveError> {
+        let key = SlotKey::new::<T>(user)?;
+        let bytes = encode(value)?;
+        self.pending.insert(key, Some(bytes));
+        Ok(())
+    }
+
+    /// Stage deleting a user's slot.
+    pub fn delete<T: SaveData>(&mut self, user: UserId) -> Result<(), SaveError> {
+        let key = SlotKey::new::<T>(user)?;
+        self.pending.insert(key, None);
+        Ok(())
+    }
+
+    /// List the slots saved for a user, including staged changes.
+    pub fn slots(&self, user: UserId) -> Result<Vec<String>, SaveError> {
+        let mut slots = self.backend.slots(user)?;
+        for (key, data) in self.pending.iter().filter(|(key, _)| key.user == user) {
+            slots.retain(|slot| *slot != key.slot);
+            if data.is_some() {
+                slots.push(key.slot.into());
+            }
+        }
+        slots.sort();
+        Ok(slots)
+    }
+
+    /// Check if there are staged changes.
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Write all staged changes to the backend at once.
+    ///
+    /// On error the changes stay staged, so the commit can be retried.
+    pub fn commit(&mut self) -> Result<(), SaveError> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let changes: Vec<_> = self
+            .pending
+            .iter()
+            .map(|(key, data)| SaveChange {
+                user: key.user,
+                slot: key.slot,
+                data: data.as_deref(),
+            })
+            .collect();
+        self.backend.commit(&changes)?;
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// Drop all staged changes.
+    pub fn discard(&mut self) {
+        self.pending.clear();
+    }
+}
+
+/// User and slot of a staged change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct SlotKey {
+    user: UserId,
+    slot: &'static str,
+}
+
+impl SlotKey {
+    fn new<T: SaveData>(user: UserId) -> Result<Self, SaveError> {
+        let valid = !T::SLOT.is_empty()
+            && T::SLOT
+                .bytes()
+                .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_');
+        if !valid {
+            return Err(SaveError::InvalidSlot(T::SLOT));
+        }
+        Ok(Self {
+            user,
+            slot: T::SLOT,
+        })
+    }
+}
+
+/// Saved data with its schema version.
+#[derive(Serialize, Deserialize)]
+struct SaveFile<T> {
+    version: u32,
+    data: T,
+}
+
+fn encode<T: SaveData>(value: &T) -> Result<Vec<u8>, SaveError> {
+    let file = SaveFile {
+        version: T::version(),
+        data: value,
+    };
+    serde_json::to_vec(&file).map_err(|error| SaveError::Encode {
+        slot: T::SLOT,
+        message: error.to_string(),
+    })
+}
+
+fn decode<T: SaveData>(bytes: &[u8]) -> Result<T, SaveError> {
+    let corrupt = |error: serde_json::Error| SaveError::Corrupt {
+        slot: T::SLOT,
+        message: error.to_string(),
+    };
+    let SaveFile { version, mut data } =
+        serde_json::from_slice::<SaveFile<SaveValue>>(bytes).map_err(corrupt)?;
+
+    let current = T::version();
+    if version == 0 || version > current {
+        return Err(SaveError::UnsupportedVersion {
+            slot: T::SLOT,
+            version,
+            current,
+        });
+    }
+    for (from, migration) in (version..).zip(&T::MIGRATIONS[version as usize - 1..]) {
+        migration(&mut data).map_err(|message| SaveError::Migration {
+            slot: T::SLOT,
+            from,
+            message,
+        })?;
+    }
+
+    serde_json::from_value(data).map_err(corrupt)
+}
+
+/// A change to write in [`SaveBackend::commit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SaveChange<'a> {
+    /// Owner of the slot.
+    pub user: UserId,
+    /// Slot name.
+    pub slot: &'a str,
+    /// New contents, or `None` to delete the slot.
+    pub data: Option<&'a [u8]>,
+}
+
+/// Storage for save slots.
+pub trait SaveBackend: Send + Sync + 'static {
+    /// Read a slot, or `Ok(None)` if it is empty.
+    fn read(&self, user: UserId, slot: &str) -> Result<Option<Vec<u8>>, FsError>;
+
+    /// List a user's slots.
+    fn slots(&self, user: UserId) -> Result<Vec<String>, FsError>;
+
+    /// Apply changes so that each slot holds either its old or its new
+    /// contents, never a mix.
+    fn commit(&self, changes: &[SaveChange<'_>]) -> Result<(), FsError>;
+}
+
+/// Slots of a [`MemorySaveBackend`], keyed by owner and slot name.
+type MemorySlots = BTreeMap<(UserId, String), Vec<u8>>;
+
+/// In-memory save storage for tests.
+///
+/// Clones share their slots, so keep one to inspect what was committed.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySaveBackend {
+    slots: Arc<Mutex<MemorySlots>>,
+}
+
+impl MemorySaveBackend {
+    /// Create empty storage.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MemorySlots> {
+        self.slots
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl SaveBackend for MemorySaveBackend {
+    fn read(&self, user: UserId, slot: &str) -> Result<Option<Vec<u8>>, FsError> {
+        Ok(self.lock().get(&(user, slot.into())).cloned())
+    }
+
+    fn slots(&self, user: UserId) -> Result<Vec<String>, FsError> {
+        Ok(self
+            .lock()
+            .keys()
+            .filter(|(owner, _)| *owner == user)
+            .map(|(_, slot)| slot.clone())
+            .collect())
+    }
+
+    /// Apply all changes under one lock, so they land together.
+    fn commit(&self, changes: &[SaveChange<'_>]) -> Result<(), FsError> {
+        let mut slots = self.lock();
+        for change in changes {
+            let key = (change.user, change.slot.into());
+            match change.data {
+                Some(data) => slots.insert(key, data.to_vec()),
+                None => slots.remove(&key),
+            };
+        }
+        Ok(())
+    }
+}
+
+/// Journaled save storage in a desktop directory.
+///
+/// A [`JournaledSaveBackend`] on the [`DesktopHal`]: each slot is a file at
+/// `<root>/<user>/<slot>.sav`, with checksummed backups next to it.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct DesktopSaveBackend {
+    root: std::path::PathBuf,
+    journal: JournaledSaveBackend<DesktopHal>,
+}
+
+#[cfg(feature = "std")]
+impl DesktopSaveBackend {
+    /// Store saves under a directory.
+    pub fn new(root: impl Into<std::path::PathBuf>) -> Self {
+        let root = root.into();
+        let journal = JournaledSaveBackend::new(DesktopHal::new(), root.to_string_lossy());
+        Self { root, journal }
+    }
+
+    /// Store saves in `<data dir>/<app_name>/saves`, or `None` if there is no
+    /// data directory.
+    ///
+    /// The data directory is `$XDG_DATA_HOME` (or `~/.local/share`),
+    /// `~/Library/Application Support` on macOS and `%APPDATA%` on Windows.
+    pub fn for_app(app_name: &str) -> Option<Self> {
+        Some(Self::new(data_dir()?.join(app_name).join("saves")))
+    }
+
+    /// Set the number of backups kept per slot.
+    pub fn with_backups(mut self, backups: usize) -> Self {
+        self.journal = self.journal.with_backups(backups);
+        self
+    }
+
+    /// Get the root directory.
+    pub fn root(&self) -> &std::path::Path {
+        &self.root
+    }
+
+    /// Get the underlying journaled storage.
+    pub fn journal(&self) -> &JournaledSaveBackend<DesktopHal> {
+        &self.journal
+    }
+}
+
+#[cfg(feature = "std")]
+impl SaveBackend for DesktopSaveBackend {
+    fn read(&self, user: UserId, slot: &str) -> Result<Option<Vec<u8>>, FsError> {
+        self.journal.read(user, slot)
+    }
+
+    fn slots(&self, user: UserId) -> Result<Vec<String>, FsError> {
+        self.journal.slots(user)
+    }
+
+    fn commit(&self, changes: &[SaveChange<'_>]) -> Result<(), FsError> {
+        self.journal.commit(changes)
+    }
+}
+
+/// Get the user's data directory.
+#[cfg(feature = "std")]
+fn data_dir() -> Option<std::path::PathBuf> {
+    use std::path::PathBuf;
+
+    let var = |name| std::env::var_os(name).filter(|value| !value.is_empty());
+    if cfg!(target_os = "windows") {
+        return var("APPDATA").map(PathBuf::from);
+    }
+    let home = var("HOME").map(PathBuf::from);
+    if cfg!(target_os = "macos") {
+        return home.map(|home| home.join("Library/Application Support"));
+    }
+    var("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| home.map(|home| home.join(".local/share")))
+}
+
+/// Error returned by [`SwitchSave`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SaveError {
+    /// The backend failed to read or write
+    Backend(FsError),
+    /// The slot name contains characters other than ASCII letters, digits, `-` and `_`
+    InvalidSlot(&'static str),
+    /// The value could not be serialized
+    Encode {
+        /// Slot name.
+        slot: &'static str,
+        /// Serializer message.
+        message: String,
+    },
+    /// The saved data is not valid for the slot's type
+    Corrupt {
+        /// Slot name.
+        slot: &'static str,
+        /// Deserializer message.
+        message: String,
+    },
+    /// The data was saved with an unknown, e.g. newer, schema version
+    UnsupportedVersion {
+        /// Slot name.
+        slot: &'static str,
+        /// Version of the saved data.
+        version: u32,
+        /// Current schema version.
+        current: u32,
+    },
+    /// A migration failed
+    Migration {
+        /// Slot name.
+        slot: &'static str,
+        /// Version the migration started from.
+        from: u32,
+        /// Migration message.
+        message: String,
+    },
+}
+
+impl From<FsError> for SaveError {
+    fn from(error: FsError) -> Self {
+        SaveError::Backend(error)
+    }
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Backend(error) => write!(f, "save storage error: {error}"),
+            SaveError::InvalidSlot(slot) => write!(f, "invalid save slot name {slot:?}"),
+            SaveError::Encode { slot, message } => {
+                write!(f, "failed to encode save slot `{slot}`: {message}")
+            }
+            SaveError::Corrupt { slot, message } => {
+                write!(f, "save slot `{slot}` is corrupt: {message}")
+            }
+            SaveError::UnsupportedVersion {
+                slot,
+                version,
+                current,
+            } => write!(
+                f,
+                "save slot `{slot}` has version {version}, expected at most {current}"
+            ),
+            SaveError::Migration {
+                slot,
+                from,
+                message,
+            } => write!(
+                f,
+                "failed to migrate save slot `{slot}` from version {from}: {message}"
+            ),
+        }
+    }
+}
+
+impl core::error::Error for SaveError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            SaveError::Backend(error) => Some(error),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,353 @@
+//! Journaled save storage on a [`SwitchHal`] filesystem.
+//!
+//! Every file is framed with a length and CRC-32, so torn or corrupted
+//! writes are detected on load. A commit:
+//!
+//! 1. writes each new slot to `<slot>.sav.tmp` and reads it back,
+//! 2. writes a `journal` listing the changes and reads it back,
+//! 3. rotates `<slot>.sav` into the backups `<slot>.sav.1` .. `<slot>.sav.N`
+//!    and renames the temporary files into place,
+//! 4. removes the journal.
+//!
+//! Each step is made durable with [`SwitchHal::sync`] before the next one
+//! starts. A file that reads back corrupt fails the commit, which is rolled
+//! back. If the app dies or power is lost before the journal is complete, the
+//! next access rolls back by deleting the temporary files; once it is
+//! complete, the next access finishes the commit. Slots that fail their
+//! checksum are read from the newest valid backup instead.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use log::Level;
+
+use super::{SaveBackend, SaveChange, UserId};
+use crate::platform::{FsError, SwitchHal};
+
+/// Marks the start of a framed file.
+const MAGIC: &[u8; 4] = b"SWSV";
+
+/// Size of the magic, length and checksum in front of the payload.
+const HEADER_LEN: usize = 12;
+
+/// Default number of backups kept per slot.
+pub const DEFAULT_BACKUPS: usize = 2;
+
+/// Journaled save storage in a directory of a [`SwitchHal`] filesystem.
+///
+/// Slots are files at `<root>/<user>/<slot>.sav`. See the
+/// [module docs](self) for how commits survive crashes.
+#[derive(Debug, Clone)]
+pub struct JournaledSaveBackend<H> {
+    fs: H,
+    root: String,
+    backups: usize,
+}
+
+impl<H: SwitchHal> JournaledSaveBackend<H> {
+    /// Store saves under a directory, keeping [`DEFAULT_BACKUPS`] backups.
+    pub fn new(fs: H, root: impl Into<String>) -> Self {
+        Self {
+            fs,
+            root: root.into().trim_end_matches('/').into(),
+            backups: DEFAULT_BACKUPS,
+        }
+    }
+
+    /// Set the number of backups kept per slot.
+    pub fn with_backups(mut self, backups: usize) -> Self {
+        self.backups = backups;
+        self
+    }
+
+    /// Get the filesystem.
+    pub fn fs(&self) -> &H {
+        &self.fs
+    }
+
+    /// Get the root directory.
+    pub fn root(&self) -> &str {
+        &self.root
+    }
+
+    /// Read a slot's backups, newest first, skipping missing and corrupt ones.
+    pub fn backups(&self, user: UserId, slot: &str) -> Result<Vec<Vec<u8>>, FsError> {
+        self.recover()?;
+        let path = self.slot_path(user, slot);
+        let mut backups = Vec::new();
+        for index in 1..=self.backups {
+            if let Some(data) = self.read_framed(&backup_path(&path, index))? {
+                backups.push(data);
+            }
+        }
+        Ok(backups)
+    }
+
+    /// Finish or roll back a commit interrupted by a crash.
+    ///
+    /// Runs automatically before every read and commit.
+    pub fn recover(&self) -> Result<(), FsError> {
+        let journal = self.journal_path();
+        match self.read_framed(&journal)? {
+            Some(entries) => {
+                let entries = String::from_utf8(entries)
+                    .map_err(|_| FsError::Other("save journal is not valid UTF-8".into()))?;
+                for entry in entries.lines() {
+                    self.apply(entry)?;
+                }
+                // The slots must be in place before the journal goes away
+                self.fs.sync(&self.root)?;
+                remove_if_exists(&self.fs, &journal)?;
+                self.fs.sync(&self.root)
+            }
+            None => {
+                self.remove_temp_files()?;
+                remove_if_exists(&self.fs, &journal)
+            }
+        }
+    }
+
+    fn journal_path(&self) -> String {
+        self.join("journal")
+    }
+
+    fn user_dir(&self, user: UserId) -> String {
+        self.join(&format!("{user}"))
+    }
+
+    fn slot_path(&self, user: UserId, slot: &str) -> String {
+        format!("{}/{slot}.sav", self.user_dir(user))
+    }
+
+    fn join(&self, name: &str) -> String {
+        if self.root.is_empty() {
+            name.into()
+        } else {
+            format!("{}/{name}", self.root)
+        }
+    }
+
+    /// Read a framed file, telling missing and corrupt files apart.
+    fn load(&self, path: &str) -> Result<Framed, FsError> {
+        match self.fs.read_file(path) {
+            Ok(bytes) => Ok(match unframe(&bytes) {
+                Some(data) => Framed::Valid(data.to_vec()),
+                None => Framed::Corrupt,
+            }),
+            Err(FsError::NotFound) => Ok(Framed::Missing),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Read a framed file, or `Ok(None)` if it is missing or corrupt.
+    fn read_framed(&self, path: &str) -> Result<Option<Vec<u8>>, FsError> {
+        match self.load(path)? {
+            Framed::Valid(data) => Ok(Some(data)),
+            Framed::Missing | Framed::Corrupt => Ok(None),
+        }
+    }
+
+    /// Apply one journal entry. Safe to repeat after a crash.
+    fn apply(&self, entry: &str) -> Result<(), FsError> {
+        let (op, path) = entry
+            .split_once(' ')
+            .ok_or_else(|| FsError::Other(format!("invalid save journal entry {entry:?}")))?;
+        let path = self.join(path);
+        match op {
+            "W" => {
+                let temp = format!("{path}.tmp");
+                // A missing temporary file was already renamed into place
+                if self.read_framed(&temp)?.is_none() {
+                    return remove_if_exists(&self.fs, &temp);
+                }
+                self.rotate(&path)?;
+                self.fs.rename(&temp, &path)
+            }
+            "D" => {
+                remove_if_exists(&self.fs, &path)?;
+                for index in 1..=self.backups {
+                    remove_if_exists(&self.fs, &backup_path(&path, index))?;
+                }
+                Ok(())
+            }
+            _ => Err(FsError::Other(format!(
+                "invalid save journal entry {entry:?}"
+            ))),
+        }
+    }
+
+    /// Shift the backups of a slot and move the slot into the first one.
+    ///
+    /// A corrupt slot is dropped instead, so it never pushes out a good backup.
+    fn rotate(&self, path: &str) -> Result<(), FsError> {
+        match self.load(path)? {
+            Framed::Missing => return Ok(()),
+            Framed::Valid(_) if self.backups > 0 => {}
+            Framed::Valid(_) | Framed::Corrupt => return remove_if_exists(&self.fs, path),
+        }
+
+        // One listing finds the backups without reading each of them
+        let (dir, name) = path.rsplit_once('/').unwrap_or((".", path));
+        let files = self.fs.list_dir(dir)?;
+        for index in (1..self.backups).rev() {
+            if files.contains(&backup_path(name, index)) {
+                self.fs
+                    .rename(&backup_path(path, index), &backup_path(path, index + 1))?;
+            }
+        }
+        self.fs.rename(path, &backup_path(path, 1))
+    }
+
+    /// Check that a file just written reads back intact, rolling the commit
+    /// back if it does not.
+    fn verify(&self, path: &str) -> Result<(), FsError> {
+        if self.read_framed(path)?.is_some() {
+            return Ok(());
+        }
+        remove_if_exists(&self.fs, &self.journal_path())?;
+        self.remove_temp_files()?;
+        Err(FsError::Other(format!(
+            "save file {path} is corrupt after writing"
+        )))
+    }
+
+    /// Delete temporary files left by a commit that never wrote its journal.
+    fn remove_temp_files(&self) -> Result<(), FsError> {
+        let users = match self.fs.list_dir(&self.root) {
+            Ok(users) => users,
+            Err(FsError::NotFound) => return Ok(()),
+            Err(error) => return Err(error),
+        };
+        for user in users {
+            let dir = self.join(&user);
+            let Ok(files) = self.fs.list_dir(&dir) else {
+                continue;
+            };
+            for file in files.iter().filter(|file| file.ends_with(".sav.tmp")) {
+                remove_if_exists(&self.fs, &format!("{dir}/{file}"))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<H: SwitchHal> SaveBackend for JournaledSaveBackend<H> {
+    /// Read a slot, falling back to the newest valid backup if it is corrupt.
+    fn read(&self, user: UserId, slot: &str) -> Result<Option<Vec<u8>>, FsError> {
+        self.recover()?;
+        let path = self.slot_path(user, slot);
+        let corrupt = match self.load(&path)? {
+            Framed::Valid(data) => return Ok(Some(data)),
+            Framed::Corrupt => true,
+            Framed::Missing => false,
+        };
+        for index in 1..=self.backups {
+            if let Some(data) = self.read_framed(&backup_path(&path, index))? {
+                self.fs.log(
+                    Level::Warn,
+                    &format!(
+                        "Save slot {path} is {}, rolled back to backup {index}",
+                        if corrupt { "corrupt" } else { "missing" }
+                    ),
+                );
+                return Ok(Some(data));
+            }
+        }
+        if corrupt {
+            self.fs.log(
+                Level::Error,
+                &format!("Save slot {path} is corrupt and has no valid backup"),
+            );
+        }
+        Ok(None)
+    }
+
+    fn slots(&self, user: UserId) -> Result<Vec<String>, FsError> {
+        self.recover()?;
+        match self.fs.list_dir(&self.user_dir(user)) {
+            Ok(files) => Ok(files
+                .iter()
+                .filter_map(|file| file.strip_suffix(".sav"))
+                .map(String::from)
+                .collect()),
+            Err(FsError::NotFound) => Ok(Vec::new()),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn commit(&self, changes: &[SaveChange<'_>]) -> Result<(), FsError> {
+        self.recover()?;
+
+        let mut journal = String::new();
+        let mut temps = Vec::new();
+        for change in changes {
+            let path = format!("{}/{}.sav", change.user, change.slot);
+            match change.data {
+                Some(data) => {
+                    let temp = format!("{}.tmp", self.join(&path));
+                    self.fs.write_file(&temp, &frame(data))?;
+                    temps.push(temp);
+                    journal.push_str("W ");
+                }
+                None => journal.push_str("D "),
+            }
+            journal.push_str(&path);
+            journal.push('\n');
+        }
+
+        // Without a journal, recovery would quietly drop a corrupt slot
+        for temp in &temps {
+            self.verify(temp)?;
+        }
+        self.fs.sync(&self.root)?;
+
+        let journal_path = self.journal_path();
+        self.fs
+            .write_file(&journal_path, &frame(journal.as_bytes()))?;
+        self.verify(&journal_path)?;
+        self.fs.sync(&self.root)?;
+        self.recover()
+    }
+}
+
+/// Contents of a framed file.
+enum Framed {
+    /// The file does not exist
+    Missing,
+    /// The file is torn or fails its checksum
+    Corrupt,
+    /// The payload of an intact file
+    Valid(Vec<u8>),
+}
+
+/// Path of a slot's backup, 1 being the newest.
+fn backup_path(path: &str, index: usize) -> String {
+    format!("{path}.{index}")
+}
+
+fn remove_if_exists(fs: &impl SwitchHal, path: &str) -> Result<(), FsError> {
+    match fs.remove_file(path) {
+        Ok(()) | Err(FsError::NotFound) => Ok(()),
+        Err(error) => Err(error),
+    }
+}
+
+/// Prefix data with the magic, its length and its CRC-32.
+fn frame(data: &[u8]) -> Vec<u8> {
+    let mut framed = Vec::with_capacity(HEADER_LEN + data.len());
+    framed.extend_from_slice(MAGIC);
+    framed.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    framed.extend_from_slice(&crc32fast::hash(data).to_le_bytes());
+    framed.extend_from_slice(data);
+    framed
+}
+
+/// Get the payload of framed data, or `None` if it is torn or corrupt.
+fn unframe(bytes: &[u8]) -> Option<&[u8]> {
+    let (header, data) = bytes.split_at_checked(HEADER_LEN)?;
+    let len = u32::from_le_bytes(header[4..8].try_into().ok()?);
+    let crc = u32::from_le_bytes(header[8..12].try_into().ok()?);
+    (header[..4] == *MAGIC && data.len() == len as usize && crc32fast::hash(data) == crc)
+        .then_some(data)
+}
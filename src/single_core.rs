@@ -0,0 +1,27 @@
+//! Critical-section implementation for single-core contexts.
+//!
+//! Bevy's `no_std` build uses [`critical_section`] to emulate atomics and
+//! locks. Enable `critical-section-single-core` to link this implementation
+//! when nothing else provides one, e.g. a game running its whole
+//! [`App`](bevy::app::App) on one core of the console.
+//!
+//! Acquiring the section does nothing, so it is only sound while a single
+//! thread of execution touches Bevy's state and no interrupt or signal
+//! handler does. Use `critical-section-spin` or the platform's implementation
+//! instead once the app runs on several threads.
+
+use critical_section::RawRestoreState;
+
+struct SingleCoreCriticalSection;
+
+critical_section::set_impl!(SingleCoreCriticalSection);
+
+// SAFETY: with a single thread of execution nothing can run concurrently
+// with the code inside the critical section, so there is nothing to exclude.
+unsafe impl critical_section::Impl for SingleCoreCriticalSection {
+    unsafe fn acquire() -> RawRestoreState {
+        Default::default()
+    }
+
+    unsafe fn release(_restore_state: RawRestoreState) {}
+}
@@ -0,0 +1,158 @@
+//! Critical-section implementation backed by a spinlock.
+//!
+//! Bevy's `no_std` build uses [`critical_section`] to emulate atomics and
+//! locks. Enable `critical-section-spin` (enabled by `switch`) to link this
+//! implementation when nothing else provides one.
+//!
+//! A single global lock admits one thread at a time; the others spin, and
+//! yield to the scheduler where there is one, until it is released. The
+//! thread holding the lock may enter the section again. On bare-metal
+//! AArch64 the section also masks IRQs and FIQs, so an interrupt handler
+//! cannot preempt the holder and then spin on the lock forever. Horizon runs
+//! games in user mode, where interrupts stay with the kernel and only threads
+//! need excluding.
+//!
+//! Takes precedence over `critical-section-single-core` when both are enabled.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use critical_section::RawRestoreState;
+
+#[cfg(all(target_os = "none", not(target_arch = "aarch64")))]
+compile_error!(
+    "critical-section-spin supports Horizon, bare-metal AArch64 and hosted targets; \
+     use critical-section-single-core on other bare-metal targets"
+);
+
+/// Thread holding the lock, or 0 when it is free.
+static OWNER: AtomicUsize = AtomicUsize::new(0);
+/// How many times the owner has entered the section.
+static DEPTH: AtomicUsize = AtomicUsize::new(0);
+/// Interrupt mask to restore when the owner leaves the outermost section.
+static SAVED_INTERRUPTS: AtomicUsize = AtomicUsize::new(0);
+
+struct SpinCriticalSection;
+
+critical_section::set_impl!(SpinCriticalSection);
+
+// SAFETY: the lock admits one thread at a time and is only released by the
+// thread that took it, so the code inside the section never runs concurrently.
+// DEPTH and SAVED_INTERRUPTS are only touched by the owner.
+unsafe impl critical_section::Impl for SpinCriticalSection {
+    unsafe fn acquire() -> RawRestoreState {
+        // Mask first, so nothing can interrupt this thread while it holds the lock
+        let interrupts = interrupts::disable();
+        let thread = thread_id();
+
+        // Only this thread stores its own id, so a relaxed load suffices
+        if OWNER.load(Ordering::Relaxed) == thread {
+            DEPTH.fetch_add(1, Ordering::Relaxed);
+            return Default::default();
+        }
+
+        while OWNER
+            .compare_exchange_weak(0, thread, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            relax();
+        }
+        DEPTH.store(1, Ordering::Relaxed);
+        SAVED_INTERRUPTS.store(interrupts, Ordering::Relaxed);
+        Default::default()
+    }
+
+    unsafe fn release(_restore_state: RawRestoreState) {
+        if DEPTH.fetch_sub(1, Ordering::Relaxed) == 1 {
+            let interrupts = SAVED_INTERRUPTS.load(Ordering::Relaxed);
+            OWNER.store(0, Ordering::Release);
+            interrupts::restore(interrupts);
+        }
+    }
+}
+
+/// Nonzero id of the calling thread: its thread-local region on Horizon.
+#[cfg(target_os = "horizon")]
+fn thread_id() -> usize {
+    let tls: usize;
+    // SAFETY: TPIDRRO_EL0 is readable from user mode and holds the address of
+    // the thread's local region, which is unique and nonzero per thread
+    unsafe {
+        core::arch::asm!("mrs {}, tpidrro_el0", out(reg) tls, options(nomem, nostack));
+    }
+    tls
+}
+
+/// Nonzero id of the calling core. With interrupts masked, a core runs one
+/// thread of execution at a time.
+#[cfg(all(target_os = "none", target_arch = "aarch64"))]
+fn thread_id() -> usize {
+    let mpidr: usize;
+    // SAFETY: reading MPIDR_EL1 has no side effects
+    unsafe {
+        core::arch::asm!("mrs {}, mpidr_el1", out(reg) mpidr, options(nomem, nostack));
+    }
+    // Affinity levels 0-3, offset so core 0 is not mistaken for a free lock
+    (mpidr & 0xff_00ff_ffff) + 1
+}
+
+/// Nonzero id of the calling thread: the address of a thread-local.
+#[cfg(not(any(target_os = "horizon", target_os = "none")))]
+fn thread_id() -> usize {
+    extern crate std;
+
+    std::thread_local!(static ID: u8 = const { 0 });
+    ID.with(|id| core::ptr::from_ref(id) as usize)
+}
+
+/// Let the lock holder make progress.
+fn relax() {
+    core::hint::spin_loop();
+
+    #[cfg(all(target_os = "horizon", target_arch = "aarch64"))]
+    // SAFETY: svcSleepThread (SVC 0x0B) with -2 yields to any other thread,
+    // including lower-priority ones that may hold the lock
+    unsafe {
+        core::arch::asm!(
+            "svc 0x0b",
+            inout("x0") -2i64 => _,
+            options(nostack),
+        );
+    }
+    #[cfg(not(any(target_os = "horizon", target_os = "none")))]
+    {
+        extern crate std;
+        std::thread::yield_now();
+    }
+}
+
+#[cfg(all(target_os = "none", target_arch = "aarch64"))]
+mod interrupts {
+    /// Mask IRQs and FIQs, returning the previous DAIF value.
+    pub fn disable() -> usize {
+        let daif: usize;
+        // SAFETY: only changes the calling core's interrupt mask. No `nomem`,
+        // so memory accesses are not moved out of the section.
+        unsafe {
+            core::arch::asm!("mrs {}, daif", "msr daifset, #3", out(reg) daif, options(nostack));
+        }
+        daif
+    }
+
+    /// Restore a DAIF value returned by [`disable`].
+    pub fn restore(daif: usize) {
+        // SAFETY: see `disable`
+        unsafe {
+            core::arch::asm!("msr daif, {}", in(reg) daif, options(nostack));
+        }
+    }
+}
+
+/// Interrupts are the kernel's business outside bare metal.
+#[cfg(not(all(target_os = "none", target_arch = "aarch64")))]
+mod interrupts {
+    pub fn disable() -> usize {
+        0
+    }
+
+    pub fn restore(_daif: usize) {}
+}
@@ -1,21 +1,45 @@
 //! Window and display management for Nintendo Switch.
 
+#[cfg(feature = "std")]
+use alloc::string::String;
+
+use bevy::math::ops;
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "std")]
+use {bevy::camera::Viewport, bevy::window::WindowResolution};
 
 use crate::platform::{DisplayMode, SwitchConfig};
 
 /// Plugin for Switch window/display management.
+///
+/// Without `std` only [`SwitchDisplay`] is kept up to date; windows, cameras
+/// and UI need Bevy's rendering crates.
 pub struct SwitchWindowPlugin;
 
 impl Plugin for SwitchWindowPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<SwitchDisplay>()
+            .init_resource::<SwitchUiScale>()
             .add_systems(Update, handle_display_mode_change);
+
+        #[cfg(feature = "std")]
+        app.add_systems(
+            Update,
+            (
+                update_ui_scale,
+                sync_window_resolution,
+                update_safe_area_nodes,
+            )
+                .chain()
+                .after(handle_display_mode_change),
+        )
+        .add_systems(PostUpdate, update_bezel_viewports);
     }
 }
 
 /// Display configuration resource.
-#[derive(Debug, Clone, Resource)]
+#[derive(Debug, Clone, PartialEq, Resource)]
 pub struct SwitchDisplay {
     /// Current display mode.
     pub mode: DisplayMode,
@@ -23,6 +47,12 @@ pub struct SwitchDisplay {
     pub resolution: (u32, u32),
     /// Whether vsync is enabled.
     pub vsync: bool,
+    /// Safe area used for UI when docked (TVs may overscan).
+    pub safe_area: SafeArea,
+    /// Bezel margin around the screen on each side, in logical pixels.
+    ///
+    /// Non-zero only when simulating the handheld device on desktop.
+    pub bezel: (u32, u32),
 }
 
 impl Default for SwitchDisplay {
@@ -31,6 +61,8 @@ impl Default for SwitchDisplay {
             mode: DisplayMode::Docked,
             resolution: (1920, 1080),
             vsync: true,
+            safe_area: SafeArea::Title,
+            bezel: (0, 0),
         }
     }
 }
@@ -42,6 +74,7 @@ impl SwitchDisplay {
             mode: DisplayMode::Handheld,
             resolution: (1280, 720),
             vsync: true,
+            ..Default::default()
         }
     }
 
@@ -54,45 +87,444 @@ impl SwitchDisplay {
     pub fn aspect_ratio(&self) -> f32 {
         self.resolution.0 as f32 / self.resolution.1 as f32
     }
+
+    /// Get the logical window size needed to show the screen and its bezel.
+    pub fn window_size(&self) -> (u32, u32) {
+        (
+            self.resolution.0 + self.bezel.0 * 2,
+            self.resolution.1 + self.bezel.1 * 2,
+        )
+    }
+
+    /// Get the part of a render target covered by the screen, excluding the bezel.
+    ///
+    /// `target_size` is in physical pixels, as reported by the camera.
+    pub fn screen_rect(&self, target_size: UVec2) -> URect {
+        let window_size = UVec2::new(self.window_size().0, self.window_size().1).max(UVec2::ONE);
+        let scale = target_size.as_vec2() / window_size.as_vec2();
+        let margin = (UVec2::new(self.bezel.0, self.bezel.1).as_vec2() * scale)
+            .round()
+            .as_uvec2()
+            .min(target_size / 2);
+        URect::from_corners(margin, target_size - margin)
+    }
+
+    /// Set the safe area used for UI in docked mode.
+    pub fn with_safe_area(mut self, safe_area: SafeArea) -> Self {
+        self.safe_area = safe_area;
+        self
+    }
+
+    /// Get the safe area that applies to the current display mode.
+    ///
+    /// The built-in screen never overscans, so handheld and tabletop modes
+    /// always use the full display.
+    pub fn active_safe_area(&self) -> SafeArea {
+        match self.mode {
+            DisplayMode::Docked => self.safe_area,
+            DisplayMode::Handheld | DisplayMode::Tabletop => SafeArea::Full,
+        }
+    }
+
+    /// Get a safe area rectangle in pixels (origin at the top-left corner).
+    pub fn safe_rect(&self, area: SafeArea) -> Rect {
+        let normalized = area.normalized_rect();
+        let size = Vec2::new(self.resolution.0 as f32, self.resolution.1 as f32);
+        Rect::from_corners(normalized.min * size, normalized.max * size)
+    }
+
+    /// Get a safe area rectangle in normalized coordinates (0.0 to 1.0).
+    pub fn safe_rect_normalized(&self, area: SafeArea) -> Rect {
+        area.normalized_rect()
+    }
+
+    /// Get the action-safe rectangle in pixels.
+    pub fn action_safe(&self) -> Rect {
+        self.safe_rect(SafeArea::Action)
+    }
+
+    /// Get the title-safe rectangle in pixels.
+    pub fn title_safe(&self) -> Rect {
+        self.safe_rect(SafeArea::Title)
+    }
 }
 
-/// System to handle display mode changes.
-fn handle_display_mode_change(
-    config: Res<SwitchConfig>,
-    mut display: ResMut<SwitchDisplay>,
-    mut windows: Query<&mut Window>,
+/// Region of the display guaranteed to be visible on a TV.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SafeArea {
+    /// Whole display, no margins
+    Full,
+    /// Action-safe area - 93% of the display, for gameplay elements
+    Action,
+    /// Title-safe area - 90% of the display, for text and HUD
+    #[default]
+    Title,
+    /// Custom area covering the given percentage of each axis (0.0 to 100.0)
+    Custom(f32),
+}
+
+impl SafeArea {
+    /// Get the percentage of each axis covered by this area.
+    pub fn percent(&self) -> f32 {
+        match self {
+            SafeArea::Full => 100.0,
+            SafeArea::Action => 93.0,
+            SafeArea::Title => 90.0,
+            SafeArea::Custom(percent) => percent.clamp(0.0, 100.0),
+        }
+    }
+
+    /// Get the margin on each side as a fraction of the display (0.0 to 0.5).
+    pub fn margin(&self) -> f32 {
+        (1.0 - self.percent() / 100.0) / 2.0
+    }
+
+    /// Get this area as a normalized rectangle (0.0 to 1.0).
+    pub fn normalized_rect(&self) -> Rect {
+        let margin = self.margin();
+        Rect::new(margin, margin, 1.0 - margin, 1.0 - margin)
+    }
+}
+
+/// Marker for UI nodes that should be kept inside the display's safe area.
+///
+/// The node is positioned absolutely and resized whenever [`SwitchDisplay`]
+/// changes, so it follows docked/handheld switches automatically. Spawn your
+/// HUD as children of this node:
+///
+/// ```rust,no_run
+/// use bevy::prelude::*;
+/// use switchbrew_bevy::prelude::*;
+///
+/// fn setup_hud(mut commands: Commands) {
+///     commands.spawn(SafeAreaNode).with_children(|hud| {
+///         hud.spawn(Text::new("Score: 0"));
+///     });
+/// }
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default, Component)]
+#[require(Node)]
+pub struct SafeAreaNode;
+
+/// System to keep [`SafeAreaNode`]s inside the active safe area.
+#[cfg(feature = "std")]
+fn update_safe_area_nodes(
+    display: Res<SwitchDisplay>,
+    mut nodes: Query<(&mut Node, Ref<SafeAreaNode>)>,
 ) {
+    let rect = display.safe_rect_normalized(display.active_safe_area());
+
+    for (mut node, marker) in &mut nodes {
+        if !display.is_changed() && !marker.is_added() {
+            continue;
+        }
+
+        node.position_type = PositionType::Absolute;
+        node.left = Val::Percent(rect.min.x * 100.0);
+        node.top = Val::Percent(rect.min.y * 100.0);
+        node.width = Val::Percent(rect.width() * 100.0);
+        node.height = Val::Percent(rect.height() * 100.0);
+    }
+}
+
+/// UI scaling policy applied to Bevy's [`UiScale`] on display mode changes.
+///
+/// UI is authored once at `reference_resolution` and scaled to the active
+/// resolution, then multiplied by a per-screen factor. The built-in screen is
+/// much smaller than a TV (6.2" held close vs. a TV across the room), so text
+/// sized for docked play needs a boost in handheld and tabletop modes.
+///
+/// Insert your own value to tune it per game:
+///
+/// ```rust,no_run
+/// use bevy::prelude::*;
+/// use switchbrew_bevy::prelude::*;
+///
+/// App::new()
+///     .insert_resource(SwitchUiScale::default().with_handheld(1.5))
+///     .add_plugins(DefaultPlugins)
+///     .add_plugins(SwitchPlugin::default())
+///     .run();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SwitchUiScale {
+    /// Resolution the UI was authored for.
+    pub reference_resolution: (u32, u32),
+    /// Multiplier for the TV output (docked mode).
+    pub docked: f32,
+    /// Multiplier for the built-in screen (handheld and tabletop modes).
+    pub handheld: f32,
+}
+
+impl Default for SwitchUiScale {
+    fn default() -> Self {
+        Self {
+            reference_resolution: (1920, 1080),
+            docked: 1.0,
+            handheld: 1.25,
+        }
+    }
+}
+
+impl SwitchUiScale {
+    /// Create a policy for UI authored at the given resolution.
+    pub fn new(reference_resolution: (u32, u32)) -> Self {
+        Self {
+            reference_resolution,
+            ..Default::default()
+        }
+    }
+
+    /// Set the multiplier for docked mode.
+    pub fn with_docked(mut self, multiplier: f32) -> Self {
+        self.docked = multiplier;
+        self
+    }
+
+    /// Set the multiplier for handheld and tabletop modes.
+    pub fn with_handheld(mut self, multiplier: f32) -> Self {
+        self.handheld = multiplier;
+        self
+    }
+
+    /// Get the multiplier for a display mode.
+    pub fn multiplier(&self, mode: DisplayMode) -> f32 {
+        match mode {
+            DisplayMode::Docked => self.docked,
+            DisplayMode::Handheld | DisplayMode::Tabletop => self.handheld,
+        }
+    }
+
+    /// Compute the [`UiScale`] value for a display mode and resolution.
+    ///
+    /// Scales by height so 16:9 and letterboxed layouts behave the same.
+    pub fn scale_for(&self, mode: DisplayMode, resolution: (u32, u32)) -> f32 {
+        let reference_height = self.reference_resolution.1.max(1) as f32;
+        resolution.1 as f32 / reference_height * self.multiplier(mode)
+    }
+}
+
+/// System to handle display mode changes.
+fn handle_display_mode_change(config: Res<SwitchConfig>, mut display: ResMut<SwitchDisplay>) {
     if !config.is_changed() {
         return;
     }
 
     display.mode = config.display_mode;
     display.resolution = config.resolution;
+    display.bezel = bezel_margin(&config);
+}
+
+/// System to apply the [`SwitchUiScale`] policy to Bevy's [`UiScale`].
+#[cfg(feature = "std")]
+fn update_ui_scale(
+    config: Res<SwitchConfig>,
+    ui_scale_policy: Res<SwitchUiScale>,
+    ui_scale: Option<ResMut<UiScale>>,
+) {
+    // UiScale only exists when Bevy's UI plugin is added (not when headless)
+    if let Some(mut ui_scale) = ui_scale
+        && (config.is_changed() || ui_scale_policy.is_changed())
+    {
+        ui_scale.0 = ui_scale_policy.scale_for(config.display_mode, config.resolution);
+    }
+}
+
+/// System to keep desktop windows at the Switch resolution.
+///
+/// The logical window size always matches the Switch resolution (plus bezel),
+/// so game code sees 1920x1080 or 1280x720 regardless of the monitor. A
+/// preview scale other than 1.0 is applied on top of the OS scale factor
+/// through a scale factor override, which keeps HiDPI displays at their usual
+/// size instead of doubling the window. Without a preview scale the OS scale
+/// factor is left alone.
+#[cfg(feature = "std")]
+fn sync_window_resolution(
+    config: Res<SwitchConfig>,
+    display: Res<SwitchDisplay>,
+    mut preview_was_scaled: Local<bool>,
+    mut windows: Query<&mut Window>,
+) {
+    let (width, height) = display.window_size();
+    let preview_scaled = config.preview_scale != 1.0;
+    // Hand the scale factor back to the OS once the preview scale is reset
+    let preview_reset = *preview_was_scaled && !preview_scaled;
+    *preview_was_scaled = preview_scaled;
 
-    // Update window if on desktop
     for mut window in &mut windows {
+        let scale_factor_override =
+            preview_scaled.then(|| window.resolution.base_scale_factor() * config.preview_scale);
+        let scale_changed = (preview_scaled || preview_reset)
+            && window.resolution.scale_factor_override() != scale_factor_override;
+
+        if !scale_changed && !display.is_changed() {
+            continue;
+        }
+
+        if scale_changed {
+            window
+                .resolution
+                .set_scale_factor_override(scale_factor_override);
+        }
+        window.resolution.set(width as f32, height as f32);
+    }
+}
+
+/// System to keep cameras inside the screen area when a bezel is shown.
+///
+/// Cameras with a [`VirtualResolution`](crate::camera::VirtualResolution)
+/// handle the bezel themselves.
+#[cfg(feature = "std")]
+fn update_bezel_viewports(
+    display: Res<SwitchDisplay>,
+    mut bezel_was_shown: Local<bool>,
+    mut cameras: Query<&mut Camera, Without<crate::camera::VirtualResolution>>,
+) {
+    let bezel_shown = display.bezel != (0, 0);
+
+    if !bezel_shown {
+        // Hand the viewport back to the camera once the bezel is turned off
+        if *bezel_was_shown {
+            for mut camera in &mut cameras {
+                camera.viewport = None;
+            }
+        }
+        *bezel_was_shown = false;
+        return;
+    }
+    *bezel_was_shown = true;
+
+    for mut camera in &mut cameras {
+        let Some(target_size) = camera.physical_target_size() else {
+            continue;
+        };
+        let screen = display.screen_rect(target_size);
+
+        let unchanged = camera.viewport.as_ref().is_some_and(|current| {
+            current.physical_position == screen.min && current.physical_size == screen.size()
+        });
+        if !unchanged {
+            camera.viewport = Some(Viewport {
+                physical_position: screen.min,
+                physical_size: screen.size(),
+                ..default()
+            });
+        }
+    }
+}
+
+/// Size of the handheld device around its screen, as a fraction of the screen.
+///
+/// Based on the original model: a 137x77mm screen in a 239x102mm body with
+/// Joy-Cons attached.
+const BEZEL_FRACTION: (f32, f32) = (0.37, 0.16);
+
+/// Get the bezel margin on each side for a config, in logical pixels.
+fn bezel_margin(config: &SwitchConfig) -> (u32, u32) {
+    if !config.bezel || config.display_mode == DisplayMode::Docked {
+        return (0, 0);
+    }
+
+    (
+        ops::round(config.resolution.0 as f32 * BEZEL_FRACTION.0) as u32,
+        ops::round(config.resolution.1 as f32 * BEZEL_FRACTION.1) as u32,
+    )
+}
+
+/// Builder for window settings that follow a [`SwitchConfig`].
+///
+/// The window is sized for the config's resolution, scaled down by its
+/// `preview_scale` and surrounded by a handheld bezel if `bezel` is set:
+///
+/// ```rust,no_run
+/// use bevy::prelude::*;
+/// use switchbrew_bevy::prelude::*;
+///
+/// let config = SwitchConfig::handheld()
+///     .with_preview_scale(0.5)
+///     .with_bezel(true);
+///
+/// App::new()
+///     .add_plugins(DefaultPlugins.set(WindowPlugin {
+///         primary_window: Some(switch_window_for(&config).title("My Game").build()),
+///         ..default()
+///     }))
+///     .add_plugins(SwitchPlugin::new(config))
+///     .run();
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct SwitchWindowBuilder {
+    title: Option<String>,
+    size: (u32, u32),
+    preview_scale: f32,
+}
+
+#[cfg(feature = "std")]
+impl SwitchWindowBuilder {
+    /// Set the window title.
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Build the window settings.
+    ///
+    /// With a preview scale, the scale factor override is only a starting
+    /// point until the OS scale factor is known; [`SwitchWindowPlugin`] then
+    /// keeps it at the OS scale factor times the preview scale.
+    pub fn build(self) -> Window {
+        let physical_size = (
+            (self.size.0 as f32 * self.preview_scale).round() as u32,
+            (self.size.1 as f32 * self.preview_scale).round() as u32,
+        );
+
+        let mut resolution = WindowResolution::from(physical_size);
+        if self.preview_scale != 1.0 {
+            resolution = resolution.with_scale_factor_override(self.preview_scale);
+        }
+        let mut window = Window {
+            resolution,
+            resizable: false,
+            ..default()
+        };
+        if let Some(title) = self.title {
+            window.title = title;
+        }
         window
-            .resolution
-            .set(config.resolution.0 as f32, config.resolution.1 as f32);
+    }
+}
+
+/// Create window settings for a config.
+#[cfg(feature = "std")]
+pub fn switch_window_for(config: &SwitchConfig) -> SwitchWindowBuilder {
+    let bezel = bezel_margin(config);
+
+    SwitchWindowBuilder {
+        title: None,
+        size: (
+            config.resolution.0 + bezel.0 * 2,
+            config.resolution.1 + bezel.1 * 2,
+        ),
+        preview_scale: config.preview_scale,
     }
 }
 
 /// Helper to create Switch-compatible window settings.
+#[cfg(feature = "std")]
 pub fn switch_window(title: impl Into<String>) -> Window {
-    Window {
-        title: title.into(),
-        resolution: (1920, 1080).into(),
-        resizable: false,
-        ..default()
-    }
+    switch_window_for(&SwitchConfig::docked())
+        .title(title)
+        .build()
 }
 
 /// Helper to create handheld-mode window settings.
+#[cfg(feature = "std")]
 pub fn handheld_window(title: impl Into<String>) -> Window {
-    Window {
-        title: title.into(),
-        resolution: (1280, 720).into(),
-        resizable: false,
-        ..default()
-    }
+    switch_window_for(&SwitchConfig::handheld())
+        .title(title)
+        .build()
 }
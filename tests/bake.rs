@@ -0,0 +1,370 @@
+mod common;
+
+use std::io::Cursor;
+use std::path::Path;
+
+use common::TempDir;
+use image::ImageFormat;
+use switchbrew_bevy::bake::{
+    bake_asset, bake_audio, bake_dir, bake_texture, AssetKind, AssetManifest, BakeError,
+    BakeSettings, ByteSize,
+};
+use switchbrew_bevy::platform::Platform;
+
+const MANIFEST: &str = r#"
+[defaults]
+max_texture_size = 64
+
+[platforms.switch_handheld]
+max_texture_size = 16
+audio_sample_rate = 22050
+audio_mono = true
+
+[budgets]
+"." = "1 MiB"
+sprites = 512
+"audio/music" = "1 KiB"
+"#;
+
+fn png(width: u32, height: u32) -> Vec<u8> {
+    texture(width, height, ImageFormat::Png)
+}
+
+fn png_size(bytes: &[u8]) -> (u32, u32) {
+    texture_size(bytes, ImageFormat::Png)
+}
+
+fn texture(width: u32, height: u32, format: ImageFormat) -> Vec<u8> {
+    let texture = image::RgbImage::from_pixel(width, height, image::Rgb([200, 40, 40]));
+    let mut bytes = Vec::new();
+    texture
+        .write_to(&mut Cursor::new(&mut bytes), format)
+        .unwrap();
+    bytes
+}
+
+fn texture_size(bytes: &[u8], format: ImageFormat) -> (u32, u32) {
+    let texture = image::load_from_memory_with_format(bytes, format).unwrap();
+    (texture.width(), texture.height())
+}
+
+/// A 16-bit PCM WAV file of `frames` frames.
+fn wav(channels: u16, sample_rate: u32, frames: usize) -> Vec<u8> {
+    let data_len = (frames * channels as usize * 2) as u32;
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&channels.to_le_bytes());
+    bytes.extend_from_slice(&sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&(sample_rate * channels as u32 * 2).to_le_bytes());
+    bytes.extend_from_slice(&(channels * 2).to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for frame in 0..frames {
+        for channel in 0..channels {
+            let sample = ((frame * 37 + channel as usize * 1000) % 2000) as i16 - 1000;
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+    }
+    bytes
+}
+
+/// A mono 16-bit PCM WAV file of a sine tone at half amplitude.
+fn tone_wav(frequency: f64, sample_rate: u32, frames: usize) -> Vec<u8> {
+    let mut bytes = wav(1, sample_rate, frames);
+    for frame in 0..frames {
+        let phase = std::f64::consts::TAU * frequency * frame as f64 / sample_rate as f64;
+        let sample = (phase.sin() * 16384.0) as i16;
+        bytes[44 + frame * 2..46 + frame * 2].copy_from_slice(&sample.to_le_bytes());
+    }
+    bytes
+}
+
+/// Root mean square of a 16-bit mono WAV file, away from its ends.
+fn wav_rms(bytes: &[u8]) -> f64 {
+    let samples: Vec<f64> = bytes[44..]
+        .as_chunks::<2>()
+        .0
+        .iter()
+        .map(|&sample| i16::from_le_bytes(sample) as f64 / 32768.0)
+        .collect();
+    let middle = &samples[samples.len() / 4..samples.len() * 3 / 4];
+    (middle.iter().map(|sample| sample * sample).sum::<f64>() / middle.len() as f64).sqrt()
+}
+
+/// Channels and sample rate of a WAV file, and its data size.
+fn wav_format(bytes: &[u8]) -> (u16, u32, u32) {
+    let channels = u16::from_le_bytes([bytes[22], bytes[23]]);
+    let rate = u32::from_le_bytes(bytes[24..28].try_into().unwrap());
+    let data_len = u32::from_le_bytes(bytes[40..44].try_into().unwrap());
+    (channels, rate, data_len)
+}
+
+#[test]
+fn manifest_merges_platform_settings_over_defaults() {
+    let manifest = AssetManifest::from_toml(MANIFEST).unwrap();
+
+    assert_eq!(
+        manifest.settings_for(Platform::Desktop),
+        BakeSettings {
+            max_texture_size: Some(64),
+            ..Default::default()
+        }
+    );
+    assert_eq!(
+        manifest.settings_for(Platform::SwitchHandheld),
+        BakeSettings {
+            max_texture_size: Some(16),
+            audio_sample_rate: Some(22050),
+            audio_mono: Some(true),
+        }
+    );
+    assert_eq!(manifest.budgets["sprites"], ByteSize(512));
+    assert_eq!(manifest.budgets["audio/music"], ByteSize(1024));
+}
+
+#[test]
+fn manifest_rejects_unknown_keys() {
+    let error = AssetManifest::from_toml("[defaults]\nmax_texture = 1").unwrap_err();
+    assert!(matches!(error, BakeError::Manifest(_)));
+
+    let error = AssetManifest::from_toml("[budgets]\nsprites = \"lots\"").unwrap_err();
+    assert!(matches!(error, BakeError::Manifest(_)));
+}
+
+#[test]
+fn byte_sizes_parse_and_display() {
+    assert_eq!(ByteSize::parse("512"), Some(ByteSize(512)));
+    assert_eq!(ByteSize::parse("1.5 KiB"), Some(ByteSize(1536)));
+    assert_eq!(ByteSize::parse("16MiB"), Some(ByteSize(16 << 20)));
+    assert_eq!(ByteSize::parse("1 GiB"), Some(ByteSize(1 << 30)));
+    assert_eq!(ByteSize::parse("3 parsecs"), None);
+
+    assert_eq!(ByteSize(100).to_string(), "100 B");
+    assert_eq!(ByteSize(1536).to_string(), "1.5 KiB");
+    assert_eq!(ByteSize(16 << 20).to_string(), "16.0 MiB");
+}
+
+#[test]
+fn asset_kind_follows_extension() {
+    assert_eq!(
+        AssetKind::of(Path::new("sprites/crab.PNG")),
+        AssetKind::Texture
+    );
+    assert_eq!(
+        AssetKind::of(Path::new("photos/beach.jpeg")),
+        AssetKind::Texture
+    );
+    assert_eq!(AssetKind::of(Path::new("audio/hop.wav")), AssetKind::Audio);
+    assert_eq!(
+        AssetKind::of(Path::new("sprites/crab.ktx2")),
+        AssetKind::Unsupported
+    );
+    assert_eq!(
+        AssetKind::of(Path::new("audio/theme.ogg")),
+        AssetKind::Unsupported
+    );
+    assert_eq!(AssetKind::of(Path::new("levels/1.ron")), AssetKind::Other);
+
+    let bytes = b"(tiles: [])".to_vec();
+    let settings = BakeSettings {
+        max_texture_size: Some(1),
+        ..Default::default()
+    };
+    let baked = bake_asset(Path::new("levels/1.ron"), &bytes, &settings).unwrap();
+    assert_eq!(&*baked, &bytes[..]);
+}
+
+#[test]
+fn large_textures_are_downsized_keeping_aspect_ratio() {
+    let settings = BakeSettings {
+        max_texture_size: Some(32),
+        ..Default::default()
+    };
+
+    let large = png(128, 64);
+    let baked = bake_texture(&large, &settings).unwrap();
+    assert_eq!(png_size(&baked), (32, 16));
+
+    let small = png(16, 16);
+    let baked = bake_texture(&small, &settings).unwrap();
+    assert_eq!(&*baked, &small[..]);
+}
+
+#[test]
+fn jpeg_and_bmp_textures_keep_their_format() {
+    let settings = BakeSettings {
+        max_texture_size: Some(32),
+        ..Default::default()
+    };
+
+    for format in [ImageFormat::Jpeg, ImageFormat::Bmp] {
+        let bytes = texture(64, 128, format);
+        let baked = bake_texture(&bytes, &settings).unwrap();
+        assert_eq!(image::guess_format(&baked).unwrap(), format);
+        assert_eq!(texture_size(&baked, format), (16, 32));
+    }
+}
+
+#[test]
+fn textures_are_unchanged_without_a_limit() {
+    let bytes = png(128, 128);
+    let baked = bake_texture(&bytes, &BakeSettings::default()).unwrap();
+    assert_eq!(&*baked, &bytes[..]);
+}
+
+#[test]
+fn invalid_png_is_an_error() {
+    let settings = BakeSettings {
+        max_texture_size: Some(32),
+        ..Default::default()
+    };
+    let error = bake_texture(b"\x89PNG not really", &settings).unwrap_err();
+    assert!(matches!(error, BakeError::Texture(_)));
+}
+
+#[test]
+fn audio_is_resampled_and_mixed_to_mono() {
+    let settings = BakeSettings {
+        audio_sample_rate: Some(22050),
+        audio_mono: Some(true),
+        ..Default::default()
+    };
+    let stereo = wav(2, 44100, 4410);
+    let baked = bake_audio(&stereo, &settings).unwrap();
+    let (channels, rate, data_len) = wav_format(&baked);
+    assert_eq!((channels, rate), (1, 22050));
+    assert_eq!(data_len, 2205 * 2);
+}
+
+#[test]
+fn resampling_filters_frequencies_above_the_new_rate() {
+    let settings = BakeSettings {
+        audio_sample_rate: Some(22050),
+        ..Default::default()
+    };
+
+    // Below the new Nyquist frequency of 11 kHz, kept at its level
+    let low = tone_wav(1000.0, 48000, 4800);
+    let rms = wav_rms(&bake_audio(&low, &settings).unwrap());
+    assert!(
+        (rms - 0.5 / 2f64.sqrt()).abs() < 0.01,
+        "1 kHz tone at {rms}"
+    );
+
+    // Above it, filtered out instead of aliasing to 6 kHz
+    let high = tone_wav(16000.0, 48000, 4800);
+    let rms = wav_rms(&bake_audio(&high, &settings).unwrap());
+    assert!(rms < 0.005, "16 kHz tone at {rms}");
+}
+
+#[test]
+fn audio_that_fits_is_unchanged() {
+    let settings = BakeSettings {
+        audio_sample_rate: Some(48000),
+        audio_mono: Some(true),
+        ..Default::default()
+    };
+    let bytes = wav(1, 32000, 100);
+    let baked = bake_audio(&bytes, &settings).unwrap();
+    assert_eq!(&*baked, &bytes[..]);
+
+    let ogg = b"OggS not a wav".to_vec();
+    let baked = bake_audio(&ogg, &settings).unwrap();
+    assert_eq!(&*baked, &ogg[..]);
+}
+
+#[test]
+fn truncated_wav_is_an_error() {
+    let mut bytes = wav(1, 44100, 100);
+    bytes.truncate(60);
+    let settings = BakeSettings {
+        audio_sample_rate: Some(22050),
+        ..Default::default()
+    };
+    let error = bake_audio(&bytes, &settings).unwrap_err();
+    assert!(matches!(error, BakeError::Audio(_)));
+}
+
+#[test]
+fn bake_dir_writes_baked_files_and_reports_budgets() {
+    let source = TempDir::new("bake-source");
+    let out = TempDir::new("bake-out");
+    std::fs::create_dir_all(source.join("sprites")).unwrap();
+    std::fs::create_dir_all(source.join("audio/music")).unwrap();
+    std::fs::write(source.join("sprites/crab.png"), png(64, 64)).unwrap();
+    std::fs::write(source.join("audio/music/theme.wav"), wav(2, 44100, 4410)).unwrap();
+    std::fs::write(source.join("level.ron"), b"()").unwrap();
+
+    let manifest = AssetManifest::from_toml(MANIFEST).unwrap();
+    let report = bake_dir(&source, &out, &manifest, Platform::SwitchHandheld).unwrap();
+
+    let crab = std::fs::read(out.join("sprites/crab.png")).unwrap();
+    assert_eq!(png_size(&crab), (16, 16));
+    let theme = std::fs::read(out.join("audio/music/theme.wav")).unwrap();
+    assert_eq!(wav_format(&theme).0, 1);
+    assert_eq!(std::fs::read(out.join("level.ron")).unwrap(), b"()");
+
+    let root = report.dir(".").unwrap();
+    assert_eq!(root.files, 3);
+    assert_eq!(root.budget, Some(1 << 20));
+    assert!(root.baked_bytes < root.source_bytes);
+
+    let audio = report.dir("audio").unwrap();
+    assert_eq!(audio.files, 1);
+    assert_eq!(audio.budget, None);
+    assert_eq!(audio.baked_bytes, theme.len() as u64);
+
+    let over: Vec<_> = report
+        .over_budget()
+        .map(|usage| usage.dir.as_str())
+        .collect();
+    assert_eq!(over, ["audio/music"]);
+    assert!(report.to_string().contains("OVER BUDGET"));
+}
+
+#[test]
+fn bake_dir_reports_files_it_cannot_bake() {
+    let source = TempDir::new("bake-unsupported");
+    let out = TempDir::new("bake-unsupported-out");
+    std::fs::create_dir_all(source.join("audio")).unwrap();
+    std::fs::write(source.join("audio/theme.ogg"), b"OggS").unwrap();
+    std::fs::write(source.join("crab.ktx2"), b"KTX 20").unwrap();
+    std::fs::write(source.join("level.ron"), b"()").unwrap();
+
+    let manifest = AssetManifest::from_toml(MANIFEST).unwrap();
+    let report = bake_dir(&source, &out, &manifest, Platform::SwitchHandheld).unwrap();
+
+    assert_eq!(report.unbaked, ["crab.ktx2", "audio/theme.ogg"]);
+    assert_eq!(std::fs::read(out.join("audio/theme.ogg")).unwrap(), b"OggS");
+    let text = report.to_string();
+    assert!(text.contains("warning: crab.ktx2 not baked, unsupported format"));
+    assert!(text.contains("warning: audio/theme.ogg not baked"));
+}
+
+#[test]
+fn budgets_without_files_are_reported_empty() {
+    let manifest = AssetManifest::from_toml("[budgets]\nfonts = 100").unwrap();
+    let source = TempDir::new("bake-empty");
+    let out = TempDir::new("bake-empty-out");
+
+    let report = bake_dir(&source, &out, &manifest, Platform::Desktop).unwrap();
+    let fonts = report.dir("fonts").unwrap();
+    assert_eq!((fonts.files, fonts.baked_bytes), (0, 0));
+    assert_eq!(report.over_budget().count(), 0);
+}
+
+#[test]
+fn platform_names_parse() {
+    assert_eq!(Platform::parse("desktop"), Some(Platform::Desktop));
+    assert_eq!(
+        Platform::parse("switch_docked"),
+        Some(Platform::SwitchDocked)
+    );
+    assert_eq!(Platform::parse("Handheld"), Some(Platform::SwitchHandheld));
+    assert_eq!(Platform::parse("wii"), None);
+}
@@ -0,0 +1,37 @@
+//! Helpers shared by the integration tests.
+
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// Empty temporary directory, removed with its contents when dropped.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Create a directory unique to this test process.
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("switchbrew-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,272 @@
+#![cfg(feature = "config")]
+
+use std::path::Path;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use switchbrew_bevy::config::{
+    apply_env_overrides, load_settings, ConfigFileError, SettingsFile, SwitchSettings,
+};
+use switchbrew_bevy::platform::MockHal;
+use switchbrew_bevy::prelude::*;
+
+fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+    move |name| {
+        vars.iter()
+            .find(|(var, _)| *var == name)
+            .map(|(_, value)| value.to_string())
+    }
+}
+
+fn docked_settings() -> SwitchSettings {
+    SwitchSettings {
+        config: SwitchConfig::docked(),
+        ..default()
+    }
+}
+
+#[test]
+fn parses_toml() {
+    let file = SettingsFile::parse(
+        Path::new("switch.toml"),
+        r#"
+        display_mode = "handheld"
+        target_fps = 30
+        log_level = "debug"
+
+        [display]
+        safe_area = "action"
+
+        [input]
+        stick_deadzone = 0.2
+        "#,
+    )
+    .unwrap();
+
+    let mut settings = docked_settings();
+    file.apply(&mut settings);
+
+    assert_eq!(settings.config.display_mode, DisplayMode::Handheld);
+    assert_eq!(settings.config.platform, Platform::SwitchHandheld);
+    assert_eq!(settings.config.resolution, (1280, 720));
+    assert_eq!(settings.config.target_fps, 30);
+    assert_eq!(settings.config.log_level, LogLevel::Debug);
+    assert_eq!(settings.display.safe_area, SafeArea::Action);
+    assert_eq!(settings.input.stick_deadzone, 0.2);
+}
+
+#[test]
+fn parses_ron() {
+    let file = SettingsFile::parse(
+        Path::new("switch.ron"),
+        "(display_mode: tabletop, resolution: (640, 360), display: (safe_area: custom(95.0)))",
+    )
+    .unwrap();
+
+    let mut settings = docked_settings();
+    file.apply(&mut settings);
+
+    assert_eq!(settings.config.display_mode, DisplayMode::Tabletop);
+    assert_eq!(settings.config.resolution, (640, 360));
+    assert_eq!(settings.display.safe_area, SafeArea::Custom(95.0));
+}
+
+#[test]
+fn rejects_unknown_keys_and_formats() {
+    let error = SettingsFile::parse(Path::new("switch.toml"), "target_fsp = 30").unwrap_err();
+    assert!(matches!(error, ConfigFileError::Parse { .. }));
+    assert!(error.to_string().contains("switch.toml"));
+    assert!(error.to_string().contains("target_fsp"));
+
+    let error = SettingsFile::parse(Path::new("switch.json"), "{}").unwrap_err();
+    assert!(matches!(error, ConfigFileError::UnsupportedFormat { .. }));
+}
+
+#[test]
+fn env_overrides_apply() {
+    let mut settings = docked_settings();
+    apply_env_overrides(
+        &mut settings,
+        env(&[
+            ("SWITCHBREW_PLATFORM", "Handheld"),
+            ("SWITCHBREW_TARGET_FPS", "30"),
+            ("SWITCHBREW_PREVIEW_SCALE", "0.5"),
+            ("SWITCHBREW_BEZEL", "on"),
+            ("SWITCHBREW_LOG_LEVEL", "WARN"),
+        ]),
+    )
+    .unwrap();
+
+    assert_eq!(settings.config.display_mode, DisplayMode::Handheld);
+    assert_eq!(settings.config.resolution, (1280, 720));
+    assert_eq!(settings.config.target_fps, 30);
+    assert_eq!(settings.config.preview_scale, 0.5);
+    assert!(settings.config.bezel);
+    assert_eq!(settings.config.log_level, LogLevel::Warn);
+
+    apply_env_overrides(&mut settings, env(&[("SWITCHBREW_RESOLUTION", "800x600")])).unwrap();
+    assert_eq!(settings.config.resolution, (800, 600));
+}
+
+#[test]
+fn lite_model_without_mode_falls_back_to_handheld() {
+    let mut settings = docked_settings();
+    apply_env_overrides(&mut settings, env(&[("SWITCHBREW_MODEL", "lite")])).unwrap();
+    assert_eq!(settings.config.display_mode, DisplayMode::Handheld);
+    assert!(settings.validate().is_ok());
+
+    let mut settings = docked_settings();
+    SettingsFile::parse(Path::new("game.toml"), "model = \"lite\"")
+        .unwrap()
+        .apply(&mut settings);
+    assert_eq!(settings.config.display_mode, DisplayMode::Handheld);
+
+    // An explicit mode is kept, so validation can report it
+    let mut settings = docked_settings();
+    apply_env_overrides(
+        &mut settings,
+        env(&[
+            ("SWITCHBREW_MODEL", "lite"),
+            ("SWITCHBREW_PLATFORM", "docked"),
+        ]),
+    )
+    .unwrap();
+    assert_eq!(settings.config.display_mode, DisplayMode::Docked);
+    assert!(settings.validate().is_err());
+}
+
+#[test]
+fn plugin_validates_inserted_config() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .insert_resource(Hal::new(MockHal::new()))
+        .add_plugins(SwitchConfigPlugin {
+            config: Some(SwitchConfig::docked().with_model(SwitchModel::Lite)),
+        });
+    let config = app.world().resource::<SwitchConfig>();
+    assert_eq!(config.display_mode, DisplayMode::Handheld);
+    assert_eq!(config.model, SwitchModel::Lite);
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .insert_resource(Hal::new(MockHal::new()))
+        .add_plugins(SwitchConfigPlugin {
+            config: Some(
+                SwitchConfig::docked()
+                    .with_fps(0)
+                    .with_preview_scale(0.5)
+                    .with_bezel(true),
+            ),
+        });
+    let config = app.world().resource::<SwitchConfig>();
+    assert!(config.validate().is_ok());
+    // Only the invalid value is replaced
+    assert_eq!(config.target_fps, 60);
+    assert_eq!(config.display_mode, DisplayMode::Docked);
+    assert_eq!(config.preview_scale, 0.5);
+    assert!(config.bezel);
+}
+
+#[test]
+fn invalid_values_are_replaced_one_by_one() {
+    let info = PlatformInfo::detect_with(Some("docked"));
+    let mut config = SwitchConfig::docked()
+        .with_model(SwitchModel::Lite)
+        .with_preview_scale(9.0)
+        .with_fps(30);
+    config.resolution = (0, 720);
+
+    let config = config.with_valid_values(&info);
+    assert!(config.validate().is_ok());
+    assert_eq!(config.display_mode, DisplayMode::Handheld);
+    assert_eq!(config.resolution, (1280, 720));
+    assert_eq!(config.preview_scale, 1.0);
+    assert_eq!(config.target_fps, 30);
+}
+
+#[test]
+fn env_override_errors_name_the_variable() {
+    let mut settings = docked_settings();
+    let error =
+        apply_env_overrides(&mut settings, env(&[("SWITCHBREW_TARGET_FPS", "fast")])).unwrap_err();
+
+    assert!(matches!(
+        error,
+        ConfigFileError::Env {
+            var: "SWITCHBREW_TARGET_FPS",
+            ..
+        }
+    ));
+    assert_eq!(settings.config.target_fps, 60);
+}
+
+#[test]
+fn validation_errors_are_reported() {
+    let settings = docked_settings();
+    let error = load_settings(
+        &MockHal::new(),
+        Path::new("missing-switch.toml"),
+        &settings,
+        env(&[("SWITCHBREW_TARGET_FPS", "0")]),
+    )
+    .unwrap_err();
+
+    let ConfigFileError::Invalid { error, .. } = error else {
+        panic!("expected a validation error, got {error}");
+    };
+    assert_eq!(error.field, ConfigField::TargetFps);
+
+    let config = SwitchConfig::docked().with_preview_scale(0.0);
+    assert_eq!(
+        config.validate().unwrap_err().field,
+        ConfigField::PreviewScale
+    );
+}
+
+#[test]
+fn plugin_applies_file_to_resources() {
+    let path = std::env::temp_dir().join(format!("switchbrew-{}.toml", std::process::id()));
+    std::fs::write(&path, "target_fps = 30\n[ui_scale]\nhandheld = 1.5\n").unwrap();
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(SwitchPlugins::default())
+        .add_plugins(SwitchConfigFilePlugin::new(&path).with_watch(false));
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(app.world().resource::<SwitchConfig>().target_fps, 30);
+    assert_eq!(app.world().resource::<SwitchUiScale>().handheld, 1.5);
+}
+
+#[test]
+fn plugin_reads_and_reloads_through_hal() {
+    let mock = MockHal::new()
+        .with_file("game.ron", "(target_fps: 30)")
+        .with_env("SWITCHBREW_BEZEL", "on");
+
+    let mut app = App::new();
+    app.insert_resource(Hal::new(mock.clone()))
+        .add_plugins(MinimalPlugins)
+        .add_plugins(SwitchPlugins::new(SwitchConfig::handheld()))
+        .add_plugins(SwitchConfigFilePlugin::new("game.ron").with_watch(true));
+    app.update();
+
+    let config = app.world().resource::<SwitchConfig>();
+    assert_eq!(config.target_fps, 30);
+    assert!(config.bezel);
+
+    // Changes made since startup survive a reload
+    app.world_mut()
+        .resource_mut::<SwitchConfig>()
+        .show_perf_overlay = true;
+
+    mock.write_file("game.ron", b"(target_fps: 20)").unwrap();
+    app.update();
+    assert_eq!(app.world().resource::<SwitchConfig>().target_fps, 30);
+
+    mock.advance(Duration::from_secs(1));
+    app.update();
+    let config = app.world().resource::<SwitchConfig>();
+    assert_eq!(config.target_fps, 20);
+    assert!(config.show_perf_overlay);
+}
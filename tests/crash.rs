@@ -0,0 +1,118 @@
+#![cfg(feature = "std")]
+
+mod common;
+
+use std::path::Path;
+use std::process::Command;
+
+use bevy::prelude::*;
+use common::TempDir;
+use log::Level;
+use switchbrew_bevy::crash::{report_panic, CrashReport};
+use switchbrew_bevy::platform::MockHal;
+use switchbrew_bevy::prelude::*;
+
+/// Set in the child process, to the crash report path.
+const CHILD_ENV_VAR: &str = "SWITCHBREW_CRASH_TEST_REPORT";
+
+/// Log a line each frame and panic on the third.
+fn crab_game(logger: Res<SwitchLogger>, mut frame: Local<u32>) {
+    logger.log(
+        Level::Info,
+        "crab_crossing",
+        format_args!("frame {}", *frame),
+    );
+    *frame += 1;
+    if *frame == 3 {
+        panic!("crab escaped the level");
+    }
+}
+
+/// Re-run this test binary with only `test`, as the child process.
+fn run_child(test: &str, report: &Path) -> std::process::Output {
+    Command::new(std::env::current_exe().unwrap())
+        .args([test, "--exact", "--nocapture", "--test-threads=1"])
+        .env(CHILD_ENV_VAR, report)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn formats_report() {
+    let report = CrashReport {
+        message: "index out of bounds".into(),
+        location: Some("src/main.rs:12:5".into()),
+        frame: 42,
+        config: Some(SwitchConfig::handheld().with_fps(30)),
+        recent_log: vec!["[    0.700] INFO  game: loaded".into()],
+    };
+    let text = report.to_string();
+
+    assert!(text.starts_with("switchbrew_bevy crash report\n"));
+    assert!(text.contains("Message: index out of bounds\n"));
+    assert!(text.contains("Location: src/main.rs:12:5\n"));
+    assert!(text.contains("Frame: 42\n"));
+    assert!(text.contains("display_mode: Handheld,"));
+    assert!(text.contains("target_fps: 30,"));
+    assert!(text.ends_with("Recent log:\n[    0.700] INFO  game: loaded\n"));
+}
+
+#[test]
+fn reports_through_the_hal() {
+    let mock = MockHal::new().with_executable_path("sdmc:/switch/crab/crab.nro");
+    let mut app = App::new();
+    app.insert_resource(Hal::new(mock.clone()))
+        .add_plugins(SwitchPlugin::new(SwitchConfig::docked()))
+        .add_plugins(SwitchCrashPlugin::new("crashes/last.txt"));
+    app.update();
+    app.world()
+        .resource::<SwitchLogger>()
+        .log(Level::Warn, "game", format_args!("low memory"));
+    app.update();
+
+    let report = report_panic(&"out of memory", None).unwrap();
+    assert_eq!(report.frame, 2);
+    assert_eq!(report.location, None);
+    assert_eq!(report.config, Some(SwitchConfig::docked()));
+    assert_eq!(report.recent_log, ["[    0.000] WARN  game: low memory"]);
+
+    // Written next to the NRO
+    let file = String::from_utf8(mock.file("sdmc:/switch/crab/crashes/last.txt").unwrap()).unwrap();
+    assert_eq!(file, report.to_string());
+    let [(title, message)] = &mock.errors()[..] else {
+        panic!("expected one error");
+    };
+    assert_eq!(title, "The game crashed");
+    assert!(message.starts_with("out of memory\n"));
+    assert!(message.ends_with("Crash report saved to sdmc:/switch/crab/crashes/last.txt"));
+}
+
+#[test]
+fn panic_writes_crash_report() {
+    if let Some(report) = std::env::var_os(CHILD_ENV_VAR) {
+        let mut app = App::new();
+        app.add_plugins(SwitchPlugin::new(SwitchConfig::handheld().with_fps(30)))
+            .add_plugins(SwitchCrashPlugin::new(report.to_string_lossy()).with_show_error(false))
+            .add_systems(Update, crab_game);
+        loop {
+            app.update();
+        }
+    }
+
+    let dir = TempDir::new("crash-panic");
+    let report = dir.join("crash.txt");
+    let output = run_child("panic_writes_crash_report", &report);
+    assert!(!output.status.success());
+    // The default hook still prints the panic
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("crab escaped the level"), "{stderr}");
+
+    let text = std::fs::read_to_string(&report).unwrap();
+    assert!(text.contains("Message: crab escaped the level\n"), "{text}");
+    assert!(text.contains("Location: tests/crash.rs:"), "{text}");
+    assert!(text.contains("Frame: 2\n"), "{text}");
+    assert!(text.contains("display_mode: Handheld,"), "{text}");
+    assert!(text.contains("target_fps: 30,"), "{text}");
+    assert!(text.contains("INFO  crab_crossing: frame 0\n"), "{text}");
+    assert!(text.contains("INFO  crab_crossing: frame 2\n"), "{text}");
+}
@@ -0,0 +1,43 @@
+//! Runs the spinlock critical section linked by the `switch` feature:
+//!
+//! ```bash
+//! cargo test --no-default-features --features switch --test critical_section
+//! ```
+
+#![cfg(feature = "critical-section-spin")]
+
+use core::cell::Cell;
+use std::thread;
+
+use critical_section::Mutex;
+// Links the implementation, as nothing else here uses the crate
+use switchbrew_bevy as _;
+
+static COUNTER: Mutex<Cell<u64>> = Mutex::new(Cell::new(0));
+
+#[test]
+fn spin_section_excludes_threads_and_nests() {
+    const THREADS: u64 = 4;
+    const ITERATIONS: u64 = 10_000;
+
+    let handles: Vec<_> = (0..THREADS)
+        .map(|_| {
+            thread::spawn(|| {
+                for _ in 0..ITERATIONS {
+                    critical_section::with(|cs| {
+                        let counter = COUNTER.borrow(cs);
+                        let value = counter.get();
+                        // The owner may re-enter without deadlocking
+                        critical_section::with(|_| thread::yield_now());
+                        counter.set(value + 1);
+                    });
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    critical_section::with(|cs| assert_eq!(COUNTER.borrow(cs).get(), THREADS * ITERATIONS));
+}
@@ -0,0 +1,131 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use switchbrew_bevy::emulator::{EmulatorConfig, EmulatorKind};
+use switchbrew_bevy::nro::SwitchMetadata;
+
+fn os_args(args: &[&str]) -> Vec<OsString> {
+    args.iter().map(OsString::from).collect()
+}
+
+#[test]
+fn guesses_kind_from_executable_name() {
+    for (path, kind) in [
+        ("/opt/ryubing/Ryujinx", EmulatorKind::Ryujinx),
+        ("C:\\Emulators\\Ryujinx.exe", EmulatorKind::Ryujinx),
+        ("Ryubing.AppImage", EmulatorKind::Ryujinx),
+        ("/usr/bin/citron", EmulatorKind::Citron),
+        ("yuzu-cmd", EmulatorKind::Citron),
+        ("/usr/bin/my-launcher", EmulatorKind::Custom),
+    ] {
+        assert_eq!(EmulatorKind::from_path(Path::new(path)), kind, "{path}");
+    }
+
+    let config = EmulatorConfig::new("citron").with_kind(EmulatorKind::Ryujinx);
+    assert_eq!(config.kind(), EmulatorKind::Ryujinx);
+}
+
+#[test]
+fn builds_arguments_per_kind() {
+    let nro = Path::new("target/game.nro");
+
+    let ryujinx = EmulatorConfig::new("Ryujinx").with_args(["--fullscreen"]);
+    assert_eq!(
+        ryujinx.args(nro),
+        os_args(&["--fullscreen", "target/game.nro"])
+    );
+
+    let citron = EmulatorConfig::new("citron");
+    assert_eq!(citron.args(nro), os_args(&["-g", "target/game.nro"]));
+
+    let custom = EmulatorConfig::new("launcher").with_args(["--rom={nro}", "--debug"]);
+    assert_eq!(
+        custom.args(nro),
+        os_args(&["--rom=target/game.nro", "--debug"])
+    );
+    let custom = EmulatorConfig::new("launcher").with_args(["--debug"]);
+    assert_eq!(custom.args(nro), os_args(&["--debug", "target/game.nro"]));
+
+    let command = ryujinx.command(nro);
+    assert_eq!(command.get_program(), "Ryujinx");
+    assert_eq!(command.get_args().count(), 2);
+}
+
+#[test]
+fn quotes_dry_run_command_line() {
+    let config = EmulatorConfig::new("/Applications/Ryujinx.app/Contents/MacOS/Ryujinx")
+        .with_args(["--title", "Crab's Crossing"]);
+    assert_eq!(
+        config.command_line(Path::new("/tmp/my games/crab.nro")),
+        "/Applications/Ryujinx.app/Contents/MacOS/Ryujinx --title 'Crab'\\''s Crossing' \
+         '/tmp/my games/crab.nro'"
+    );
+}
+
+#[test]
+fn env_override_replaces_path_and_kind() {
+    let config = EmulatorConfig::new("Ryujinx")
+        .with_kind(EmulatorKind::Ryujinx)
+        .with_args(["--fullscreen"]);
+
+    let overridden = config.clone().with_env_override(Some("/usr/bin/citron"));
+    assert_eq!(overridden.path, PathBuf::from("/usr/bin/citron"));
+    assert_eq!(overridden.kind(), EmulatorKind::Citron);
+    assert_eq!(overridden.args, ["--fullscreen"]);
+
+    assert_eq!(config.clone().with_env_override(None), config);
+    assert_eq!(config.clone().with_env_override(Some("")), config);
+}
+
+#[test]
+fn reads_emulator_from_cargo_toml() {
+    let manifest = r#"
+        [package]
+        name = "crab_crossing"
+        version = "0.2.0"
+        authors = ["Ibrahim Cesar"]
+
+        [package.metadata.switch.emulator]
+        path = "tools/Ryujinx"
+        args = ["--fullscreen"]
+        log = "logs/ryujinx.log"
+    "#;
+    let metadata = SwitchMetadata::from_cargo_toml(manifest, Path::new("game")).unwrap();
+    assert_eq!(
+        metadata.emulator,
+        Some(
+            EmulatorConfig::new("game/tools/Ryujinx")
+                .with_args(["--fullscreen"])
+                .with_log("game/logs/ryujinx.log")
+        )
+    );
+
+    // Bare names are looked up in the PATH
+    let manifest = r#"
+        [package]
+        name = "crab_crossing"
+        version = "0.2.0"
+        authors = ["Ibrahim Cesar"]
+
+        [package.metadata.switch.emulator]
+        path = "citron"
+        kind = "citron"
+    "#;
+    let metadata = SwitchMetadata::from_cargo_toml(manifest, Path::new("game")).unwrap();
+    assert_eq!(
+        metadata.emulator,
+        Some(EmulatorConfig::new("citron").with_kind(EmulatorKind::Citron))
+    );
+
+    let manifest = r#"
+        [package]
+        name = "crab_crossing"
+        version = "0.2.0"
+        authors = ["Ibrahim Cesar"]
+
+        [package.metadata.switch.emulator]
+        path = "citron"
+        kind = "dolphin"
+    "#;
+    assert!(SwitchMetadata::from_cargo_toml(manifest, Path::new("")).is_err());
+}
@@ -0,0 +1,151 @@
+#![cfg(feature = "std")]
+
+use bevy::prelude::*;
+use log::Level;
+use switchbrew_bevy::platform::{FsError, HardwareProfile, MockHal};
+use switchbrew_bevy::prelude::*;
+
+fn app_with(mock: &MockHal) -> App {
+    let mut app = App::new();
+    app.insert_resource(Hal::new(mock.clone()))
+        .add_plugins(SwitchPlugin::default());
+    app
+}
+
+#[test]
+fn mock_filesystem_round_trips() {
+    let mock = MockHal::new().with_file("save.tmp", "data");
+
+    assert_eq!(mock.read_file("save.bin"), Err(FsError::NotFound));
+    mock.rename("save.tmp", "save.bin").unwrap();
+    assert_eq!(mock.read_file("save.bin").unwrap(), b"data");
+    assert_eq!(mock.paths(), ["save.bin"]);
+    assert_eq!(mock.read_file_range("save.bin", 1, 2).unwrap(), b"at");
+    assert_eq!(mock.read_file_range("save.bin", 2, 10).unwrap(), b"ta");
+    assert_eq!(mock.read_file_range("save.bin", 10, 1).unwrap(), b"");
+
+    mock.remove_file("save.bin").unwrap();
+    assert_eq!(mock.remove_file("save.bin"), Err(FsError::NotFound));
+}
+
+#[test]
+fn game_paths_resolve_next_to_the_nro() {
+    let mock = MockHal::new();
+    assert_eq!(mock.game_path("crash.txt"), "crash.txt");
+
+    let mock = mock.with_executable_path("sdmc:/switch/crab/crab.nro");
+    assert_eq!(mock.game_path("crash.txt"), "sdmc:/switch/crab/crash.txt");
+    assert_eq!(mock.game_path("logs/a.log"), "sdmc:/switch/crab/logs/a.log");
+    assert_eq!(mock.game_path("/crash.txt"), "/crash.txt");
+    assert_eq!(mock.game_path("save:/crash.txt"), "save:/crash.txt");
+}
+
+#[test]
+fn detects_console_through_hal() {
+    let mock = MockHal::new()
+        .with_display_mode(DisplayMode::Handheld)
+        .with_model(SwitchModel::Oled)
+        .with_emulator(true);
+    let app = app_with(&mock);
+
+    let info = app.world().resource::<PlatformInfo>();
+    assert_eq!(info.platform, Platform::SwitchHandheld);
+    assert!(info.emulator);
+    assert!(!info.simulated);
+
+    let config = app.world().resource::<SwitchConfig>();
+    assert_eq!(config.display_mode, DisplayMode::Handheld);
+    assert_eq!(config.resolution, (1280, 720));
+    assert_eq!(config.model, SwitchModel::Oled);
+}
+
+#[test]
+fn detects_applet_mode_through_hal() {
+    let mock = MockHal::new()
+        .with_display_mode(DisplayMode::Handheld)
+        .with_launch_mode(LaunchMode::Applet)
+        .with_memory_limit(400 << 20);
+    let app = app_with(&mock);
+
+    let info = app.world().resource::<PlatformInfo>();
+    assert_eq!(info.launch_mode, LaunchMode::Applet);
+    assert_eq!(info.memory_limit, Some(400 << 20));
+
+    let config = app.world().resource::<SwitchConfig>();
+    assert_eq!(config.launch_mode, LaunchMode::Applet);
+    assert_eq!(
+        config.hardware().memory_budget,
+        HardwareProfile::APPLET_MEMORY
+    );
+}
+
+#[test]
+fn platform_override_comes_from_hal_env() {
+    let mock = MockHal::new().with_env("SWITCHBREW_PLATFORM", "tabletop");
+    let info = PlatformInfo::from_hal(&mock);
+
+    assert_eq!(info.platform, Platform::SwitchHandheld);
+    assert_eq!(info.display_mode, DisplayMode::Tabletop);
+    assert!(info.simulated);
+}
+
+#[test]
+fn native_input_reaches_switch_input() {
+    let mock = MockHal::new();
+    let mut app = app_with(&mock);
+
+    mock.set_input(Some(HalInput {
+        buttons: vec![SwitchButton::A],
+        left_stick: Vec2::new(0.0, 0.5),
+        ..default()
+    }));
+    app.update();
+    let input = app.world().resource::<SwitchInput>();
+    assert!(input.just_pressed(SwitchButton::A));
+    assert_eq!(input.left_stick, Vec2::new(0.0, 0.5));
+
+    app.update();
+    assert!(app
+        .world()
+        .resource::<SwitchInput>()
+        .pressed(SwitchButton::A));
+    assert!(!app
+        .world()
+        .resource::<SwitchInput>()
+        .just_pressed(SwitchButton::A));
+
+    mock.set_input(Some(HalInput::default()));
+    app.update();
+    let input = app.world().resource::<SwitchInput>();
+    assert!(input.just_released(SwitchButton::A));
+    assert_eq!(input.left_stick, Vec2::ZERO);
+}
+
+#[test]
+fn diagnostics_log_through_hal() {
+    let mock = MockHal::new();
+    let mut app = app_with(&mock);
+    app.update();
+
+    let logs = mock.logs();
+    assert_eq!(
+        logs[0],
+        (Level::Info, "switchbrew_bevy initialized".to_string())
+    );
+    assert!(logs.iter().any(|(_, line)| line == "Resolution: 1920x1080"));
+}
+
+#[test]
+fn hal_drives_the_runner() {
+    let mock = MockHal::new();
+    let mut app = app_with(&mock);
+    let mut runner = SwitchRunner::new(app.world().resource::<Hal>().clone());
+
+    assert_eq!(runner.step(&mut app), None);
+    mock.push_message(AppletMessage::FocusLost);
+    assert_eq!(runner.step(&mut app), None);
+    assert!(runner.is_paused());
+    mock.push_message(AppletMessage::ExitRequested);
+    assert_eq!(runner.step(&mut app), Some(AppExit::Success));
+    assert_eq!(mock.frames(), 3);
+}
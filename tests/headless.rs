@@ -0,0 +1,88 @@
+#![cfg(feature = "std")]
+
+use bevy::prelude::*;
+use switchbrew_bevy::prelude::*;
+
+fn assert_percent(val: Val, expected: f32) {
+    match val {
+        Val::Percent(percent) => {
+            assert!((percent - expected).abs() < 1e-4, "{percent} != {expected}")
+        }
+        other => panic!("expected a percentage, got {other:?}"),
+    }
+}
+
+fn headless_app() -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(SwitchPlugin::default());
+    app
+}
+
+#[test]
+fn runs_under_minimal_plugins() {
+    let mut app = headless_app();
+    app.update();
+    app.update();
+
+    let input = app.world().resource::<SwitchInput>();
+    assert_eq!(input.movement(), Vec2::ZERO);
+    assert!(!input.pressed(SwitchButton::A));
+}
+
+#[test]
+fn tracks_display_mode_without_a_window() {
+    let mut app = headless_app();
+    app.update();
+
+    let display = app.world().resource::<SwitchDisplay>();
+    assert_eq!(display.resolution, (1920, 1080));
+
+    *app.world_mut().resource_mut::<SwitchConfig>() = SwitchConfig::handheld();
+    app.update();
+
+    let display = app.world().resource::<SwitchDisplay>();
+    assert_eq!(display.mode, DisplayMode::Handheld);
+    assert_eq!(display.resolution, (1280, 720));
+}
+
+#[test]
+fn lays_out_safe_area_nodes_without_a_window() {
+    let mut app = headless_app();
+    let hud = app.world_mut().spawn(SafeAreaNode).id();
+    app.update();
+
+    let node = app.world().get::<Node>(hud).unwrap();
+    assert_percent(node.width, 90.0);
+    assert_percent(node.left, 5.0);
+
+    *app.world_mut().resource_mut::<SwitchConfig>() = SwitchConfig::handheld();
+    app.update();
+
+    let node = app.world().get::<Node>(hud).unwrap();
+    assert_percent(node.width, 100.0);
+    assert_percent(node.left, 0.0);
+}
+
+#[test]
+fn overrides_scale_factor_only_for_preview_scale() {
+    let mut app = headless_app();
+    let window = app.world_mut().spawn(Window::default()).id();
+    app.update();
+
+    let resolution = &app.world().get::<Window>(window).unwrap().resolution;
+    assert_eq!(resolution.scale_factor_override(), None);
+    assert_eq!(resolution.width(), 1920.0);
+
+    *app.world_mut().resource_mut::<SwitchConfig>() =
+        SwitchConfig::docked().with_preview_scale(0.5);
+    app.update();
+    let resolution = &app.world().get::<Window>(window).unwrap().resolution;
+    assert_eq!(resolution.scale_factor_override(), Some(0.5));
+    assert_eq!(resolution.width(), 1920.0);
+
+    *app.world_mut().resource_mut::<SwitchConfig>() = SwitchConfig::docked();
+    app.update();
+    let resolution = &app.world().get::<Window>(window).unwrap().resolution;
+    assert_eq!(resolution.scale_factor_override(), None);
+}
@@ -0,0 +1,36 @@
+//! Runs [`HorizonHal`] against the host stand-ins for libnx:
+//!
+//! ```bash
+//! cargo test --no-default-features --features switch --test horizon
+//! ```
+
+#![cfg(feature = "switch")]
+
+use switchbrew_bevy::platform::{horizon_host, AppletMessage, DisplayMode, HorizonHal, SwitchHal};
+
+/// `AppletFocusState_InFocus`
+const FOCUS_STATE_IN_FOCUS: u32 = 1;
+/// `AppletFocusState_OutOfFocus`
+const FOCUS_STATE_OUT_OF_FOCUS: u32 = 2;
+/// `AppletOperationMode_Handheld`
+const OPERATION_MODE_HANDHELD: u32 = 0;
+
+#[test]
+fn applet_messages_report_the_new_state() {
+    let hal = HorizonHal::new();
+    assert_eq!(hal.display_mode(), Some(DisplayMode::Docked));
+    assert_eq!(hal.poll_applet_message(), None);
+
+    horizon_host::change_focus(FOCUS_STATE_OUT_OF_FOCUS);
+    assert_eq!(hal.poll_applet_message(), Some(AppletMessage::FocusLost));
+    horizon_host::change_focus(FOCUS_STATE_IN_FOCUS);
+    assert_eq!(hal.poll_applet_message(), Some(AppletMessage::FocusGained));
+
+    horizon_host::change_operation_mode(OPERATION_MODE_HANDHELD);
+    assert_eq!(
+        hal.poll_applet_message(),
+        Some(AppletMessage::OperationModeChanged(DisplayMode::Handheld))
+    );
+    assert_eq!(hal.display_mode(), Some(DisplayMode::Handheld));
+    assert_eq!(hal.poll_applet_message(), None);
+}
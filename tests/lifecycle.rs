@@ -0,0 +1,255 @@
+#![cfg(feature = "std")]
+
+use bevy::prelude::*;
+use bevy::time::TimePlugin;
+use switchbrew_bevy::lifecycle::{is_running, BeforeExit};
+use switchbrew_bevy::platform::MockHal;
+use switchbrew_bevy::prelude::*;
+
+#[derive(Resource, Default)]
+struct Received(Vec<LifecycleEvent>);
+
+#[derive(Resource, Default)]
+struct Ticks(usize);
+
+fn record(mut events: MessageReader<LifecycleEvent>, mut received: ResMut<Received>) {
+    received.0.extend(events.read().copied());
+}
+
+fn tick(mut ticks: ResMut<Ticks>) {
+    ticks.0 += 1;
+}
+
+fn build_app_with(mock: MockHal) -> App {
+    let mut app = App::new();
+    app.insert_resource(Hal::new(mock))
+        .add_plugins(TimePlugin)
+        .add_plugins(SwitchPlugin::new(SwitchConfig::handheld()))
+        .init_resource::<Received>()
+        .init_resource::<Ticks>()
+        .add_systems(Update, (record, tick.run_if(is_running)));
+    app
+}
+
+/// Queue messages and step the runner once.
+fn step(
+    app: &mut App,
+    runner: &mut SwitchRunner<Hal>,
+    mock: &MockHal,
+    messages: &[AppletMessage],
+) {
+    for &message in messages {
+        mock.push_message(message);
+    }
+    runner.step(app);
+}
+
+#[test]
+fn applet_messages_pause_and_resume() {
+    let mock = MockHal::new();
+    let mut app = build_app_with(mock.clone());
+    let mut runner = SwitchRunner::new(Hal::new(mock.clone()));
+
+    step(&mut app, &mut runner, &mock, &[]);
+    assert!(!app.world().resource::<Time<Virtual>>().is_paused());
+
+    step(
+        &mut app,
+        &mut runner,
+        &mock,
+        &[AppletMessage::FocusLost, AppletMessage::Suspended],
+    );
+    let lifecycle = app.world().resource::<SwitchLifecycle>();
+    assert!(!lifecycle.is_focused());
+    assert!(lifecycle.is_suspended());
+    assert!(app.world().resource::<Time<Virtual>>().is_paused());
+    assert_eq!(app.world().resource::<Ticks>().0, 1);
+
+    step(&mut app, &mut runner, &mock, &[AppletMessage::Resumed]);
+    assert!(app.world().resource::<SwitchLifecycle>().is_paused());
+    assert!(app.world().resource::<Time<Virtual>>().is_paused());
+
+    // Losing focus again while out of focus sends nothing
+    step(
+        &mut app,
+        &mut runner,
+        &mock,
+        &[AppletMessage::FocusLost, AppletMessage::FocusGained],
+    );
+    assert!(!app.world().resource::<SwitchLifecycle>().is_paused());
+    assert!(!app.world().resource::<Time<Virtual>>().is_paused());
+    assert_eq!(app.world().resource::<Ticks>().0, 2);
+
+    assert_eq!(
+        app.world().resource::<Received>().0,
+        [
+            LifecycleEvent::FocusLost,
+            LifecycleEvent::Suspended,
+            LifecycleEvent::Resumed,
+            LifecycleEvent::FocusGained,
+        ]
+    );
+}
+
+#[test]
+fn suspend_then_resume_keeps_focus() {
+    let mock = MockHal::new();
+    let mut app = build_app_with(mock.clone());
+    let mut runner = SwitchRunner::new(Hal::new(mock.clone()));
+
+    step(&mut app, &mut runner, &mock, &[]);
+    // Moved to the background by the HOME menu
+    step(&mut app, &mut runner, &mock, &[AppletMessage::Suspended]);
+    let lifecycle = app.world().resource::<SwitchLifecycle>();
+    assert!(lifecycle.is_focused());
+    assert!(lifecycle.is_suspended());
+    assert!(runner.is_paused());
+    assert!(app.world().resource::<Time<Virtual>>().is_paused());
+
+    step(&mut app, &mut runner, &mock, &[AppletMessage::Resumed]);
+    assert!(!app.world().resource::<SwitchLifecycle>().is_paused());
+    assert!(!runner.is_paused());
+    assert!(!app.world().resource::<Time<Virtual>>().is_paused());
+
+    // A sleep reported on wake-up
+    step(
+        &mut app,
+        &mut runner,
+        &mock,
+        &[AppletMessage::Suspended, AppletMessage::Resumed],
+    );
+    assert!(!app.world().resource::<SwitchLifecycle>().is_paused());
+    assert!(!runner.is_paused());
+
+    assert_eq!(
+        app.world().resource::<Received>().0,
+        [
+            LifecycleEvent::Suspended,
+            LifecycleEvent::Resumed,
+            LifecycleEvent::Suspended,
+            LifecycleEvent::Resumed,
+        ]
+    );
+}
+
+#[test]
+fn time_paused_by_the_game_stays_paused() {
+    let mock = MockHal::new();
+    let mut app = build_app_with(mock.clone());
+    let mut runner = SwitchRunner::new(Hal::new(mock.clone()));
+
+    step(&mut app, &mut runner, &mock, &[]);
+    app.world_mut().resource_mut::<Time<Virtual>>().pause();
+    step(&mut app, &mut runner, &mock, &[AppletMessage::FocusLost]);
+    step(&mut app, &mut runner, &mock, &[AppletMessage::FocusGained]);
+
+    assert!(app.world().resource::<Time<Virtual>>().is_paused());
+}
+
+#[test]
+fn before_exit_runs_once() {
+    #[derive(Resource, Default)]
+    struct Flushes(usize);
+
+    let mock = MockHal::new();
+    let mut app = build_app_with(mock.clone());
+    app.init_resource::<Flushes>()
+        .add_systems(BeforeExit, |mut flushes: ResMut<Flushes>| flushes.0 += 1);
+    let mut runner = SwitchRunner::new(Hal::new(mock.clone()));
+
+    step(&mut app, &mut runner, &mock, &[]);
+    assert_eq!(app.world().resource::<Flushes>().0, 0);
+
+    step(
+        &mut app,
+        &mut runner,
+        &mock,
+        &[AppletMessage::ExitRequested],
+    );
+    assert!(app.world().resource::<SwitchLifecycle>().exit_requested());
+    assert_eq!(app.world().resource::<Flushes>().0, 1);
+
+    app.world_mut().write_message(AppExit::Success);
+    app.update();
+    assert_eq!(app.world().resource::<Flushes>().0, 1);
+    assert_eq!(
+        app.world().resource::<Received>().0,
+        [LifecycleEvent::ExitRequested]
+    );
+}
+
+#[cfg(feature = "save")]
+#[test]
+fn commits_saves_before_exit() {
+    use serde::{Deserialize, Serialize};
+    use switchbrew_bevy::save::{MemorySaveBackend, SaveBackend};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Progress {
+        level: u32,
+    }
+
+    impl SaveData for Progress {
+        const SLOT: &'static str = "progress";
+    }
+
+    let backend = MemorySaveBackend::new();
+    let mut app = build_app_with(MockHal::new());
+    app.add_plugins(SwitchSavePlugin::new(backend.clone()));
+    app.update();
+
+    app.world_mut()
+        .resource_mut::<SwitchSave>()
+        .store(UserId::DEFAULT, &Progress { level: 4 })
+        .unwrap();
+    app.update();
+    assert_eq!(backend.slots(UserId::DEFAULT).unwrap(), [] as [String; 0]);
+
+    app.world_mut().write_message(AppExit::Success);
+    app.update();
+    assert!(!app.world().resource::<SwitchSave>().has_pending());
+    assert_eq!(backend.slots(UserId::DEFAULT).unwrap(), ["progress"]);
+}
+
+#[test]
+fn primary_window_maps_to_lifecycle_events() {
+    use bevy::window::{PrimaryWindow, WindowCloseRequested, WindowFocused, WindowOccluded};
+
+    let mut app = build_app_with(MockHal::new());
+    let window = app.world_mut().spawn(PrimaryWindow).id();
+    let other = app.world_mut().spawn_empty().id();
+    app.update();
+
+    app.world_mut().write_message(WindowFocused {
+        window: other,
+        focused: false,
+    });
+    app.world_mut().write_message(WindowOccluded {
+        window,
+        occluded: true,
+    });
+    app.update();
+    assert!(app.world().resource::<SwitchLifecycle>().is_focused());
+    assert!(app.world().resource::<SwitchLifecycle>().is_suspended());
+
+    app.world_mut().write_message(WindowFocused {
+        window,
+        focused: false,
+    });
+    app.world_mut().write_message(WindowOccluded {
+        window,
+        occluded: false,
+    });
+    app.world_mut().write_message(WindowCloseRequested { window });
+    app.update();
+
+    assert_eq!(
+        app.world().resource::<Received>().0,
+        [
+            LifecycleEvent::Suspended,
+            LifecycleEvent::FocusLost,
+            LifecycleEvent::Resumed,
+            LifecycleEvent::ExitRequested,
+        ]
+    );
+}
@@ -0,0 +1,189 @@
+#![cfg(feature = "std")]
+
+use std::time::Duration;
+
+use bevy::log::tracing_subscriber::layer::SubscriberExt;
+use bevy::log::{tracing, tracing_subscriber};
+use bevy::prelude::*;
+use log::Level;
+use switchbrew_bevy::logging::{format_line, SwitchLogLayer, DEFAULT_LOG_FILE};
+use switchbrew_bevy::platform::MockHal;
+use switchbrew_bevy::prelude::*;
+
+fn app_with(mock: &MockHal, plugin: SwitchLogPlugin) -> App {
+    let mut app = App::new();
+    app.insert_resource(Hal::new(mock.clone()))
+        .add_plugins(SwitchPlugins::default().set(plugin));
+    app
+}
+
+#[test]
+fn formats_lines_without_std() {
+    let mut line = String::new();
+    format_line(
+        &mut line,
+        Duration::from_millis(12_345),
+        Level::Warn,
+        "crab_crossing",
+        format_args!("low memory: {} MiB", 12),
+    )
+    .unwrap();
+    assert_eq!(line, "[   12.345] WARN  crab_crossing: low memory: 12 MiB");
+}
+
+#[test]
+fn parses_levels() {
+    assert_eq!(LogLevel::parse(" Debug "), Some(LogLevel::Debug));
+    assert_eq!(LogLevel::parse("off"), Some(LogLevel::Off));
+    assert_eq!(LogLevel::parse("verbose"), None);
+
+    assert!(LogLevel::Warn.enables(Level::Error));
+    assert!(!LogLevel::Warn.enables(Level::Info));
+    assert!(!LogLevel::Off.enables(Level::Error));
+}
+
+#[test]
+fn logger_filters_and_writes_to_debug_output() {
+    let mock = MockHal::new();
+    mock.advance(Duration::from_millis(1500));
+    let logger = SwitchLogger::new(Hal::new(mock.clone()));
+
+    logger.log(Level::Info, "game", format_args!("started"));
+    logger.log(Level::Debug, "game", format_args!("hidden"));
+    logger.set_level(LogLevel::Debug);
+    logger.log(Level::Debug, "game", format_args!("shown"));
+
+    let lines = [
+        "[    1.500] INFO  game: started",
+        "[    1.500] DEBUG game: shown",
+    ];
+    assert_eq!(mock.debug_lines(), lines);
+    assert_eq!(logger.recent_lines(10), lines);
+    assert_eq!(logger.recent_lines(1), lines[1..]);
+}
+
+#[test]
+fn ring_buffer_keeps_the_newest_lines() {
+    let logger = SwitchLogger::new(Hal::new(MockHal::new()));
+    logger.set_capacity(100);
+    for i in 0..10 {
+        logger.log(Level::Info, "game", format_args!("line {i}"));
+    }
+
+    // Each line is 31 bytes with its newline
+    let lines = logger.recent_lines(10);
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].ends_with("line 7"));
+    assert!(logger.contents().ends_with("game: line 9\n"));
+}
+
+#[test]
+fn plugin_follows_config_log_level() {
+    let mock = MockHal::new();
+    let mut app = app_with(&mock, SwitchLogPlugin::default());
+    app.update();
+    let logger = app.world().resource::<SwitchLogger>().clone();
+    assert_eq!(logger.level(), LogLevel::Info);
+
+    app.world_mut().resource_mut::<SwitchConfig>().log_level = LogLevel::Error;
+    app.update();
+    assert_eq!(logger.level(), LogLevel::Error);
+    assert!(!logger.enabled(Level::Warn));
+}
+
+#[test]
+fn plugin_works_without_a_config() {
+    let mock = MockHal::new();
+    let mut app = App::new();
+    app.insert_resource(Hal::new(mock.clone()))
+        .add_plugins(SwitchLogPlugin::default().with_file("game.log"));
+    app.update();
+
+    let logger = app.world().resource::<SwitchLogger>().clone();
+    assert_eq!(logger.level(), LogLevel::Info);
+    logger.log(Level::Info, "game", format_args!("no config"));
+    app.world_mut().write_message(AppExit::Success);
+    app.update();
+    assert!(mock.file("game.log").is_some());
+}
+
+#[test]
+fn plugin_writes_log_file_every_interval() {
+    let mock = MockHal::new();
+    let mut app = app_with(&mock, SwitchLogPlugin::default().with_file("logs/game.log"));
+    let logger = app.world().resource::<SwitchLogger>().clone();
+
+    logger.log(Level::Info, "game", format_args!("first"));
+    app.update();
+    let file = String::from_utf8(mock.file("logs/game.log").unwrap()).unwrap();
+    assert_eq!(file, "[    0.000] INFO  game: first\n");
+
+    logger.log(Level::Info, "game", format_args!("second"));
+    mock.advance(Duration::from_secs(1));
+    app.update();
+    assert_eq!(mock.file("logs/game.log").unwrap(), file.as_bytes());
+
+    mock.advance(Duration::from_secs(1));
+    app.update();
+    let file = String::from_utf8(mock.file("logs/game.log").unwrap()).unwrap();
+    assert!(file.ends_with("INFO  game: second\n"));
+    assert_eq!(file.lines().count(), 2);
+}
+
+#[test]
+fn plugin_writes_log_file_on_exit() {
+    let mock = MockHal::new();
+    let mut app = app_with(&mock, SwitchLogPlugin::default().with_file("game.log"));
+    app.update();
+
+    let logger = app.world().resource::<SwitchLogger>().clone();
+    logger.log(Level::Error, "game", format_args!("giving up"));
+    app.world_mut().write_message(AppExit::Success);
+    app.update();
+
+    let file = String::from_utf8(mock.file("game.log").unwrap()).unwrap();
+    assert!(file.ends_with("ERROR game: giving up\n"));
+}
+
+#[test]
+fn plugin_writes_log_file_next_to_the_nro() {
+    let mock = MockHal::new().with_executable_path("sdmc:/switch/crab/crab.nro");
+    let app = app_with(
+        &mock,
+        SwitchLogPlugin::default().with_file(DEFAULT_LOG_FILE),
+    );
+    let logger = app.world().resource::<SwitchLogger>();
+    assert_eq!(
+        logger.file().as_deref(),
+        Some("sdmc:/switch/crab/switchbrew.log")
+    );
+
+    // Paths on a device are kept
+    let app = app_with(
+        &mock,
+        SwitchLogPlugin::default().with_file("sdmc:/logs/crab.log"),
+    );
+    let logger = app.world().resource::<SwitchLogger>();
+    assert_eq!(logger.file().as_deref(), Some("sdmc:/logs/crab.log"));
+}
+
+#[test]
+fn tracing_events_reach_the_logger() {
+    let mock = MockHal::new();
+    let logger = SwitchLogger::new(Hal::new(mock.clone()));
+    let subscriber = tracing_subscriber::registry().with(SwitchLogLayer::new(logger));
+
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info!(target: "crab_crossing", score = 3, "level cleared");
+        tracing::debug!("filtered out");
+        tracing::warn!(target: "crab_crossing::audio", "missing {}", "splash.ogg");
+    });
+
+    assert_eq!(
+        mock.debug_lines(),
+        [
+            "[    0.000] INFO  crab_crossing: level cleared score=3",
+            "[    0.000] WARN  crab_crossing::audio: missing splash.ogg",
+        ]
+    );
+}
@@ -0,0 +1,94 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+
+use bevy::prelude::*;
+use switchbrew_bevy::memory::{
+    with_tag, MemoryTag, MemoryUsage, SwitchMemoryPlugin, TrackingAllocator,
+};
+use switchbrew_bevy::platform::MockHal;
+use switchbrew_bevy::prelude::*;
+
+#[global_allocator]
+static GLOBAL: TrackingAllocator<System> = TrackingAllocator::new(System);
+
+#[test]
+fn tracks_current_and_peak() {
+    let allocator = TrackingAllocator::new(System);
+    let layout = Layout::from_size_align(1000, 64).unwrap();
+
+    unsafe {
+        let a = allocator.alloc(layout);
+        let b = with_tag(MemoryTag::ASSETS, || allocator.alloc(layout));
+        assert_eq!(a as usize % 64, 0);
+        assert_eq!(allocator.tracker().usage().current, 2000);
+        assert_eq!(allocator.tracker().usage().tagged(MemoryTag::ASSETS), 1000);
+
+        // Freed outside the tag scope, still counted against the tag
+        allocator.dealloc(b, layout);
+        let usage = allocator.tracker().usage();
+        assert_eq!(usage.current, 1000);
+        assert_eq!(usage.peak, 2000);
+        assert_eq!(usage.tagged(MemoryTag::ASSETS), 0);
+        assert_eq!(usage.tagged(MemoryTag::UNTAGGED), 1000);
+
+        let a = allocator.realloc(a, layout, 3000);
+        assert_eq!(allocator.tracker().usage().current, 3000);
+        allocator.dealloc(a, Layout::from_size_align(3000, 64).unwrap());
+    }
+    assert_eq!(allocator.tracker().usage().current, 0);
+}
+
+#[test]
+fn enforced_budget_refuses_allocations() {
+    let allocator = TrackingAllocator::new(System);
+    allocator.tracker().set_budget(1024);
+    allocator.tracker().set_enforce(true);
+    let layout = Layout::from_size_align(800, 8).unwrap();
+
+    unsafe {
+        let a = allocator.alloc(layout);
+        assert!(!a.is_null());
+        assert!(allocator.alloc(layout).is_null());
+        assert!(allocator.realloc(a, layout, 2048).is_null());
+
+        let usage = allocator.tracker().usage();
+        assert_eq!(usage.current, 800);
+        assert_eq!(usage.denied, 2);
+        allocator.dealloc(a, layout);
+    }
+}
+
+#[test]
+fn plugin_reports_usage_with_hardware_budget() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(SwitchPlugin::new(SwitchConfig::handheld()))
+        .add_plugins(SwitchMemoryPlugin::new(GLOBAL.tracker()));
+
+    let data = with_tag(MemoryTag::GAME, || vec![0u8; 1 << 20]);
+    app.update();
+
+    let usage = app.world().resource::<MemoryUsage>();
+    assert_eq!(
+        usage.budget,
+        Some(SwitchConfig::handheld().hardware().memory_budget as usize)
+    );
+    assert!(usage.tagged(MemoryTag::GAME) >= data.len());
+    assert!(usage.peak >= usage.current);
+}
+
+#[test]
+fn plugin_uses_the_memory_limit_of_the_console() {
+    static APPLET: TrackingAllocator<System> = TrackingAllocator::new(System);
+    let mock = MockHal::new()
+        .with_display_mode(DisplayMode::Handheld)
+        .with_memory_limit(400 << 20);
+    let mut app = App::new();
+    app.insert_resource(Hal::new(mock))
+        .add_plugins(MinimalPlugins)
+        .add_plugins(SwitchPlugin::default())
+        .add_plugins(SwitchMemoryPlugin::new(APPLET.tracker()));
+    app.update();
+
+    let usage = app.world().resource::<MemoryUsage>();
+    assert_eq!(usage.budget, Some(400 << 20));
+}
@@ -0,0 +1,76 @@
+//! Runs the crate built without `std`:
+//!
+//! ```bash
+//! cargo test --no-default-features --features no_std --test no_std
+//! ```
+
+use bevy::prelude::*;
+use switchbrew_bevy::memory::{with_tag, MemoryTag, TrackingAllocator};
+use switchbrew_bevy::prelude::*;
+
+#[test]
+fn plugin_tracks_display() {
+    let mut app = App::new();
+    app.add_plugins(SwitchPlugin::new(SwitchConfig::handheld()));
+    app.update();
+
+    let display = app.world().resource::<SwitchDisplay>();
+    assert_eq!(display.mode, DisplayMode::Handheld);
+    assert_eq!(display.resolution, (1280, 720));
+    assert_eq!(display.active_safe_area(), SafeArea::Full);
+}
+
+#[test]
+fn keyboard_maps_to_buttons() {
+    let mut app = App::new();
+    app.add_plugins(SwitchPlugin::default())
+        .init_resource::<ButtonInput<KeyCode>>();
+
+    app.world_mut()
+        .resource_mut::<ButtonInput<KeyCode>>()
+        .press(KeyCode::KeyX);
+    app.update();
+
+    let input = app.world().resource::<SwitchInput>();
+    assert!(input.just_pressed(SwitchButton::A));
+}
+
+#[test]
+fn config_validation_works() {
+    let config = SwitchConfig::docked().with_model(SwitchModel::Lite);
+    assert_eq!(
+        config.validate().unwrap_err().field,
+        ConfigField::DisplayMode
+    );
+}
+
+#[test]
+fn allocator_tracks_tags() {
+    use std::alloc::{GlobalAlloc, Layout, System};
+
+    let allocator = TrackingAllocator::new(System);
+    let layout = Layout::new::<[u64; 4]>();
+    unsafe {
+        let ptr = with_tag(MemoryTag::AUDIO, || allocator.alloc(layout));
+        assert_eq!(allocator.tracker().usage().tagged(MemoryTag::AUDIO), 32);
+        allocator.dealloc(ptr, layout);
+    }
+}
+
+#[test]
+fn runner_exits_on_request() {
+    struct ExitPlatform(Option<AppletMessage>);
+
+    impl RunnerPlatform for ExitPlatform {
+        fn poll_message(&mut self) -> Option<AppletMessage> {
+            self.0.take()
+        }
+    }
+
+    let mut app = App::new();
+    app.add_plugins(SwitchPlugin::default());
+    assert_eq!(
+        SwitchRunner::new(ExitPlatform(Some(AppletMessage::ExitRequested))).run(app),
+        AppExit::Success
+    );
+}
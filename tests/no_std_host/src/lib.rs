@@ -0,0 +1,41 @@
+//! Minimal `no_std` game logic built on switchbrew_bevy.
+//!
+//! Only `core` and `alloc` are available here, so this fails to build if the
+//! crate or its Bevy features pull in `std`.
+
+#![no_std]
+
+use bevy::math::ops;
+use bevy::prelude::*;
+use switchbrew_bevy::prelude::*;
+
+/// Player speed in units per update.
+pub const SPEED: f32 = 4.0;
+
+/// Player position.
+#[derive(Component, Debug, Default)]
+pub struct Player {
+    /// Current position.
+    pub position: Vec2,
+    /// Distance travelled so far.
+    pub distance: f32,
+}
+
+/// Build the game for the given config.
+pub fn game(config: SwitchConfig) -> App {
+    let mut app = App::new();
+    app.add_plugins(SwitchPlugin::new(config))
+        .init_resource::<ButtonInput<KeyCode>>()
+        .add_systems(Update, move_player);
+    app.world_mut().spawn(Player::default());
+    app
+}
+
+/// Move the player with the left stick or D-pad.
+fn move_player(input: Res<SwitchInput>, mut players: Query<&mut Player>) {
+    let step = input.movement() * SPEED;
+    for mut player in &mut players {
+        player.position += step;
+        player.distance += ops::hypot(step.x, step.y);
+    }
+}
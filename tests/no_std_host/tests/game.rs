@@ -0,0 +1,31 @@
+use bevy::prelude::*;
+use switchbrew_bevy::prelude::*;
+use switchbrew_bevy_no_std_host::{game, Player, SPEED};
+
+// The single-core critical section is only sound on one thread, so all
+// checks run in one test.
+#[test]
+fn runs_without_std() {
+    let mut app = game(SwitchConfig::handheld());
+    app.update();
+    assert_eq!(
+        app.world().resource::<SwitchDisplay>().resolution,
+        (1280, 720)
+    );
+
+    app.world_mut()
+        .resource_mut::<ButtonInput<KeyCode>>()
+        .press(KeyCode::ArrowRight);
+    app.update();
+    let start = player(&mut app).position;
+    app.update();
+
+    let player = player(&mut app);
+    assert_eq!(player.position - start, Vec2::new(SPEED, 0.0));
+    assert!(player.distance >= SPEED);
+}
+
+fn player(app: &mut App) -> &Player {
+    let mut players = app.world_mut().query::<&Player>();
+    players.single(app.world()).unwrap()
+}
@@ -0,0 +1,702 @@
+mod common;
+
+use std::path::Path;
+use std::process::Command;
+
+use common::TempDir;
+use switchbrew_bevy::nro::{
+    jpeg_size, validate_icon, Nacp, Nro, NroBuilder, NroError, NroSegments, SwitchMetadata,
+    NACP_SIZE,
+};
+use switchbrew_bevy::romfs::{RomFs, RomFsBuilder};
+
+const BUILD_ID: [u8; 20] = [
+    0xde, 0xad, 0xbe, 0xef, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16,
+];
+
+fn put(bytes: &mut Vec<u8>, offset: usize, data: &[u8]) {
+    if bytes.len() < offset + data.len() {
+        bytes.resize(offset + data.len(), 0);
+    }
+    bytes[offset..offset + data.len()].copy_from_slice(data);
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+/// An AArch64 ELF laid out like libnx's linker script: text at 0, rodata
+/// at 0x1000 with `.dynstr` and `.dynsym`, data at 0x2000 followed by bss,
+/// and a GNU build ID note.
+fn elf() -> Vec<u8> {
+    let mut elf = Vec::new();
+    put(&mut elf, 0, b"\x7fELF\x02\x01\x01");
+    put(&mut elf, 0x10, &2u16.to_le_bytes());
+    put(&mut elf, 0x12, &183u16.to_le_bytes());
+    put(&mut elf, 0x20, &0x40u64.to_le_bytes());
+    put(&mut elf, 0x28, &0x700u64.to_le_bytes());
+    put(&mut elf, 0x36, &0x38u16.to_le_bytes());
+    put(&mut elf, 0x38, &4u16.to_le_bytes());
+    put(&mut elf, 0x3a, &0x40u16.to_le_bytes());
+    put(&mut elf, 0x3c, &4u16.to_le_bytes());
+    put(&mut elf, 0x3e, &3u16.to_le_bytes());
+
+    // Type, file offset, address, file size and memory size
+    let phdrs: [(u32, u64, u64, u64, u64); 4] = [
+        (1, 0x200, 0, 0x100, 0x100),
+        (1, 0x400, 0x1000, 0x40, 0x40),
+        (1, 0x500, 0x2000, 0x20, 0x1800),
+        (4, 0x120, 0, 36, 36),
+    ];
+    for (index, (kind, offset, vaddr, file_size, mem_size)) in phdrs.into_iter().enumerate() {
+        let phdr = 0x40 + index * 0x38;
+        put(&mut elf, phdr, &kind.to_le_bytes());
+        put(&mut elf, phdr + 0x08, &offset.to_le_bytes());
+        put(&mut elf, phdr + 0x10, &vaddr.to_le_bytes());
+        put(&mut elf, phdr + 0x20, &file_size.to_le_bytes());
+        put(&mut elf, phdr + 0x28, &mem_size.to_le_bytes());
+    }
+
+    // Build ID note: name size, descriptor size, NT_GNU_BUILD_ID, name
+    put(&mut elf, 0x120, &4u32.to_le_bytes());
+    put(&mut elf, 0x124, &20u32.to_le_bytes());
+    put(&mut elf, 0x128, &3u32.to_le_bytes());
+    put(&mut elf, 0x12c, b"GNU\0");
+    put(&mut elf, 0x130, &BUILD_ID);
+
+    // crt0: branch past the header, MOD0 offset, then the header placeholder
+    put(&mut elf, 0x200, &0x1400_0020u32.to_le_bytes());
+    put(&mut elf, 0x204, &0x80u32.to_le_bytes());
+    put(&mut elf, 0x208, b"HOMEBREW");
+    put(&mut elf, 0x280, &[0xaa; 0x80]);
+    put(&mut elf, 0x400, &[0xbb; 0x40]);
+    put(&mut elf, 0x500, &[0xcc; 0x20]);
+
+    put(&mut elf, 0x600, b"\0.dynstr\0.dynsym\0.shstrtab\0");
+    // Name, address, offset and size of each section after the null one
+    let shdrs: [(u32, u64, u64, u64); 3] = [
+        (1, 0x1010, 0x410, 0x10),
+        (9, 0x1020, 0x420, 0x18),
+        (17, 0, 0x600, 27),
+    ];
+    for (index, (name, addr, offset, size)) in shdrs.into_iter().enumerate() {
+        let shdr = 0x740 + index * 0x40;
+        put(&mut elf, shdr, &name.to_le_bytes());
+        put(&mut elf, shdr + 0x10, &addr.to_le_bytes());
+        put(&mut elf, shdr + 0x18, &offset.to_le_bytes());
+        put(&mut elf, shdr + 0x20, &size.to_le_bytes());
+    }
+    elf.resize(0x800, 0);
+    elf
+}
+
+/// Headers of a baseline JPEG of the given size, enough to validate.
+fn jpeg(width: u16, height: u16) -> Vec<u8> {
+    let mut jpeg = vec![0xff, 0xd8];
+    // APP0 JFIF
+    jpeg.extend_from_slice(&[0xff, 0xe0, 0, 16]);
+    jpeg.extend_from_slice(b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0");
+    // SOF0 with three components
+    jpeg.extend_from_slice(&[0xff, 0xc0, 0, 17, 8]);
+    jpeg.extend_from_slice(&height.to_be_bytes());
+    jpeg.extend_from_slice(&width.to_be_bytes());
+    jpeg.extend_from_slice(&[3, 1, 0x22, 0, 2, 0x11, 1, 3, 0x11, 1]);
+    jpeg.extend_from_slice(&[0xff, 0xd9]);
+    jpeg
+}
+
+#[test]
+fn reads_segments_from_elf() {
+    let segments = NroSegments::from_elf(&elf()).unwrap();
+    assert_eq!(segments.text.len(), 0x100);
+    assert_eq!(segments.ro, [0xbb; 0x40]);
+    assert_eq!(segments.data, [0xcc; 0x20]);
+    assert_eq!(segments.bss_size, 0x17e0);
+    assert_eq!(segments.build_id, BUILD_ID);
+    assert_eq!(segments.api_info, (0, 0));
+    assert_eq!(segments.dynstr, (0x10, 0x10));
+    assert_eq!(segments.dynsym, (0x20, 0x18));
+}
+
+#[test]
+fn rejects_elves_not_laid_out_for_nro() {
+    assert!(matches!(
+        NroSegments::from_elf(b"MZ not an elf"),
+        Err(NroError::InvalidElf(_))
+    ));
+
+    // x86-64 instead of AArch64
+    let mut elf = elf();
+    put(&mut elf, 0x12, &62u16.to_le_bytes());
+    assert!(matches!(
+        NroSegments::from_elf(&elf),
+        Err(NroError::InvalidElf(_))
+    ));
+
+    // Rodata not right after the text
+    let mut elf = self::elf();
+    put(&mut elf, 0x40 + 0x38 + 0x10, &0x4000u64.to_le_bytes());
+    assert_eq!(
+        NroSegments::from_elf(&elf).unwrap_err(),
+        NroError::InvalidElf("rodata segment at 0x4000, expected 0x1000".into())
+    );
+
+    // Only two loadable segments
+    let mut elf = self::elf();
+    put(&mut elf, 0x40 + 2 * 0x38, &6u32.to_le_bytes());
+    assert_eq!(
+        NroSegments::from_elf(&elf).unwrap_err(),
+        NroError::InvalidElf("expected 3 PT_LOAD segments, found 2".into())
+    );
+}
+
+#[test]
+fn rejects_out_of_range_elf_offsets() {
+    // Program headers past the end of the address space
+    let mut elf = elf();
+    put(&mut elf, 0x20, &u64::MAX.to_le_bytes());
+    assert!(matches!(
+        NroSegments::from_elf(&elf),
+        Err(NroError::InvalidElf(_))
+    ));
+
+    // Text segment ending past the end of the address space
+    let mut elf = self::elf();
+    put(&mut elf, 0x40 + 0x20, &u64::MAX.to_le_bytes());
+    assert_eq!(
+        NroSegments::from_elf(&elf).unwrap_err(),
+        NroError::InvalidElf("segment end out of range".into())
+    );
+
+    // Section headers past the end of the address space
+    let mut elf = self::elf();
+    put(&mut elf, 0x28, &(u64::MAX - 0x10).to_le_bytes());
+    assert_eq!(
+        NroSegments::from_elf(&elf).unwrap_err(),
+        NroError::InvalidElf("header offset out of range".into())
+    );
+
+    // Section names past the end of the address space are not found
+    let mut elf = self::elf();
+    put(&mut elf, 0x700 + 3 * 0x40 + 0x18, &u64::MAX.to_le_bytes());
+    let segments = NroSegments::from_elf(&elf).unwrap();
+    assert_eq!(segments.dynstr, (0, 0));
+}
+
+#[test]
+fn matches_nro_header_layout() {
+    let nro = NroBuilder::new(NroSegments::from_elf(&elf()).unwrap()).build();
+    assert_eq!(nro.len(), 0x3000);
+
+    // crt0 start is kept: branch, MOD0 offset and magic
+    assert_eq!(u32_at(&nro, 0), 0x1400_0020);
+    assert_eq!(u32_at(&nro, 4), 0x80);
+    assert_eq!(&nro[8..0x10], b"HOMEBREW");
+
+    assert_eq!(&nro[0x10..0x14], b"NRO0");
+    // Version, size, flags, then offset and size of text, rodata and data
+    let fields: Vec<_> = (0..11)
+        .map(|index| u32_at(&nro, 0x14 + index * 4))
+        .collect();
+    assert_eq!(
+        fields,
+        [0, 0x3000, 0, 0, 0x1000, 0x1000, 0x1000, 0x2000, 0x1000, 0x2000, 0]
+    );
+    // Build ID, zero padded to 32 bytes
+    assert_eq!(&nro[0x40..0x54], BUILD_ID);
+    assert_eq!(&nro[0x54..0x60], [0; 12]);
+    // DSO handle, reserved, then api_info, dynstr and dynsym
+    let fields: Vec<_> = (0..8).map(|index| u32_at(&nro, 0x60 + index * 4)).collect();
+    assert_eq!(fields, [0, 0, 0, 0, 0x10, 0x10, 0x20, 0x18]);
+
+    assert_eq!(&nro[0x80..0x100], [0xaa; 0x80]);
+    assert_eq!(&nro[0x1000..0x1040], [0xbb; 0x40]);
+    assert_eq!(&nro[0x2000..0x2020], [0xcc; 0x20]);
+    assert!(nro[0x2020..].iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn matches_asset_section_layout() {
+    let icon = jpeg(256, 256);
+    let mut romfs = RomFsBuilder::new();
+    romfs.add_file("title.txt", b"Crab".to_vec()).unwrap();
+    let romfs = romfs.build();
+
+    let nro = NroBuilder::new(NroSegments::from_elf(&elf()).unwrap())
+        .with_icon(icon.clone())
+        .unwrap()
+        .with_nacp(&Nacp::new("Crab Crossing", "Ibrahim Cesar", "0.2.0"))
+        .unwrap()
+        .with_romfs(romfs.clone())
+        .build();
+
+    // Assets follow the data segment, offsets relative to the section
+    let assets = &nro[0x3000..];
+    assert_eq!(&assets[..4], b"ASET");
+    assert_eq!(u32_at(assets, 4), 0);
+    let icon_offset = 0x38;
+    let nacp_offset = icon_offset + icon.len();
+    let romfs_offset = nacp_offset + NACP_SIZE;
+    let fields: Vec<_> = (0..6).map(|index| u64_at(assets, 8 + index * 8)).collect();
+    assert_eq!(
+        fields,
+        [
+            icon_offset as u64,
+            icon.len() as u64,
+            nacp_offset as u64,
+            NACP_SIZE as u64,
+            romfs_offset as u64,
+            romfs.len() as u64,
+        ]
+    );
+    assert_eq!(&assets[icon_offset..nacp_offset], icon);
+    assert_eq!(&assets[nacp_offset..nacp_offset + 13], b"Crab Crossing");
+    assert_eq!(assets.len(), romfs_offset + romfs.len());
+
+    let packed = RomFs::new(assets[romfs_offset..].to_vec()).unwrap();
+    assert_eq!(packed.file("title.txt"), Some(&b"Crab"[..]));
+}
+
+#[test]
+fn matches_nacp_layout() {
+    let nacp = Nacp::new("Crab Crossing", "Ibrahim Cesar", "0.2.0")
+        .with_title_id(0x0100_0000_0000_1000)
+        .to_bytes()
+        .unwrap();
+    assert_eq!(nacp.len(), 0x4000);
+
+    // The same title and author in each of the 16 languages
+    for language in 0..16 {
+        let entry = &nacp[language * 0x300..(language + 1) * 0x300];
+        assert_eq!(&entry[..14], b"Crab Crossing\0");
+        assert!(entry[14..0x200].iter().all(|&byte| byte == 0));
+        assert_eq!(&entry[0x200..0x20e], b"Ibrahim Cesar\0");
+    }
+    assert_eq!(&nacp[0x3060..0x3070], b"0.2.0\0\0\0\0\0\0\0\0\0\0\0");
+    assert_eq!(&nacp[0x3040..0x3060], [0xff; 0x20]);
+    assert_eq!(u64_at(&nacp, 0x3038), 0x0100_0000_0000_1000);
+    assert_eq!(u64_at(&nacp, 0x3070), 0x0100_0000_0000_2000);
+    assert_eq!(u64_at(&nacp, 0x3078), 0x0100_0000_0000_1000);
+    assert_eq!(u64_at(&nacp, 0x30b0), 0x0100_0000_0000_1000);
+
+    // Without a title ID the derived IDs stay zero
+    let nacp = Nacp::new("Crab", "Crab", "1").to_bytes().unwrap();
+    assert_eq!(u64_at(&nacp, 0x3078), 0);
+}
+
+#[test]
+fn parses_packaged_nro() {
+    let nacp =
+        Nacp::new("Crab Crossing", "Ibrahim Cesar", "0.2.0").with_title_id(0x0100_0000_0000_1000);
+    let mut romfs = RomFsBuilder::new();
+    romfs
+        .add_file("sprites/crab.txt", b"crab".to_vec())
+        .unwrap();
+    let nro = NroBuilder::new(NroSegments::from_elf(&elf()).unwrap())
+        .with_icon(jpeg(256, 256))
+        .unwrap()
+        .with_nacp(&nacp)
+        .unwrap()
+        .with_romfs(romfs.build())
+        .build();
+
+    let parsed = Nro::parse(&nro).unwrap();
+    let header = &parsed.header;
+    assert_eq!(header.size, 0x3000);
+    assert_eq!(
+        [header.text, header.ro, header.data],
+        [(0, 0x1000), (0x1000, 0x1000), (0x2000, 0x1000)]
+    );
+    assert_eq!(header.bss_size, 0x2000);
+    assert_eq!(&header.build_id[..20], BUILD_ID);
+    assert_eq!(
+        [header.api_info, header.dynstr, header.dynsym],
+        [(0, 0), (0x10, 0x10), (0x20, 0x18)]
+    );
+    assert_eq!(parsed.icon, Some(jpeg(256, 256)));
+    assert_eq!(parsed.nacp, Some(nacp));
+    let romfs = parsed.romfs.unwrap();
+    assert_eq!(romfs.file("sprites/crab.txt"), Some(&b"crab"[..]));
+
+    // Assets are optional
+    let nro = NroBuilder::new(NroSegments::from_elf(&elf()).unwrap())
+        .with_romfs(RomFsBuilder::new().build())
+        .build();
+    let parsed = Nro::parse(&nro).unwrap();
+    assert!(parsed.icon.is_none() && parsed.nacp.is_none() && parsed.romfs.is_some());
+    let nro = NroBuilder::new(NroSegments::from_elf(&elf()).unwrap()).build();
+    assert!(Nro::parse(&nro).unwrap().romfs.is_none());
+}
+
+#[test]
+fn rejects_invalid_nros() {
+    let nro = NroBuilder::new(NroSegments::from_elf(&elf()).unwrap())
+        .with_nacp(&Nacp::new("Crab", "Crab", "1"))
+        .unwrap()
+        .build();
+    assert!(Nro::parse(&nro).is_ok());
+
+    assert_eq!(
+        Nro::parse(&elf()).unwrap_err(),
+        NroError::InvalidNro("missing NRO0 magic".into())
+    );
+    assert_eq!(
+        Nro::parse(&nro[..0x2000]).unwrap_err(),
+        NroError::InvalidNro("8192 bytes, header says 12288".into())
+    );
+    assert_eq!(
+        Nro::parse(&nro[..0x3010]).unwrap_err(),
+        NroError::InvalidNro("invalid asset section header".into())
+    );
+    assert_eq!(
+        Nro::parse(&nro[..nro.len() - 1]).unwrap_err(),
+        NroError::InvalidNro("asset out of bounds".into())
+    );
+
+    // A NACP entry too small to hold a NACP
+    let mut short = nro.clone();
+    put(&mut short, 0x3000 + 0x20, &0x100u64.to_le_bytes());
+    assert!(matches!(Nro::parse(&short), Err(NroError::InvalidNacp(_))));
+}
+
+#[test]
+fn nacp_round_trips() {
+    let nacp = Nacp::new("Crab Crossing", "Ibrahim Cesar", "0.2.0");
+    assert_eq!(Nacp::parse(&nacp.to_bytes().unwrap()), Ok(nacp.clone()));
+
+    let nacp = nacp.with_title_id(0x0100_0000_0000_1000);
+    assert_eq!(Nacp::parse(&nacp.to_bytes().unwrap()), Ok(nacp));
+
+    // Title and author come from the first language that has them
+    let mut bytes = Nacp::new("Crab", "Crab", "1").to_bytes().unwrap();
+    bytes[..0x300].fill(0);
+    assert_eq!(Nacp::parse(&bytes).unwrap().title, "Crab");
+    assert!(Nacp::parse(&bytes[..0x3000]).is_err());
+}
+
+#[test]
+fn rejects_nacp_fields_that_do_not_fit() {
+    let long = "v".repeat(16);
+    assert_eq!(
+        Nacp::new("Crab", "Crab", long).to_bytes().unwrap_err(),
+        NroError::InvalidNacp("version is 16 bytes, at most 15 fit".into())
+    );
+    assert_eq!(
+        Nacp::new("", "Crab", "1").to_bytes().unwrap_err(),
+        NroError::InvalidNacp("title is empty".into())
+    );
+    assert!(Nacp::new("Crab", "a".repeat(255), "1").to_bytes().is_ok());
+    assert!(Nacp::new("Crab", "a".repeat(256), "1").to_bytes().is_err());
+}
+
+#[test]
+fn validates_icon_size_and_format() {
+    assert_eq!(validate_icon(&jpeg(256, 256)), Ok(()));
+    assert_eq!(jpeg_size(&jpeg(64, 32)), Some((64, 32)));
+    assert_eq!(
+        validate_icon(&jpeg(512, 256)).unwrap_err(),
+        NroError::InvalidIcon("512×256 pixels, expected 256×256".into())
+    );
+    assert_eq!(
+        validate_icon(b"\x89PNG\r\n\x1a\n").unwrap_err(),
+        NroError::InvalidIcon("not a JPEG image".into())
+    );
+    // Truncated before the frame header
+    assert!(validate_icon(&jpeg(256, 256)[..22]).is_err());
+}
+
+#[test]
+fn reads_metadata_from_cargo_toml() {
+    let manifest = r#"
+        [package]
+        name = "crab_crossing"
+        version = "0.3.1"
+        authors = ["Ibrahim Cesar <ibrahim@example.com>", "Crab"]
+
+        [package.metadata.switch]
+        icon = "assets/icon.jpg"
+        romfs = "assets"
+        title_id = "0x0100000000001000"
+    "#;
+    let metadata = SwitchMetadata::from_cargo_toml(manifest, Path::new("game")).unwrap();
+    assert_eq!(
+        metadata.nacp,
+        Nacp::new("crab_crossing", "Ibrahim Cesar, Crab", "0.3.1")
+            .with_title_id(0x0100_0000_0000_1000)
+    );
+    assert_eq!(
+        metadata.icon,
+        Some(Path::new("game/assets/icon.jpg").into())
+    );
+    assert_eq!(metadata.romfs, Some(Path::new("game/assets").into()));
+
+    // Switch fields override the package, and workspace versions need one
+    let manifest = r#"
+        [package]
+        name = "crab_crossing"
+        version.workspace = true
+
+        [package.metadata.switch]
+        title = "Crab Crossing"
+        author = "Ibrahim Cesar"
+        version = "1.0"
+    "#;
+    let metadata = SwitchMetadata::from_cargo_toml(manifest, Path::new("")).unwrap();
+    assert_eq!(
+        metadata.nacp,
+        Nacp::new("Crab Crossing", "Ibrahim Cesar", "1.0")
+    );
+    assert_eq!((metadata.icon, metadata.romfs), (None, None));
+
+    for manifest in [
+        "[package]\nname = \"a\"\nversion.workspace = true\nauthors = [\"a\"]",
+        "[package]\nname = \"a\"\nversion = \"1\"",
+        "[package]\nname = \"a\"\nversion = \"1\"\nauthors = [\"a\"]\n[package.metadata.switch]\ntitle_id = \"1000\"",
+        "[package]\nname = \"a\"\nversion = \"1\"\nauthors = [\"a\"]\n[package.metadata.switch]\nname = \"b\"",
+        "[workspace]",
+    ] {
+        assert!(matches!(
+            SwitchMetadata::from_cargo_toml(manifest, Path::new("")),
+            Err(NroError::InvalidMetadata(_))
+        ));
+    }
+}
+
+#[test]
+fn resolves_metadata_inherited_from_workspace() {
+    let dir = TempDir::new("nro-workspace");
+    std::fs::create_dir_all(dir.join("crates/game")).unwrap();
+    std::fs::write(
+        dir.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"crates/game\"]\n\n[workspace.package]\nversion = \"0.4.0\"\nauthors = [\"Ibrahim Cesar <ibrahim@example.com>\"]",
+    )
+    .unwrap();
+    let manifest = r#"
+        [package]
+        name = "crab_crossing"
+        version.workspace = true
+        authors.workspace = true
+    "#;
+    let metadata = SwitchMetadata::from_cargo_toml(manifest, &dir.join("crates/game")).unwrap();
+    assert_eq!(
+        metadata.nacp,
+        Nacp::new("crab_crossing", "Ibrahim Cesar", "0.4.0")
+    );
+
+    // A package can also be its own workspace root
+    let root =
+        format!("{manifest}\n[workspace.package]\nversion = \"0.5.0\"\nauthors = [\"Crab\"]");
+    let metadata = SwitchMetadata::from_cargo_toml(&root, &dir).unwrap();
+    assert_eq!(metadata.nacp, Nacp::new("crab_crossing", "Crab", "0.5.0"));
+
+    // The workspace root must set the inherited value
+    std::fs::write(
+        dir.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"crates/game\"]",
+    )
+    .unwrap();
+    let error = SwitchMetadata::from_cargo_toml(manifest, &dir.join("crates/game")).unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("has no [workspace.package] authors"),
+        "{error}"
+    );
+
+    // Without a workspace root, [package.metadata.switch] must set it
+    let error = SwitchMetadata::from_cargo_toml(manifest, Path::new("")).unwrap_err();
+    assert!(
+        error.to_string().contains("no workspace root was found"),
+        "{error}"
+    );
+}
+
+#[test]
+fn cli_packages_elf_into_nro() {
+    let dir = TempDir::new("nro-cli");
+    std::fs::create_dir_all(dir.join("assets/sprites")).unwrap();
+    std::fs::write(dir.join("assets/sprites/crab.txt"), "crab").unwrap();
+    std::fs::write(dir.join("icon.jpg"), jpeg(256, 256)).unwrap();
+    std::fs::write(dir.join("game.elf"), elf()).unwrap();
+    std::fs::write(
+        dir.join("Cargo.toml"),
+        r#"
+        [package]
+        name = "crab_crossing"
+        version = "0.2.0"
+        authors = ["Ibrahim Cesar"]
+
+        [package.metadata.switch]
+        title = "Crab Crossing"
+        icon = "icon.jpg"
+        romfs = "assets"
+        "#,
+    )
+    .unwrap();
+    let nro = dir.join("out/game.nro");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_cargo-switchbrew"))
+        .args(["switchbrew", "package"])
+        .arg(dir.join("game.elf"))
+        .arg(&nro)
+        .arg(dir.join("Cargo.toml"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Packaged Crab Crossing 0.2.0"));
+
+    let nro = std::fs::read(&nro).unwrap();
+    assert_eq!(&nro[0x10..0x14], b"NRO0");
+    let assets = &nro[0x3000..];
+    assert_eq!(&assets[..4], b"ASET");
+    let romfs_offset = u64_at(assets, 0x28) as usize;
+    let romfs = RomFs::new(assets[romfs_offset..].to_vec()).unwrap();
+    assert_eq!(romfs.paths(), ["sprites/crab.txt"]);
+
+    // Inspecting shows the metadata and RomFS tree, and saves the icon
+    let output = Command::new(env!("CARGO_BIN_EXE_cargo-switchbrew"))
+        .args(["switchbrew", "inspect"])
+        .arg(dir.join("out/game.nro"))
+        .arg(dir.join("out/icon.jpg"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for line in [
+        "Title:    Crab Crossing",
+        "Author:   Ibrahim Cesar",
+        "Version:  0.2.0",
+        "Build ID: deadbeef0102030405060708090a0b0c0d0e0f10",
+        "Icon:     256×256 JPEG",
+        "RomFS:    1 files",
+        "    sprites/",
+        "      crab.txt (4 bytes)",
+    ] {
+        assert!(stdout.contains(line), "{line:?} not in:\n{stdout}");
+    }
+    assert_eq!(
+        std::fs::read(dir.join("out/icon.jpg")).unwrap(),
+        jpeg(256, 256)
+    );
+
+    // A wrongly sized icon is refused
+    std::fs::write(dir.join("icon.jpg"), jpeg(128, 128)).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_cargo-switchbrew"))
+        .args(["package"])
+        .arg(dir.join("game.elf"))
+        .arg(dir.join("out/bad.nro"))
+        .arg(dir.join("Cargo.toml"))
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("128×128 pixels"));
+}
+
+/// Write a crate with an ELF and an emulator table, returning its directory.
+fn emulator_crate(name: &str, emulator: &str) -> TempDir {
+    let dir = TempDir::new(&format!("nro-{name}"));
+    std::fs::write(dir.join("game.elf"), elf()).unwrap();
+    let manifest = format!(
+        "[package]\nname = \"crab_crossing\"\nversion = \"0.2.0\"\nauthors = [\"Ibrahim Cesar\"]\n{emulator}"
+    );
+    std::fs::write(dir.join("Cargo.toml"), manifest).unwrap();
+    dir
+}
+
+fn cargo_switchbrew_run(dir: &Path, extra: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_cargo-switchbrew"))
+        .args(["switchbrew", "run"])
+        .arg(dir.join("game.elf"))
+        .arg(dir.join("Cargo.toml"))
+        .args(extra)
+        .env_remove("SWITCHBREW_EMULATOR")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn cli_run_dry_run_prints_emulator_command() {
+    let dir = emulator_crate(
+        "dry-run",
+        "[package.metadata.switch.emulator]\npath = \"/opt/ryubing/Ryujinx\"\nargs = [\"--fullscreen\"]",
+    );
+
+    let output = cargo_switchbrew_run(&dir, &["--dry-run"]);
+    assert!(output.status.success(), "{output:?}");
+    let nro = dir.join("game.nro");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.ends_with(&format!(
+            "/opt/ryubing/Ryujinx --fullscreen {}\n",
+            nro.display()
+        )),
+        "{stdout}"
+    );
+    assert_eq!(&std::fs::read(&nro).unwrap()[0x10..0x14], b"NRO0");
+
+    // The environment variable picks another emulator
+    let output = Command::new(env!("CARGO_BIN_EXE_cargo-switchbrew"))
+        .args(["run", "--dry-run"])
+        .arg(dir.join("game.elf"))
+        .arg(dir.join("Cargo.toml"))
+        .env("SWITCHBREW_EMULATOR", "/usr/bin/citron")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.ends_with(&format!(
+            "/usr/bin/citron --fullscreen -g {}\n",
+            nro.display()
+        )),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn cli_run_needs_an_emulator() {
+    let dir = emulator_crate("no-emulator", "");
+    let output = cargo_switchbrew_run(&dir, &["--dry-run"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no emulator set"));
+}
+
+#[cfg(unix)]
+#[test]
+fn cli_run_forwards_emulator_output_and_log() {
+    let dir = emulator_crate("run", "");
+    let log = dir.join("emulator.log");
+    std::fs::write(&log, "old line\n").unwrap();
+    // A stand-in emulator printing to stdout, stderr and its log file
+    let emulator = format!(
+        "[package.metadata.switch.emulator]\npath = \"/bin/sh\"\nargs = [\"-c\", \"echo launched {{nro}}; echo warning >&2; echo from log >> {log}\"]\nlog = \"{log}\"\n",
+        log = log.display()
+    );
+    let mut manifest = std::fs::read_to_string(dir.join("Cargo.toml")).unwrap();
+    manifest.push_str(&emulator);
+    std::fs::write(dir.join("Cargo.toml"), manifest).unwrap();
+
+    let output = cargo_switchbrew_run(&dir, &[]);
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!("launched {}", dir.join("game.nro").display())));
+    assert!(stdout.contains("from log"), "{stdout}");
+    assert!(!stdout.contains("old line"), "{stdout}");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("warning"));
+}
+
+#[cfg(unix)]
+#[test]
+fn cli_run_reports_emulator_failure() {
+    let dir = emulator_crate(
+        "run-fail",
+        "[package.metadata.switch.emulator]\npath = \"/bin/sh\"\nargs = [\"-c\", \"exit 3\"]",
+    );
+    let output = cargo_switchbrew_run(&dir, &[]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("emulator exited with"));
+}
@@ -0,0 +1,106 @@
+#![cfg(feature = "std")]
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use switchbrew_bevy::platform::{ClockProfile, MockHal};
+use switchbrew_bevy::prelude::*;
+
+#[derive(Resource, Default)]
+struct Received(Vec<PerformanceModeChanged>);
+
+fn record(mut messages: MessageReader<PerformanceModeChanged>, mut received: ResMut<Received>) {
+    received.0.extend(messages.read().copied());
+}
+
+#[test]
+fn budgets_depend_on_display_mode() {
+    let docked = PerformanceMode::Normal.budget(DisplayMode::Docked, 60);
+    let handheld = PerformanceMode::Normal.budget(DisplayMode::Handheld, 30);
+
+    assert_eq!(docked.clocks, ClockProfile::DOCKED);
+    assert_eq!(docked.slowdown(), 1.0);
+    assert_eq!(handheld.clocks, ClockProfile::HANDHELD);
+    assert_eq!(handheld.slowdown(), 2.0);
+    assert_eq!(handheld.frame_time.as_micros(), 33_333);
+
+    // Boost mode is CPU-bound, so the faster CPU wins over the slower GPU
+    let boost = PerformanceMode::Boost.budget(DisplayMode::Handheld, 60);
+    assert!(boost.slowdown() < 1.0);
+
+    assert_eq!(
+        PerformanceMode::BatterySaver.clocks(DisplayMode::Docked),
+        ClockProfile::DOCKED
+    );
+    assert_eq!(
+        PerformanceMode::Boost.clocks(DisplayMode::Tabletop),
+        ClockProfile::BOOST
+    );
+}
+
+#[test]
+fn sends_message_on_change() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(SwitchPlugin::new(SwitchConfig::docked()))
+        .init_resource::<Received>()
+        .add_systems(Update, record);
+    app.update();
+    assert!(app.world().resource::<Received>().0.is_empty());
+
+    *app.world_mut().resource_mut::<PerformanceMode>() = PerformanceMode::Boost;
+    app.update();
+    *app.world_mut().resource_mut::<SwitchConfig>() = SwitchConfig::handheld();
+    app.update();
+
+    let received = &app.world().resource::<Received>().0;
+    assert_eq!(received.len(), 2);
+    assert_eq!(received[0].previous, PerformanceMode::Normal);
+    assert_eq!(received[0].mode, PerformanceMode::Boost);
+    assert_eq!(received[1].display_mode, DisplayMode::Handheld);
+    assert_eq!(received[1].clocks, ClockProfile::BOOST);
+}
+
+#[test]
+fn frame_limiter_sleeps_on_hal_clock() {
+    let mock = MockHal::new();
+    let mut app = App::new();
+    app.insert_resource(Hal::new(mock.clone()))
+        .add_plugins(SwitchPlugin::new(SwitchConfig::docked()))
+        .add_plugins(SwitchFrameLimiterPlugin);
+    app.update();
+    app.update();
+    app.update();
+
+    let frame_time = PerformanceMode::Normal
+        .budget(DisplayMode::Docked, 60)
+        .frame_time;
+    assert_eq!(mock.now(), frame_time * 2);
+
+    // Handheld GPU clocks take twice as long for the same work
+    *app.world_mut().resource_mut::<SwitchConfig>() = SwitchConfig::handheld();
+    app.update();
+    let start = mock.now();
+    mock.advance(Duration::from_millis(20));
+    app.update();
+    let frame = mock.now() - start;
+    assert!(frame.abs_diff(Duration::from_millis(40)) < Duration::from_micros(1));
+}
+
+#[test]
+fn frame_limiter_waits_for_a_config() {
+    let mock = MockHal::new();
+    let mut app = App::new();
+    app.insert_resource(Hal::new(mock.clone()))
+        .add_plugins(SwitchFrameLimiterPlugin);
+    app.update();
+    app.update();
+    assert_eq!(mock.now(), Duration::ZERO);
+
+    app.insert_resource(SwitchConfig::docked());
+    app.update();
+    let frame_time = PerformanceMode::Normal
+        .budget(DisplayMode::Docked, 60)
+        .frame_time;
+    assert_eq!(mock.now(), frame_time);
+}
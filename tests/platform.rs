@@ -0,0 +1,128 @@
+#![cfg(feature = "std")]
+
+use switchbrew_bevy::platform::{
+    ConfigField, DisplayMode, HardwareProfile, HostOs, LaunchMode, Platform, PlatformInfo,
+    SwitchConfig, SwitchModel,
+};
+
+#[test]
+fn no_override_detects_host() {
+    let info = PlatformInfo::detect_with(None);
+
+    assert_eq!(info.os, HostOs::current());
+    assert_eq!(info.platform, Platform::current());
+    assert!(!info.simulated);
+    assert!(!info.emulator);
+}
+
+#[test]
+fn override_simulates_handheld() {
+    let info = PlatformInfo::detect_with(Some("handheld"));
+
+    assert_eq!(info.platform, Platform::SwitchHandheld);
+    assert_eq!(info.display_mode, DisplayMode::Handheld);
+    assert_eq!(info.os, HostOs::current());
+    assert!(info.simulated);
+}
+
+#[test]
+fn override_accepts_prefix_and_case() {
+    let info = PlatformInfo::detect_with(Some(" Switch-Docked "));
+    assert_eq!(info.platform, Platform::SwitchDocked);
+    assert_eq!(info.display_mode, DisplayMode::Docked);
+
+    let info = PlatformInfo::detect_with(Some("switch_tabletop"));
+    assert_eq!(info.platform, Platform::SwitchHandheld);
+    assert_eq!(info.display_mode, DisplayMode::Tabletop);
+
+    let info = PlatformInfo::detect_with(Some(" handheld"));
+    assert_eq!(info.platform, Platform::SwitchHandheld);
+    assert_eq!(info.display_mode, DisplayMode::Handheld);
+
+    let info = PlatformInfo::detect_with(Some("\tTabletop "));
+    assert_eq!(info.display_mode, DisplayMode::Tabletop);
+}
+
+#[test]
+fn override_can_force_desktop() {
+    let info = PlatformInfo::detect_with(Some("desktop"));
+
+    assert_eq!(info.platform, Platform::Desktop);
+    assert!(info.simulated);
+}
+
+#[test]
+fn invalid_override_is_ignored() {
+    assert_eq!(
+        PlatformInfo::detect_with(Some("gamecube")),
+        PlatformInfo::detect_with(None)
+    );
+    assert_eq!(
+        PlatformInfo::detect_with(Some("")),
+        PlatformInfo::detect_with(None)
+    );
+}
+
+#[test]
+fn lite_is_handheld_only() {
+    let lite = SwitchModel::Lite.profile();
+    assert!(!lite.supports(DisplayMode::Docked));
+    assert!(!lite.supports(DisplayMode::Tabletop));
+    assert!(lite.supports(DisplayMode::Handheld));
+    assert!(!lite.detachable_joycons);
+
+    let oled = SwitchModel::Oled.profile();
+    assert!(oled.supports(DisplayMode::Docked));
+    assert_eq!(oled.screen_inches, 7.0);
+}
+
+#[test]
+fn applets_get_less_memory() {
+    let config = SwitchConfig::handheld().with_model(SwitchModel::Oled);
+    assert_eq!(config.launch_mode, LaunchMode::Application);
+    assert_eq!(
+        config.hardware().memory_budget,
+        HardwareProfile::APPLICATION_MEMORY
+    );
+
+    let config = config.with_launch_mode(LaunchMode::Applet);
+    assert_eq!(
+        config.hardware().memory_budget,
+        HardwareProfile::APPLET_MEMORY
+    );
+    assert_eq!(config.hardware().screen_inches, 7.0);
+}
+
+#[test]
+fn config_rejects_unsupported_display_mode() {
+    let error = SwitchConfig::docked()
+        .with_model(SwitchModel::Lite)
+        .validate()
+        .unwrap_err();
+    assert_eq!(error.field, ConfigField::DisplayMode);
+    assert!(error.to_string().contains("Switch Lite"));
+
+    assert!(SwitchConfig::handheld()
+        .with_model(SwitchModel::Lite)
+        .validate()
+        .is_ok());
+}
+
+#[test]
+fn lite_defaults_to_handheld() {
+    let info = PlatformInfo {
+        display_mode: DisplayMode::Docked,
+        model: SwitchModel::Lite,
+        ..PlatformInfo::detect_with(Some("docked"))
+    };
+    let config = SwitchConfig::for_platform(&info);
+    assert_eq!(config.display_mode, DisplayMode::Handheld);
+    assert_eq!(config.resolution, (1280, 720));
+    assert!(config.validate().is_ok());
+
+    let config = SwitchConfig::docked()
+        .with_model(SwitchModel::Lite)
+        .with_supported_display_mode();
+    assert_eq!(config.display_mode, DisplayMode::Handheld);
+    assert_eq!(config.platform, Platform::SwitchHandheld);
+}
@@ -0,0 +1,55 @@
+#![cfg(feature = "std")]
+
+use bevy::prelude::*;
+use switchbrew_bevy::prelude::*;
+
+#[test]
+fn inserts_default_config() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(SwitchPlugin::default());
+
+    let config = app.world().resource::<SwitchConfig>();
+    assert_eq!(config.target_fps, 60);
+}
+
+#[test]
+fn uses_config_passed_to_plugin() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(SwitchPlugin::new(SwitchConfig::handheld().with_fps(30)));
+    app.update();
+
+    assert_eq!(app.world().resource::<SwitchConfig>().target_fps, 30);
+    assert_eq!(
+        app.world().resource::<SwitchDisplay>().resolution,
+        (1280, 720)
+    );
+}
+
+#[test]
+fn respects_existing_config() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .insert_resource(SwitchConfig::handheld().with_fps(30))
+        .add_plugins(SwitchPlugin::new(SwitchConfig::docked()));
+
+    let config = app.world().resource::<SwitchConfig>();
+    assert_eq!(config.display_mode, DisplayMode::Handheld);
+    assert_eq!(config.target_fps, 30);
+}
+
+#[test]
+fn plugin_group_parts_can_be_disabled() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins).add_plugins(
+        SwitchPlugins::default()
+            .build()
+            .disable::<SwitchInputPlugin>(),
+    );
+    app.update();
+
+    assert!(app.world().contains_resource::<SwitchConfig>());
+    assert!(app.world().contains_resource::<SwitchDisplay>());
+    assert!(!app.world().contains_resource::<SwitchInput>());
+}
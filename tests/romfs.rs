@@ -0,0 +1,380 @@
+#![cfg(feature = "std")]
+
+mod common;
+
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext, LoadState};
+use bevy::prelude::*;
+use common::TempDir;
+use log::Level;
+use switchbrew_bevy::nro::{NroBuilder, NroSegments};
+use switchbrew_bevy::platform::{FsError, MockHal};
+use switchbrew_bevy::prelude::*;
+use switchbrew_bevy::romfs::{RomFs, RomFsBuilder, RomFsEntry, RomFsError};
+
+#[derive(Asset, TypePath)]
+struct Blob(Vec<u8>);
+
+#[derive(Default)]
+struct BlobLoader;
+
+impl AssetLoader for BlobLoader {
+    type Asset = Blob;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Blob, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(Blob(bytes))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["txt"]
+    }
+}
+
+/// Build an NRO with an empty program and an optional RomFS.
+fn nro(romfs: Option<Vec<u8>>) -> Vec<u8> {
+    let segments = NroSegments {
+        text: vec![0; 0x1000],
+        ..Default::default()
+    };
+    let builder = NroBuilder::new(segments);
+    match romfs {
+        Some(romfs) => builder.with_romfs(romfs).build(),
+        None => builder.build(),
+    }
+}
+
+/// HAL that refuses whole-file reads and counts the bytes of ranged ones.
+#[derive(Clone)]
+struct RangedReads {
+    inner: MockHal,
+    bytes: Arc<AtomicUsize>,
+}
+
+impl SwitchHal for RangedReads {
+    fn name(&self) -> &'static str {
+        "ranged"
+    }
+
+    fn now(&self) -> Duration {
+        self.inner.now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.inner.sleep(duration);
+    }
+
+    fn read_file(&self, path: &str) -> Result<Vec<u8>, FsError> {
+        Err(FsError::Other(format!("read all of {path}")))
+    }
+
+    fn read_file_range(&self, path: &str, offset: u64, len: usize) -> Result<Vec<u8>, FsError> {
+        let data = self.inner.read_file_range(path, offset, len)?;
+        self.bytes.fetch_add(data.len(), Ordering::SeqCst);
+        Ok(data)
+    }
+
+    fn write_file(&self, path: &str, data: &[u8]) -> Result<(), FsError> {
+        self.inner.write_file(path, data)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<(), FsError> {
+        self.inner.rename(from, to)
+    }
+
+    fn remove_file(&self, path: &str) -> Result<(), FsError> {
+        self.inner.remove_file(path)
+    }
+
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, FsError> {
+        self.inner.list_dir(path)
+    }
+
+    fn log(&self, level: Level, message: &str) {
+        self.inner.log(level, message);
+    }
+
+    fn executable_path(&self) -> Option<String> {
+        self.inner.executable_path()
+    }
+}
+
+/// Load a text asset through an app's asset server.
+fn load_blob(app: &mut App, path: &'static str) -> Vec<u8> {
+    let handle: Handle<Blob> = app.world().resource::<AssetServer>().load(path);
+    for _ in 0..1000 {
+        app.update();
+        let server = app.world().resource::<AssetServer>();
+        match server.load_state(&handle) {
+            LoadState::Loaded => break,
+            LoadState::Failed(error) => panic!("failed to load {path}: {error}"),
+            _ => std::thread::yield_now(),
+        }
+    }
+    let blobs = app.world().resource::<Assets<Blob>>();
+    blobs.get(&handle).expect("asset not loaded").0.clone()
+}
+
+fn asset_app(romfs: RomFsAssetPlugin) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(romfs)
+        .add_plugins(AssetPlugin::default())
+        .init_asset::<Blob>()
+        .init_asset_loader::<BlobLoader>();
+    app
+}
+
+#[test]
+fn round_trips_files_and_directories() {
+    let mut builder = RomFsBuilder::new();
+    // Enough files for the hash tables to need more buckets
+    for index in 0..40 {
+        builder
+            .add_file(
+                &format!("levels/{index:02}.txt"),
+                vec![index; index as usize],
+            )
+            .unwrap();
+    }
+    builder
+        .add_file("sprites/crab.png", b"crab".to_vec())
+        .unwrap();
+    builder
+        .add_file("/title.txt", b"Crab Crossing".to_vec())
+        .unwrap();
+    builder.add_dir("empty").unwrap();
+
+    let romfs = RomFs::new(builder.build()).unwrap();
+    assert_eq!(romfs.file("title.txt"), Some(&b"Crab Crossing"[..]));
+    assert_eq!(romfs.file("/sprites/crab.png"), Some(&b"crab"[..]));
+    for index in 0..40 {
+        let data = romfs.file(&format!("levels/{index:02}.txt")).unwrap();
+        assert_eq!(data, vec![index; index as usize]);
+    }
+    assert_eq!(romfs.file("sprites/missing.png"), None);
+    assert_eq!(romfs.file("sprites"), None);
+
+    assert!(romfs.is_dir("") && romfs.is_dir("empty") && romfs.is_dir("sprites"));
+    assert!(!romfs.is_dir("title.txt"));
+    assert_eq!(romfs.read_dir("empty"), Some(Vec::new()));
+    assert_eq!(
+        romfs.read_dir("").unwrap(),
+        [
+            RomFsEntry {
+                name: "empty".into(),
+                size: None
+            },
+            RomFsEntry {
+                name: "levels".into(),
+                size: None
+            },
+            RomFsEntry {
+                name: "sprites".into(),
+                size: None
+            },
+            RomFsEntry {
+                name: "title.txt".into(),
+                size: Some(13)
+            },
+        ]
+    );
+    assert_eq!(romfs.paths().len(), 42);
+}
+
+#[test]
+fn matches_console_layout() {
+    let mut builder = RomFsBuilder::new();
+    builder.add_file("a.txt", b"hello".to_vec()).unwrap();
+    let image = builder.build();
+
+    let u32_at = |offset: usize| u32::from_le_bytes(image[offset..offset + 4].try_into().unwrap());
+    let u64_at = |offset: usize| u64::from_le_bytes(image[offset..offset + 8].try_into().unwrap());
+
+    // Header: size, then offset and size of each table, then the data offset
+    let header: Vec<_> = (0..10).map(|index| u64_at(index * 8)).collect();
+    assert_eq!(
+        header,
+        [0x50, 0x208, 0xc, 0x214, 0x18, 0x22c, 0xc, 0x238, 0x28, 0x200]
+    );
+    assert_eq!(image.len(), 0x260);
+    assert_eq!(&image[0x200..0x205], b"hello");
+
+    // The root directory hashes into the first of three buckets
+    assert_eq!(
+        [u32_at(0x208), u32_at(0x20c), u32_at(0x210)],
+        [0, u32::MAX, u32::MAX]
+    );
+    // Root: parent, sibling, child directory, child file, next in bucket, name length
+    let root: Vec<_> = (0..6).map(|index| u32_at(0x214 + index * 4)).collect();
+    assert_eq!(root, [0, u32::MAX, u32::MAX, 0, u32::MAX, 0]);
+    // File: parent, sibling, data offset, size, next in bucket, name length, name
+    assert_eq!([u32_at(0x238), u32_at(0x23c)], [0, u32::MAX]);
+    assert_eq!([u64_at(0x240), u64_at(0x248)], [0, 5]);
+    assert_eq!([u32_at(0x250), u32_at(0x254)], [u32::MAX, 5]);
+    assert_eq!(&image[0x258..0x260], b"a.txt\0\0\0");
+}
+
+#[test]
+fn rejects_invalid_paths_and_images() {
+    let mut builder = RomFsBuilder::new();
+    builder.add_file("sprites/crab.png", Vec::new()).unwrap();
+    assert_eq!(
+        builder.add_file("sprites", Vec::new()).unwrap_err(),
+        RomFsError::Conflict("sprites".into())
+    );
+    assert_eq!(
+        builder
+            .add_file("sprites/crab.png/big.png", Vec::new())
+            .unwrap_err(),
+        RomFsError::Conflict("sprites/crab.png/big.png".into())
+    );
+    for path in ["", "sprites/", "a//b", "../secret", "./a"] {
+        assert_eq!(
+            builder.add_file(path, Vec::new()).unwrap_err(),
+            RomFsError::InvalidPath(path.into())
+        );
+    }
+
+    let image = builder.build();
+    assert_eq!(
+        RomFs::new(image[..0x40].to_vec()).unwrap_err(),
+        RomFsError::InvalidImage
+    );
+    assert_eq!(
+        RomFs::new(image[..image.len() - 1].to_vec()).unwrap_err(),
+        RomFsError::InvalidImage
+    );
+}
+
+#[test]
+fn cli_packs_directory_into_image() {
+    let dir = TempDir::new("romfs-cli");
+    let assets = dir.join("assets");
+    std::fs::create_dir_all(assets.join("sprites")).unwrap();
+    std::fs::write(assets.join("sprites/crab.txt"), "crab").unwrap();
+    std::fs::write(assets.join("title.txt"), "Crab Crossing").unwrap();
+    let image = dir.join("out/romfs.bin");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_cargo-switchbrew"))
+        .args(["switchbrew", "romfs"])
+        .arg(&assets)
+        .arg(&image)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Packed 2 files"));
+
+    let romfs = RomFs::new(std::fs::read(&image).unwrap()).unwrap();
+    assert_eq!(romfs.paths(), ["sprites/crab.txt", "title.txt"]);
+
+    // Assets load from the image instead of the assets directory
+    let mut app = asset_app(RomFsAssetPlugin::image(&image));
+    assert_eq!(load_blob(&mut app, "sprites/crab.txt"), b"crab");
+}
+
+#[test]
+fn cli_reports_usage_errors() {
+    let output = Command::new(env!("CARGO_BIN_EXE_cargo-switchbrew"))
+        .args(["romfs", "assets"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Usage: cargo switchbrew"));
+}
+
+#[test]
+fn loads_assets_from_named_sources() {
+    let mut builder = RomFsBuilder::new();
+    builder.add_file("title.txt", b"packed".to_vec()).unwrap();
+    let mut embedded = RomFsBuilder::new();
+    embedded
+        .add_file("sprites/crab.txt", b"embedded".to_vec())
+        .unwrap();
+    let mock = MockHal::new()
+        .with_file("sdmc:/switch/game.nro", nro(Some(embedded.build())))
+        .with_executable_path("sdmc:/switch/game.nro");
+
+    let mut app = App::new();
+    app.insert_resource(Hal::new(mock))
+        .add_plugins(MinimalPlugins)
+        .add_plugins(
+            RomFsAssetPlugin::new(RomFs::new(builder.build()).unwrap()).with_source_name("packed"),
+        )
+        .add_plugins(RomFsAssetPlugin::executable().with_source_name("romfs"))
+        .add_plugins(AssetPlugin::default())
+        .init_asset::<Blob>()
+        .init_asset_loader::<BlobLoader>();
+
+    assert_eq!(load_blob(&mut app, "packed://title.txt"), b"packed");
+    assert_eq!(load_blob(&mut app, "romfs://sprites/crab.txt"), b"embedded");
+}
+
+#[test]
+fn reads_romfs_of_running_nro() {
+    let mut builder = RomFsBuilder::new();
+    builder
+        .add_file("title.txt", b"Crab Crossing".to_vec())
+        .unwrap();
+    let mock = MockHal::new()
+        .with_file("sdmc:/switch/game.nro", nro(Some(builder.build())))
+        .with_file("sdmc:/switch/bare.nro", nro(None));
+
+    // Not started from an NRO
+    assert_eq!(
+        RomFs::from_executable(&mock).unwrap_err(),
+        FsError::Unsupported
+    );
+
+    let romfs = RomFs::from_executable(&mock.clone().with_executable_path("sdmc:/switch/game.nro"))
+        .unwrap();
+    assert_eq!(romfs.file("title.txt"), Some(&b"Crab Crossing"[..]));
+
+    let error = RomFs::from_executable(&mock.clone().with_executable_path("sdmc:/switch/bare.nro"))
+        .unwrap_err();
+    assert!(error.to_string().contains("has no RomFS"), "{error}");
+    assert_eq!(
+        RomFs::from_executable(&mock.with_executable_path("sdmc:/switch/missing.nro")).unwrap_err(),
+        FsError::NotFound
+    );
+}
+
+#[test]
+fn reads_only_the_romfs_of_the_nro() {
+    let mut builder = RomFsBuilder::new();
+    builder
+        .add_file("title.txt", b"Crab Crossing".to_vec())
+        .unwrap();
+    let image = builder.build();
+    let segments = NroSegments {
+        text: vec![0; 0x10000],
+        ..Default::default()
+    };
+    let hal = RangedReads {
+        inner: MockHal::new()
+            .with_file(
+                "sdmc:/switch/game.nro",
+                NroBuilder::new(segments).with_romfs(image.clone()).build(),
+            )
+            .with_executable_path("sdmc:/switch/game.nro"),
+        bytes: Arc::default(),
+    };
+
+    let romfs = RomFs::from_executable(&hal).unwrap();
+    assert_eq!(romfs.file("title.txt"), Some(&b"Crab Crossing"[..]));
+    // The NRO header, the asset header and the image
+    assert_eq!(hal.bytes.load(Ordering::SeqCst), 0x80 + 0x38 + image.len());
+}
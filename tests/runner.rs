@@ -0,0 +1,181 @@
+#![cfg(feature = "std")]
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use switchbrew_bevy::platform::MockHal;
+use switchbrew_bevy::prelude::*;
+
+/// Platform that delivers scripted messages, one batch per frame.
+#[derive(Default)]
+struct FakePlatform {
+    frames: VecDeque<Vec<AppletMessage>>,
+    frame: Vec<AppletMessage>,
+    ended_frames: usize,
+    waits: usize,
+}
+
+impl FakePlatform {
+    fn new(frames: impl IntoIterator<Item = Vec<AppletMessage>>) -> Self {
+        let mut frames: VecDeque<_> = frames.into_iter().collect();
+        let frame = frames.pop_front().unwrap_or_default();
+        Self {
+            frames,
+            frame,
+            ended_frames: 0,
+            waits: 0,
+        }
+    }
+}
+
+impl RunnerPlatform for FakePlatform {
+    fn poll_message(&mut self) -> Option<AppletMessage> {
+        (!self.frame.is_empty()).then(|| self.frame.remove(0))
+    }
+
+    fn wait_message(&mut self) -> Option<AppletMessage> {
+        self.waits += 1;
+        self.poll_message()
+    }
+
+    fn end_frame(&mut self) {
+        self.frame = self.frames.pop_front().unwrap_or_default();
+        self.ended_frames += 1;
+    }
+}
+
+#[derive(Resource, Default)]
+struct Updates(usize);
+
+#[derive(Resource, Default)]
+struct Received(Vec<AppletMessage>);
+
+fn count(mut updates: ResMut<Updates>) {
+    updates.0 += 1;
+}
+
+fn record(mut messages: MessageReader<AppletMessage>, mut received: ResMut<Received>) {
+    received.0.extend(messages.read().copied());
+}
+
+fn build_app() -> App {
+    let mut app = App::new();
+    app.add_plugins(SwitchPlugin::new(SwitchConfig::docked()))
+        .init_resource::<Updates>()
+        .init_resource::<Received>()
+        .add_systems(Update, (count, record));
+    app
+}
+
+// Expanding the entry point on desktop only needs `App::run`.
+#[allow(dead_code)]
+mod entry {
+    switchbrew_bevy::switch_main!(super::build_app);
+}
+
+#[test]
+fn updates_until_exit_requested() {
+    let mut app = build_app();
+    let mut runner = SwitchRunner::new(FakePlatform::new([
+        vec![],
+        vec![],
+        vec![AppletMessage::ExitRequested],
+    ]));
+
+    assert_eq!(runner.step(&mut app), None);
+    assert_eq!(runner.step(&mut app), None);
+    assert_eq!(runner.step(&mut app), Some(AppExit::Success));
+
+    assert_eq!(app.world().resource::<Updates>().0, 3);
+    assert_eq!(
+        app.world().resource::<Received>().0,
+        [AppletMessage::ExitRequested]
+    );
+    assert_eq!(runner.platform().ended_frames, 3);
+}
+
+#[test]
+fn pauses_while_out_of_focus() {
+    let mut app = build_app();
+    let mut runner = SwitchRunner::new(FakePlatform::new([
+        vec![],
+        vec![AppletMessage::FocusLost],
+        vec![],
+        vec![AppletMessage::Suspended],
+        vec![AppletMessage::FocusGained],
+        vec![],
+        vec![AppletMessage::Resumed],
+        vec![],
+    ]));
+
+    let mut updates = Vec::new();
+    for _ in 0..8 {
+        assert_eq!(runner.step(&mut app), None);
+        updates.push(app.world().resource::<Updates>().0);
+    }
+
+    // Frames that deliver a message still update; the others stay paused.
+    assert_eq!(updates, [1, 2, 2, 3, 4, 4, 5, 6]);
+    assert_eq!(runner.platform().waits, 2);
+    assert!(!runner.is_paused());
+    assert_eq!(app.world().resource::<Received>().0.len(), 4);
+}
+
+#[test]
+fn hal_sleeps_while_paused() {
+    let mock = MockHal::new();
+    let mut app = build_app();
+    let mut runner = SwitchRunner::new(Hal::new(mock.clone()));
+
+    runner.step(&mut app);
+    assert_eq!(mock.now(), Duration::ZERO);
+
+    mock.push_message(AppletMessage::FocusLost);
+    runner.step(&mut app);
+    runner.step(&mut app);
+    assert!(mock.now() >= Duration::from_millis(16));
+    assert_eq!(app.world().resource::<Updates>().0, 2);
+
+    // Focus coming back resumes updates
+    mock.push_message(AppletMessage::FocusGained);
+    runner.step(&mut app);
+    assert!(!runner.is_paused());
+    assert_eq!(app.world().resource::<Updates>().0, 3);
+}
+
+#[test]
+fn operation_mode_change_updates_display() {
+    let mut app = build_app();
+    let mut runner = SwitchRunner::new(FakePlatform::new([
+        vec![],
+        vec![AppletMessage::OperationModeChanged(DisplayMode::Handheld)],
+    ]));
+
+    runner.step(&mut app);
+    runner.step(&mut app);
+
+    let config = app.world().resource::<SwitchConfig>();
+    assert_eq!(config.display_mode, DisplayMode::Handheld);
+    assert_eq!(config.resolution, (1280, 720));
+    let display = app.world().resource::<SwitchDisplay>();
+    assert_eq!(display.mode, DisplayMode::Handheld);
+    assert_eq!(display.resolution, (1280, 720));
+}
+
+#[test]
+fn run_returns_app_exit_code() {
+    let mut app = build_app();
+    app.add_systems(
+        Update,
+        |updates: Res<Updates>, mut exit: MessageWriter<AppExit>| {
+            if updates.0 == 5 {
+                exit.write(AppExit::from_code(3));
+            }
+        },
+    );
+
+    let exit = SwitchRunner::new(FakePlatform::default()).run(app);
+    assert_eq!(exit, AppExit::from_code(3));
+    assert_eq!(switchbrew_bevy::runner::exit_code(exit), 3);
+}
@@ -0,0 +1,58 @@
+use bevy::prelude::*;
+use switchbrew_bevy::prelude::*;
+
+fn assert_rect(rect: Rect, min: (f32, f32), max: (f32, f32)) {
+    let expected = Rect::new(min.0, min.1, max.0, max.1);
+    assert!(
+        rect.min.abs_diff_eq(expected.min, 1e-3) && rect.max.abs_diff_eq(expected.max, 1e-3),
+        "{rect:?} != {expected:?}"
+    );
+}
+
+#[test]
+fn title_safe_keeps_five_percent_margins() {
+    assert!((SafeArea::Title.margin() - 0.05).abs() < 1e-6);
+
+    let docked = SwitchDisplay::default();
+    assert_rect(docked.title_safe(), (96.0, 54.0), (1824.0, 1026.0));
+    let handheld = SwitchDisplay::handheld();
+    assert_rect(handheld.title_safe(), (64.0, 36.0), (1216.0, 684.0));
+}
+
+#[test]
+fn action_safe_keeps_three_and_a_half_percent_margins() {
+    assert!((SafeArea::Action.margin() - 0.035).abs() < 1e-6);
+
+    let docked = SwitchDisplay::default();
+    assert_rect(docked.action_safe(), (67.2, 37.8), (1852.8, 1042.2));
+    let handheld = SwitchDisplay::handheld();
+    assert_rect(handheld.action_safe(), (44.8, 25.2), (1235.2, 694.8));
+}
+
+#[test]
+fn custom_areas_are_clamped() {
+    assert_rect(
+        SafeArea::Custom(80.0).normalized_rect(),
+        (0.1, 0.1),
+        (0.9, 0.9),
+    );
+    assert_eq!(SafeArea::Custom(120.0).margin(), 0.0);
+    assert_eq!(SafeArea::Custom(-5.0).margin(), 0.5);
+    assert_eq!(
+        SafeArea::Full.normalized_rect(),
+        Rect::new(0.0, 0.0, 1.0, 1.0)
+    );
+}
+
+#[test]
+fn handheld_uses_the_full_screen() {
+    let display = SwitchDisplay::handheld().with_safe_area(SafeArea::Action);
+    assert_eq!(display.active_safe_area(), SafeArea::Full);
+    let docked = SwitchDisplay::default().with_safe_area(SafeArea::Action);
+    assert_eq!(docked.active_safe_area(), SafeArea::Action);
+    assert_rect(
+        docked.safe_rect_normalized(SafeArea::Title),
+        (0.05, 0.05),
+        (0.95, 0.95),
+    );
+}
@@ -0,0 +1,173 @@
+#![cfg(feature = "save")]
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use switchbrew_bevy::prelude::*;
+use switchbrew_bevy::save::{
+    DesktopSaveBackend, MemorySaveBackend, Migration, SaveBackend, SaveError, SaveValue,
+};
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Progress {
+    level: u32,
+}
+
+impl SaveData for Progress {
+    const SLOT: &'static str = "progress";
+}
+
+/// First version of [`Profile`], saved to the same slot.
+#[derive(Debug, Serialize, Deserialize)]
+struct ProfileV1 {
+    coins: u32,
+}
+
+impl SaveData for ProfileV1 {
+    const SLOT: &'static str = "profile";
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Profile {
+    gold: u32,
+    name: String,
+}
+
+impl SaveData for Profile {
+    const SLOT: &'static str = "profile";
+    const MIGRATIONS: &'static [Migration] = &[rename_coins, add_name];
+}
+
+fn rename_coins(data: &mut SaveValue) -> Result<(), String> {
+    let data = data.as_object_mut().ok_or("expected an object")?;
+    let coins = data.remove("coins").ok_or("missing coins")?;
+    data.insert("gold".into(), coins);
+    Ok(())
+}
+
+fn add_name(data: &mut SaveValue) -> Result<(), String> {
+    data["name"] = "Player".into();
+    Ok(())
+}
+
+const OTHER_USER: UserId = UserId(2);
+
+#[test]
+fn stores_are_staged_until_commit() {
+    let backend = MemorySaveBackend::new();
+    let mut save = SwitchSave::new(backend.clone());
+
+    save.store(UserId::DEFAULT, &Progress { level: 3 }).unwrap();
+    assert!(save.has_pending());
+    assert_eq!(
+        save.load::<Progress>(UserId::DEFAULT).unwrap(),
+        Some(Progress { level: 3 })
+    );
+    assert_eq!(backend.read(UserId::DEFAULT, "progress").unwrap(), None);
+
+    save.commit().unwrap();
+    assert!(!save.has_pending());
+    assert!(backend.read(UserId::DEFAULT, "progress").unwrap().is_some());
+
+    save.store(UserId::DEFAULT, &Progress { level: 4 }).unwrap();
+    save.discard();
+    assert_eq!(
+        save.load_or_default::<Progress>(UserId::DEFAULT).unwrap(),
+        Progress { level: 3 }
+    );
+}
+
+#[test]
+fn users_have_separate_slots() {
+    let mut save = SwitchSave::new(MemorySaveBackend::new());
+    save.store(UserId::DEFAULT, &Progress { level: 1 }).unwrap();
+    save.store(OTHER_USER, &ProfileV1 { coins: 5 }).unwrap();
+    save.commit().unwrap();
+
+    assert_eq!(save.load::<Progress>(OTHER_USER).unwrap(), None);
+    assert_eq!(save.slots(UserId::DEFAULT).unwrap(), ["progress"]);
+    assert_eq!(save.slots(OTHER_USER).unwrap(), ["profile"]);
+
+    save.delete::<Progress>(UserId::DEFAULT).unwrap();
+    assert!(save.slots(UserId::DEFAULT).unwrap().is_empty());
+    save.commit().unwrap();
+    assert_eq!(save.load::<Progress>(UserId::DEFAULT).unwrap(), None);
+}
+
+#[test]
+fn old_versions_are_migrated() {
+    let mut save = SwitchSave::new(MemorySaveBackend::new());
+    save.store(UserId::DEFAULT, &ProfileV1 { coins: 40 })
+        .unwrap();
+    save.commit().unwrap();
+
+    assert_eq!(
+        save.load::<Profile>(UserId::DEFAULT).unwrap(),
+        Some(Profile {
+            gold: 40,
+            name: "Player".into(),
+        })
+    );
+}
+
+#[test]
+fn newer_versions_are_rejected() {
+    let mut save = SwitchSave::new(MemorySaveBackend::new());
+    save.store(
+        UserId::DEFAULT,
+        &Profile {
+            gold: 1,
+            name: "Link".into(),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        save.load::<ProfileV1>(UserId::DEFAULT).unwrap_err(),
+        SaveError::UnsupportedVersion {
+            slot: "profile",
+            version: 3,
+            current: 1,
+        }
+    );
+}
+
+#[test]
+fn desktop_backend_replaces_files() {
+    let root = std::env::temp_dir().join(format!("switchbrew-save-{}", std::process::id()));
+    let backend = DesktopSaveBackend::new(&root);
+    let mut save = SwitchSave::new(backend.clone());
+
+    save.store(UserId::DEFAULT, &Progress { level: 7 }).unwrap();
+    save.commit().unwrap();
+
+    let user_dir = root.join(UserId::DEFAULT.to_string());
+    let files: Vec<_> = std::fs::read_dir(&user_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(files, ["progress.sav"]);
+    assert_eq!(
+        SwitchSave::new(backend)
+            .load::<Progress>(UserId::DEFAULT)
+            .unwrap(),
+        Some(Progress { level: 7 })
+    );
+
+    save.delete::<Progress>(UserId::DEFAULT).unwrap();
+    save.commit().unwrap();
+    assert!(save.slots(UserId::DEFAULT).unwrap().is_empty());
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn plugin_inserts_save_resource() {
+    let backend = MemorySaveBackend::new();
+    let mut app = App::new();
+    app.add_plugins(SwitchSavePlugin::new(backend.clone()));
+
+    let mut save = app.world_mut().resource_mut::<SwitchSave>();
+    save.store(UserId::DEFAULT, &Progress { level: 2 }).unwrap();
+    save.commit().unwrap();
+    assert_eq!(backend.slots(UserId::DEFAULT).unwrap(), ["progress"]);
+}
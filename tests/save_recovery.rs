@@ -0,0 +1,328 @@
+#![cfg(feature = "save")]
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::Level;
+use serde::{Deserialize, Serialize};
+use switchbrew_bevy::platform::{FsError, MockHal};
+use switchbrew_bevy::prelude::*;
+use switchbrew_bevy::save::{JournaledSaveBackend, SaveBackend, SaveError};
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Progress {
+    level: u32,
+}
+
+impl SaveData for Progress {
+    const SLOT: &'static str = "progress";
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Settings {
+    volume: u32,
+}
+
+impl SaveData for Settings {
+    const SLOT: &'static str = "settings";
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Fault {
+    /// Power is lost before the write step happens
+    Crash,
+    /// Power is lost halfway through writing a file
+    Truncate,
+    /// A written file silently gets a flipped byte
+    Corrupt,
+}
+
+/// Filesystem that injects a fault at one write step.
+///
+/// Write steps are file writes, renames, removals and syncs, counted from
+/// zero.
+/// Clones share their state.
+#[derive(Clone)]
+struct FaultyFs {
+    inner: MockHal,
+    fail_at: usize,
+    fault: Fault,
+    steps: Arc<AtomicUsize>,
+    powered_off: Arc<AtomicBool>,
+    reads: Arc<Mutex<Vec<String>>>,
+}
+
+impl FaultyFs {
+    fn new(inner: MockHal, fail_at: usize, fault: Fault) -> Self {
+        Self {
+            inner,
+            fail_at,
+            fault,
+            steps: Arc::default(),
+            powered_off: Arc::default(),
+            reads: Arc::default(),
+        }
+    }
+
+    /// Number of reads of the files whose path ends with `suffix`.
+    fn reads(&self, suffix: &str) -> usize {
+        let reads = self.reads.lock().unwrap();
+        reads.iter().filter(|path| path.ends_with(suffix)).count()
+    }
+
+    /// Number of write steps so far.
+    fn steps(&self) -> usize {
+        self.steps.load(Ordering::SeqCst)
+    }
+
+    /// Count a write step and report whether it is the faulty one.
+    fn step(&self) -> Result<bool, FsError> {
+        if self.powered_off.load(Ordering::SeqCst) {
+            return Err(FsError::Other("powered off".into()));
+        }
+        let faulty = self.steps.fetch_add(1, Ordering::SeqCst) == self.fail_at;
+        if faulty && !matches!(self.fault, Fault::Corrupt) {
+            self.powered_off.store(true, Ordering::SeqCst);
+        }
+        Ok(faulty)
+    }
+}
+
+fn power_loss() -> Result<(), FsError> {
+    Err(FsError::Other("power lost".into()))
+}
+
+impl SwitchHal for FaultyFs {
+    fn name(&self) -> &'static str {
+        "faulty"
+    }
+
+    fn now(&self) -> Duration {
+        self.inner.now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.inner.sleep(duration);
+    }
+
+    fn read_file(&self, path: &str) -> Result<Vec<u8>, FsError> {
+        self.reads.lock().unwrap().push(path.into());
+        self.inner.read_file(path)
+    }
+
+    fn write_file(&self, path: &str, data: &[u8]) -> Result<(), FsError> {
+        if !self.step()? {
+            return self.inner.write_file(path, data);
+        }
+        match self.fault {
+            Fault::Crash => power_loss(),
+            Fault::Truncate => {
+                self.inner.write_file(path, &data[..data.len() / 2])?;
+                power_loss()
+            }
+            Fault::Corrupt => {
+                let mut data = data.to_vec();
+                let last = data.len() - 1;
+                data[last] ^= 0x40;
+                self.inner.write_file(path, &data)
+            }
+        }
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<(), FsError> {
+        match self.step()? {
+            true if !matches!(self.fault, Fault::Corrupt) => power_loss(),
+            _ => self.inner.rename(from, to),
+        }
+    }
+
+    fn remove_file(&self, path: &str) -> Result<(), FsError> {
+        match self.step()? {
+            true if !matches!(self.fault, Fault::Corrupt) => power_loss(),
+            _ => self.inner.remove_file(path),
+        }
+    }
+
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, FsError> {
+        self.inner.list_dir(path)
+    }
+
+    fn sync(&self, path: &str) -> Result<(), FsError> {
+        match self.step()? {
+            true if !matches!(self.fault, Fault::Corrupt) => power_loss(),
+            _ => self.inner.sync(path),
+        }
+    }
+
+    fn log(&self, level: Level, message: &str) {
+        self.inner.log(level, message);
+    }
+}
+
+fn backend(mock: &MockHal) -> JournaledSaveBackend<MockHal> {
+    JournaledSaveBackend::new(mock.clone(), "saves")
+}
+
+/// Commit a value to both slots.
+fn store(backend: impl SaveBackend, value: u32) -> Result<(), SaveError> {
+    let mut save = SwitchSave::new(backend);
+    save.store(UserId::DEFAULT, &Progress { level: value })?;
+    save.store(UserId::DEFAULT, &Settings { volume: value })?;
+    save.commit()
+}
+
+/// Load both slots after a reboot.
+fn load(mock: &MockHal) -> (u32, u32) {
+    let save = SwitchSave::new(backend(mock));
+    let progress: Progress = save.load(UserId::DEFAULT).unwrap().unwrap();
+    let settings: Settings = save.load(UserId::DEFAULT).unwrap().unwrap();
+    (progress.level, settings.volume)
+}
+
+/// Commit values 1 and 2, then commit 3 with a fault at `fail_at`.
+///
+/// Returns the filesystem, the number of write steps of the last commit and
+/// its result.
+fn commit_with_fault(fail_at: usize, fault: Fault) -> (MockHal, usize, Result<(), SaveError>) {
+    let mock = MockHal::new();
+    store(backend(&mock), 1).unwrap();
+    store(backend(&mock), 2).unwrap();
+
+    let fs = FaultyFs::new(mock.clone(), fail_at, fault);
+    let result = store(JournaledSaveBackend::new(fs.clone(), "saves"), 3);
+    (mock, fs.steps(), result)
+}
+
+#[test]
+fn commit_without_faults_takes_several_steps() {
+    let (mock, steps, result) = commit_with_fault(usize::MAX, Fault::Crash);
+    result.unwrap();
+    assert_eq!(load(&mock), (3, 3));
+    // Two temporary files, the journal, two renames per slot, the cleanup and
+    // a sync after each stage
+    assert!(steps >= 12, "only {steps} write steps");
+}
+
+#[test]
+fn commit_reads_each_slot_once() {
+    let mock = MockHal::new();
+    store(backend(&mock), 1).unwrap();
+    store(backend(&mock), 2).unwrap();
+
+    let fs = FaultyFs::new(mock.clone(), usize::MAX, Fault::Crash);
+    store(JournaledSaveBackend::new(fs.clone(), "saves"), 3).unwrap();
+    assert_eq!(fs.reads("progress.sav"), 1);
+    assert_eq!(fs.reads("settings.sav"), 1);
+    assert_eq!(fs.reads(".sav.1"), 0);
+    assert_eq!(load(&mock), (3, 3));
+}
+
+#[test]
+fn power_loss_at_every_step_keeps_old_or_new_save() {
+    let (_, steps, _) = commit_with_fault(usize::MAX, Fault::Crash);
+
+    for fault in [Fault::Crash, Fault::Truncate] {
+        for fail_at in 0..steps {
+            let (mock, _, _) = commit_with_fault(fail_at, fault);
+
+            // Both slots come from the same commit
+            let loaded = load(&mock);
+            assert!(
+                loaded == (2, 2) || loaded == (3, 3),
+                "{fault:?} at step {fail_at} loaded {loaded:?}"
+            );
+            for path in mock.paths() {
+                assert!(
+                    !path.ends_with(".tmp") && !path.ends_with("journal"),
+                    "{fault:?} at step {fail_at} left {path}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn corrupted_writes_are_detected() {
+    let (_, steps, _) = commit_with_fault(usize::MAX, Fault::Crash);
+
+    for fail_at in 0..steps {
+        let (mock, _, result) = commit_with_fault(fail_at, Fault::Corrupt);
+
+        // A corrupt write fails the commit and leaves the old save
+        let loaded = load(&mock);
+        match result {
+            Ok(()) => assert_eq!(loaded, (3, 3), "corruption at step {fail_at}"),
+            Err(error) => {
+                assert_eq!(loaded, (2, 2), "corruption at step {fail_at}: {error}");
+                assert!(error.to_string().contains("corrupt"), "{error}");
+            }
+        }
+        for path in mock.paths() {
+            assert!(
+                !path.ends_with(".tmp") && !path.ends_with("journal"),
+                "corruption at step {fail_at} left {path}"
+            );
+        }
+    }
+}
+
+#[test]
+fn corrupt_slot_rolls_back_to_backup() {
+    let mock = MockHal::new();
+    for value in 1..=3 {
+        store(backend(&mock), value).unwrap();
+    }
+
+    let path = format!("saves/{}/progress.sav", UserId::DEFAULT);
+    let mut data = mock.read_file(&path).unwrap();
+    data.truncate(data.len() - 3);
+    mock.write_file(&path, &data).unwrap();
+
+    assert_eq!(load(&mock), (2, 3));
+    assert!(mock
+        .logs()
+        .iter()
+        .any(|(level, line)| *level == Level::Warn && line.contains("rolled back")));
+
+    // The next commit drops the corrupt file instead of backing it up
+    store(backend(&mock), 4).unwrap();
+    let backups = backend(&mock).backups(UserId::DEFAULT, "progress").unwrap();
+    assert_eq!(backups.len(), 2);
+    assert!(String::from_utf8_lossy(&backups[0]).contains("\"level\":2"));
+}
+
+#[test]
+fn keeps_configured_number_of_backups() {
+    let mock = MockHal::new();
+    let backend = JournaledSaveBackend::new(mock.clone(), "saves").with_backups(3);
+    for value in 1..=5 {
+        store(backend.clone(), value).unwrap();
+    }
+
+    let mut files: Vec<_> = mock
+        .paths()
+        .into_iter()
+        .filter(|path| path.contains("progress"))
+        .map(|path| path.rsplit('/').next().unwrap().to_string())
+        .collect();
+    files.sort();
+    assert_eq!(
+        files,
+        [
+            "progress.sav",
+            "progress.sav.1",
+            "progress.sav.2",
+            "progress.sav.3"
+        ]
+    );
+
+    let levels: Vec<_> = backend
+        .backups(UserId::DEFAULT, "progress")
+        .unwrap()
+        .iter()
+        .map(|data| String::from_utf8_lossy(data).into_owned())
+        .collect();
+    assert!(levels[0].contains("\"level\":4"));
+    assert!(levels[2].contains("\"level\":2"));
+}
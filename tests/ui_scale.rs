@@ -0,0 +1,47 @@
+use switchbrew_bevy::platform::DisplayMode;
+use switchbrew_bevy::prelude::*;
+
+fn assert_close(actual: f32, expected: f32) {
+    assert!((actual - expected).abs() < 1e-5, "{actual} != {expected}");
+}
+
+#[test]
+fn scales_by_height_from_the_reference_resolution() {
+    let policy = SwitchUiScale::default();
+    assert_close(policy.scale_for(DisplayMode::Docked, (1920, 1080)), 1.0);
+    assert_close(
+        policy.scale_for(DisplayMode::Docked, (1280, 720)),
+        2.0 / 3.0,
+    );
+    // Ultrawide windows keep the height-based scale
+    assert_close(policy.scale_for(DisplayMode::Docked, (2560, 1080)), 1.0);
+}
+
+#[test]
+fn boosts_the_built_in_screen() {
+    let policy = SwitchUiScale::default();
+    assert_close(
+        policy.scale_for(DisplayMode::Handheld, (1280, 720)),
+        720.0 / 1080.0 * 1.25,
+    );
+    assert_close(
+        policy.scale_for(DisplayMode::Tabletop, (1280, 720)),
+        policy.scale_for(DisplayMode::Handheld, (1280, 720)),
+    );
+}
+
+#[test]
+fn custom_policies() {
+    let policy = SwitchUiScale::new((1280, 720))
+        .with_docked(0.9)
+        .with_handheld(1.5);
+    assert_close(policy.scale_for(DisplayMode::Docked, (1920, 1080)), 1.35);
+    assert_close(policy.scale_for(DisplayMode::Handheld, (1280, 720)), 1.5);
+
+    // A zero reference height doesn't divide by zero
+    let degenerate = SwitchUiScale::new((0, 0));
+    assert_close(
+        degenerate.scale_for(DisplayMode::Docked, (1920, 1080)),
+        1080.0,
+    );
+}
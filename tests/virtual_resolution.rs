@@ -0,0 +1,108 @@
+#![cfg(feature = "std")]
+
+use bevy::math::UVec2;
+use switchbrew_bevy::camera::{ScaleMode, VirtualResolution};
+use switchbrew_bevy::window::SwitchDisplay;
+
+#[test]
+fn fit_matching_aspect_fills_target() {
+    let layout = ScaleMode::Fit
+        .layout(UVec2::new(640, 360), UVec2::new(1920, 1080))
+        .unwrap();
+
+    assert_eq!(layout.position, UVec2::ZERO);
+    assert_eq!(layout.size, UVec2::new(1920, 1080));
+    assert_eq!(layout.scale, 3.0);
+    assert!(!layout.has_bars());
+}
+
+#[test]
+fn fit_wider_target_pillarboxes() {
+    let layout = ScaleMode::Fit
+        .layout(UVec2::new(1920, 1080), UVec2::new(2560, 1080))
+        .unwrap();
+
+    assert_eq!(layout.size, UVec2::new(1920, 1080));
+    assert_eq!(layout.bars(), UVec2::new(320, 0));
+    assert_eq!(layout.scale, 1.0);
+}
+
+#[test]
+fn fit_taller_target_letterboxes() {
+    let layout = ScaleMode::Fit
+        .layout(UVec2::new(1280, 720), UVec2::new(1280, 1024))
+        .unwrap();
+
+    assert_eq!(layout.size, UVec2::new(1280, 720));
+    assert_eq!(layout.bars(), UVec2::new(0, 152));
+}
+
+#[test]
+fn integer_rounds_scale_down() {
+    let layout = ScaleMode::Integer
+        .layout(UVec2::new(640, 360), UVec2::new(1600, 1000))
+        .unwrap();
+
+    assert_eq!(layout.scale, 2.0);
+    assert_eq!(layout.size, UVec2::new(1280, 720));
+    assert_eq!(layout.position, UVec2::new(160, 140));
+    assert_eq!(layout.visible_size().as_uvec2(), UVec2::new(640, 360));
+}
+
+#[test]
+fn integer_falls_back_to_fit_when_target_is_smaller() {
+    let layout = ScaleMode::Integer
+        .layout(UVec2::new(1920, 1080), UVec2::new(960, 540))
+        .unwrap();
+
+    assert_eq!(layout.scale, 0.5);
+    assert_eq!(layout.size, UVec2::new(960, 540));
+}
+
+#[test]
+fn fill_covers_target_and_crops() {
+    let layout = ScaleMode::Fill
+        .layout(UVec2::new(1920, 1080), UVec2::new(1280, 1024))
+        .unwrap();
+
+    assert_eq!(layout.position, UVec2::ZERO);
+    assert_eq!(layout.size, UVec2::new(1280, 1024));
+    assert!(!layout.has_bars());
+
+    let visible = layout.visible_size();
+    assert!(visible.x < 1920.0);
+    assert!((visible.y - 1080.0).abs() < 0.01);
+}
+
+#[test]
+fn empty_sizes_have_no_layout() {
+    assert!(
+        ScaleMode::Fit
+            .layout(UVec2::new(640, 360), UVec2::ZERO)
+            .is_none()
+    );
+    assert!(
+        ScaleMode::Fill
+            .layout(UVec2::ZERO, UVec2::new(1920, 1080))
+            .is_none()
+    );
+}
+
+#[test]
+fn display_resolution_follows_switch_display() {
+    let virtual_resolution = VirtualResolution::display();
+
+    assert_eq!(
+        virtual_resolution.size(&SwitchDisplay::default()),
+        UVec2::new(1920, 1080)
+    );
+    assert_eq!(
+        virtual_resolution.size(&SwitchDisplay::handheld()),
+        UVec2::new(1280, 720)
+    );
+
+    let layout = virtual_resolution
+        .layout(&SwitchDisplay::handheld(), UVec2::new(1920, 1080))
+        .unwrap();
+    assert_eq!(layout.scale, 1.5);
+}